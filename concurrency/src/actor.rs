@@ -0,0 +1,479 @@
+//! 基于线程的 Actor 模型
+//!
+//! 每个 Actor 独占一个线程和一个邮箱（[`crate::channel`] 的无界通道），
+//! 外界只能通过 [`ActorRef`] 向它发送消息，Actor 的状态永远不会被多个线程同时访问，
+//! 因此 Actor 内部不需要任何锁。
+//!
+//! - 请求/应答：消息中携带一个 [`ReplyTo`]，配合 [`ActorRef::ask`] 使用
+//! - 监督重启：处理消息时 panic，按 [`RestartPolicy`] 用工厂函数重建 Actor 后继续处理后续消息
+//! - 优雅关闭：[`ActorRef::stop`] 之前发出的消息都会被处理完，然后调用 [`Actor::stopped`]
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::channel::{self, Receiver, Sender};
+
+pub trait Actor: Send + 'static {
+    type Message: Send + 'static;
+
+    /// 处理一条消息
+    fn handle(&mut self, msg: Self::Message, ctx: &mut Context);
+
+    /// 线程启动（以及每次重启）后、处理第一条消息前调用
+    fn started(&mut self, _ctx: &mut Context) {}
+
+    /// Actor 正常结束时调用（panic 后不会调用）
+    fn stopped(&mut self) {}
+}
+
+/// 处理消息时可用的上下文
+#[derive(Debug, Default)]
+pub struct Context {
+    stopping: bool,
+    restarts: usize,
+}
+
+impl Context {
+    /// 处理完当前消息后停止，邮箱中剩余的消息会被丢弃
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+
+    /// 到目前为止被监督者重启的次数
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+}
+
+/// 处理消息 panic 后的重启策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// 不重启，Actor 线程直接结束
+    Never,
+    /// 在 `within` 时间窗口内最多重启 `max_restarts` 次，超过则放弃（类似 Erlang 的重启强度）
+    Limited {
+        max_restarts: usize,
+        within: Duration,
+    },
+}
+
+/// Actor 线程结束的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitReason {
+    /// 收到 stop 请求或调用了 `Context::stop`
+    Stopped,
+    /// 所有 ActorRef 都已释放，邮箱断开
+    Disconnected,
+    /// panic 且不再重启，附带 panic 信息
+    Panicked(String),
+}
+
+enum Envelope<M> {
+    Message(M),
+    Stop,
+}
+
+/// 向 Actor 发送消息的句柄，可以任意 clone
+pub struct ActorRef<A: Actor> {
+    tx: Sender<Envelope<A::Message>>,
+}
+
+impl<A: Actor> Clone for ActorRef<A> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<A: Actor> fmt::Debug for ActorRef<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("ActorRef { .. }")
+    }
+}
+
+impl<A: Actor> ActorRef<A> {
+    /// 发送消息，不等待处理。Actor 已结束时消息原样返回
+    pub fn send(&self, msg: A::Message) -> Result<(), SendError<A::Message>> {
+        self.tx.send(Envelope::Message(msg)).map_err(|e| match e.0 {
+            Envelope::Message(m) => SendError(m),
+            Envelope::Stop => unreachable!(),
+        })
+    }
+
+    /// 请求/应答：`make` 用传入的 ReplyTo 构造一条消息，然后阻塞等待 Actor 回复
+    ///
+    /// Actor 已结束，或处理该消息时 panic / 丢弃了 ReplyTo，都会返回错误。
+    pub fn ask<R, F>(&self, make: F) -> Result<R, AskError>
+    where
+        R: Send + 'static,
+        F: FnOnce(ReplyTo<R>) -> A::Message,
+    {
+        let (reply_to, rx) = reply_channel();
+        self.send(make(reply_to)).map_err(|_| AskError::Closed)?;
+        rx.recv().map_err(|RecvError| AskError::NoReply)
+    }
+
+    pub fn ask_timeout<R, F>(&self, make: F, timeout: Duration) -> Result<R, AskError>
+    where
+        R: Send + 'static,
+        F: FnOnce(ReplyTo<R>) -> A::Message,
+    {
+        let (reply_to, rx) = reply_channel();
+        self.send(make(reply_to)).map_err(|_| AskError::Closed)?;
+        rx.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => AskError::Timeout,
+            RecvTimeoutError::Disconnected => AskError::NoReply,
+        })
+    }
+
+    /// 请求优雅关闭：在此之前发出的消息都会被处理完
+    pub fn stop(&self) {
+        // Actor 已经结束也无所谓
+        let _ = self.tx.send(Envelope::Stop);
+    }
+}
+
+/// 一次性的应答通道（oneshot），只能回复一次
+pub struct ReplyTo<R> {
+    tx: Sender<R>,
+}
+
+impl<R> ReplyTo<R> {
+    /// 回复请求方。请求方已放弃等待时返回 false
+    pub fn send(self, value: R) -> bool {
+        self.tx.send(value).is_ok()
+    }
+}
+
+impl<R> fmt::Debug for ReplyTo<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("ReplyTo { .. }")
+    }
+}
+
+fn reply_channel<R>() -> (ReplyTo<R>, Receiver<R>) {
+    let (tx, rx) = channel::bounded(1);
+    (ReplyTo { tx }, rx)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AskError {
+    /// Actor 已经结束，消息没有送达
+    Closed,
+    /// 消息已送达，但 Actor 没有回复就丢弃了 ReplyTo（通常是处理时 panic）
+    NoReply,
+    /// 在超时时间内没有收到回复
+    Timeout,
+}
+
+impl fmt::Display for AskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AskError::Closed => f.write_str("actor has stopped"),
+            AskError::NoReply => f.write_str("actor dropped the reply channel"),
+            AskError::Timeout => f.write_str("timed out waiting for reply"),
+        }
+    }
+}
+
+impl std::error::Error for AskError {}
+
+/// 正在运行的 Actor：持有线程句柄，可以等待它结束
+pub struct ActorHandle<A: Actor> {
+    actor_ref: ActorRef<A>,
+    thread: JoinHandle<ExitReason>,
+}
+
+impl<A: Actor> ActorHandle<A> {
+    pub fn actor_ref(&self) -> ActorRef<A> {
+        self.actor_ref.clone()
+    }
+
+    /// 释放自己持有的 ActorRef 并等待线程结束。
+    /// 只要外面还有 ActorRef 存活且没有调用 stop，这里就会一直等待。
+    pub fn join(self) -> ExitReason {
+        drop(self.actor_ref);
+        self.thread
+            .join()
+            .unwrap_or_else(|e| ExitReason::Panicked(panic_message(&*e)))
+    }
+
+    /// 优雅关闭：发送 stop 并等待已入队的消息处理完毕
+    pub fn shutdown(self) -> ExitReason {
+        self.actor_ref.stop();
+        self.join()
+    }
+}
+
+/// 启动一个不受监督的 Actor：处理消息 panic 时线程直接结束
+pub fn spawn<A: Actor>(actor: A) -> ActorHandle<A> {
+    let mut actor = Some(actor);
+    spawn_supervised(
+        move || actor.take().expect("RestartPolicy::Never 不会重建 Actor"),
+        RestartPolicy::Never,
+    )
+}
+
+/// 启动一个受监督的 Actor：`factory` 用来创建初始实例，以及在 panic 后重建实例
+pub fn spawn_supervised<A, F>(factory: F, policy: RestartPolicy) -> ActorHandle<A>
+where
+    A: Actor,
+    F: FnMut() -> A + Send + 'static,
+{
+    let (tx, rx) = channel::unbounded();
+    let thread = thread::spawn(move || run_actor(factory, policy, rx));
+    ActorHandle {
+        actor_ref: ActorRef { tx },
+        thread,
+    }
+}
+
+fn run_actor<A, F>(
+    mut factory: F,
+    policy: RestartPolicy,
+    rx: Receiver<Envelope<A::Message>>,
+) -> ExitReason
+where
+    A: Actor,
+    F: FnMut() -> A,
+{
+    let mut ctx = Context::default();
+    // 记录最近几次重启的时间，用于判断是否超过重启强度
+    let mut restart_times: Vec<Instant> = Vec::new();
+
+    let mut actor = factory();
+    // started 中 panic 同样交给监督逻辑处理
+    let mut pending_start = true;
+
+    loop {
+        let step = panic::catch_unwind(AssertUnwindSafe(|| {
+            if pending_start {
+                pending_start = false;
+                actor.started(&mut ctx);
+                if ctx.stopping {
+                    return ExitReason::Stopped;
+                }
+            }
+            // 在同一个 catch_unwind 中连续处理消息，直到结束或 panic
+            loop {
+                match rx.recv() {
+                    Ok(Envelope::Message(msg)) => {
+                        actor.handle(msg, &mut ctx);
+                        if ctx.stopping {
+                            return ExitReason::Stopped;
+                        }
+                    }
+                    Ok(Envelope::Stop) => return ExitReason::Stopped,
+                    Err(RecvError) => return ExitReason::Disconnected,
+                }
+            }
+        }));
+
+        match step {
+            Ok(reason) => {
+                actor.stopped();
+                return reason;
+            }
+            Err(payload) => {
+                let message = panic_message(&*payload);
+                let (max_restarts, within) = match policy {
+                    RestartPolicy::Never => return ExitReason::Panicked(message),
+                    RestartPolicy::Limited {
+                        max_restarts,
+                        within,
+                    } => (max_restarts, within),
+                };
+                let now = Instant::now();
+                restart_times.retain(|t| now.duration_since(*t) < within);
+                if restart_times.len() >= max_restarts {
+                    return ExitReason::Panicked(message);
+                }
+                restart_times.push(now);
+
+                // 丢弃可能处于不一致状态的旧实例，重新创建；
+                // 导致 panic 的那条消息已丢失，邮箱里的其他消息保持不变
+                ctx.restarts += 1;
+                actor = factory();
+                pending_start = true;
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("<non-string panic payload>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// 测试用的计数器 Actor
+    struct Counter {
+        value: i64,
+        stopped: Arc<AtomicUsize>,
+    }
+
+    enum CounterMsg {
+        Add(i64),
+        Get(ReplyTo<i64>),
+        // 处理时 panic；携带的 ReplyTo 会在栈展开时被丢弃
+        Crash(Option<ReplyTo<i64>>),
+        StopSelf,
+    }
+
+    impl Actor for Counter {
+        type Message = CounterMsg;
+
+        fn handle(&mut self, msg: CounterMsg, ctx: &mut Context) {
+            match msg {
+                CounterMsg::Add(n) => self.value += n,
+                CounterMsg::Get(reply) => {
+                    reply.send(self.value);
+                }
+                CounterMsg::Crash(_reply) => panic!("故意 panic"),
+                CounterMsg::StopSelf => ctx.stop(),
+            }
+        }
+
+        fn stopped(&mut self) {
+            self.stopped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn counter(stopped: &Arc<AtomicUsize>) -> Counter {
+        Counter {
+            value: 0,
+            stopped: Arc::clone(stopped),
+        }
+    }
+
+    #[test]
+    fn test_send_and_ask() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let handle = spawn(counter(&stopped));
+        let actor = handle.actor_ref();
+        for _ in 0..10 {
+            actor.send(CounterMsg::Add(2)).unwrap();
+        }
+        // 邮箱是 FIFO 的，ask 一定在前面的 Add 之后被处理
+        assert_eq!(actor.ask(CounterMsg::Get), Ok(20));
+        drop(actor);
+        assert_eq!(handle.shutdown(), ExitReason::Stopped);
+        assert_eq!(stopped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_graceful_shutdown_drains_mailbox() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let handle = spawn(counter(&stopped));
+        let actor = handle.actor_ref();
+        actor.send(CounterMsg::Add(1)).unwrap();
+        let (reply_to, rx) = reply_channel();
+        actor.send(CounterMsg::Get(reply_to)).unwrap();
+        actor.stop();
+        // stop 之后的消息不会再送达
+        assert_eq!(handle.join(), ExitReason::Stopped);
+        assert_eq!(rx.recv(), Ok(1));
+        assert!(actor.send(CounterMsg::Add(1)).is_err());
+        assert_eq!(actor.ask(CounterMsg::Get), Err(AskError::Closed));
+    }
+
+    #[test]
+    fn test_context_stop_and_disconnect() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let handle = spawn(counter(&stopped));
+        handle.actor_ref().send(CounterMsg::StopSelf).unwrap();
+        assert_eq!(handle.join(), ExitReason::Stopped);
+
+        // 所有 ActorRef 释放后，Actor 因邮箱断开而结束
+        let handle = spawn(counter(&stopped));
+        assert_eq!(handle.join(), ExitReason::Disconnected);
+        assert_eq!(stopped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_unsupervised_panic_ends_actor() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let handle = spawn(counter(&stopped));
+        let actor = handle.actor_ref();
+        actor.send(CounterMsg::Crash(None)).unwrap();
+        drop(actor);
+        assert_eq!(
+            handle.join(),
+            ExitReason::Panicked(String::from("故意 panic"))
+        );
+        assert_eq!(stopped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_supervised_restart() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let created = Arc::new(AtomicUsize::new(0));
+        let handle = spawn_supervised(
+            {
+                let stopped = Arc::clone(&stopped);
+                let created = Arc::clone(&created);
+                move || {
+                    created.fetch_add(1, Ordering::Relaxed);
+                    counter(&stopped)
+                }
+            },
+            RestartPolicy::Limited {
+                max_restarts: 3,
+                within: Duration::from_secs(60),
+            },
+        );
+        let actor = handle.actor_ref();
+        actor.send(CounterMsg::Add(5)).unwrap();
+        // 导致 panic 的消息携带的 ReplyTo 被丢弃，请求方得到 NoReply
+        assert_eq!(
+            actor.ask(|reply| CounterMsg::Crash(Some(reply))),
+            Err(AskError::NoReply)
+        );
+        // 重启后状态被重置
+        assert_eq!(actor.ask(CounterMsg::Get), Ok(0));
+        assert_eq!(created.load(Ordering::Relaxed), 2);
+
+        // 超过重启强度后放弃
+        for _ in 0..3 {
+            actor.send(CounterMsg::Crash(None)).unwrap();
+        }
+        drop(actor);
+        assert!(matches!(handle.join(), ExitReason::Panicked(_)));
+        assert_eq!(created.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_ask_timeout() {
+        struct Silent(Vec<ReplyTo<()>>);
+        impl Actor for Silent {
+            type Message = ReplyTo<()>;
+            fn handle(&mut self, reply: ReplyTo<()>, _ctx: &mut Context) {
+                // 不回复，但也不丢弃 ReplyTo
+                self.0.push(reply);
+            }
+        }
+        let handle = spawn(Silent(Vec::new()));
+        let actor = handle.actor_ref();
+        assert_eq!(
+            actor.ask_timeout(|r| r, Duration::from_millis(20)),
+            Err(AskError::Timeout)
+        );
+        drop(actor);
+        handle.shutdown();
+    }
+}
@@ -0,0 +1,442 @@
+//! 原子引用计数智能指针 MyArc / MyWeak（从 atomic_arc 演示中提取）
+//!
+//! 与 std::sync::Arc 一样，只有 `T: Send + Sync` 时 MyArc<T> 才是 Send / Sync，
+//! 这一点由 [`crate::assert`] 中的静态断言守住。
+//!
+//! 计数器使用 [`crate::model`] 里的原子类型，开启 `model-check` 后可以对引用计数做模型检查。
+
+use crate::model::hint;
+use crate::model::sync::atomic::{self, AtomicUsize, Ordering};
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+
+/// 引用计数的上限：与 std 一样取 isize::MAX，超过后直接 abort。
+/// 这样即使有大量线程同时在 fetch_add，也远远到不了 usize::MAX 产生回绕。
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+struct ArcInner<T> {
+    // 强引用计数：为 0 时销毁 data
+    strong: AtomicUsize,
+    // 弱引用计数：所有强引用共同持有一个"隐式"弱引用，为 0 时释放内存
+    // 特殊值 usize::MAX 表示被 is_unique 暂时"锁住"
+    weak: AtomicUsize,
+    // ManuallyDrop：data 的析构时机（strong 归零）和内存释放时机（weak 归零）是分开的
+    data: ManuallyDrop<T>,
+}
+
+pub struct MyArc<T> {
+    ptr: NonNull<ArcInner<T>>,
+}
+
+pub struct MyWeak<T> {
+    ptr: NonNull<ArcInner<T>>,
+}
+
+// 必须实现 Send 和 Sync 才能在多线程间传递
+unsafe impl<T: Send + Sync> Sync for MyArc<T> {}
+unsafe impl<T: Send + Sync> Send for MyArc<T> {}
+
+// MyWeak 可以 upgrade 回 MyArc，所以约束必须和 MyArc 一致
+unsafe impl<T: Send + Sync> Sync for MyWeak<T> {}
+unsafe impl<T: Send + Sync> Send for MyWeak<T> {}
+
+// 约束写错（比如漏掉 Sync）时让编译直接失败：
+// Cell 不是 Sync，多个 MyArc 共享它会产生数据竞争；MutexGuard 不是 Send，不能在别的线程释放
+const _: () = {
+    use crate::assert::{assert_send, assert_sync};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::MutexGuard;
+
+    assert_send::<MyArc<i32>>();
+    assert_sync::<MyArc<i32>>();
+    assert_send::<MyWeak<i32>>();
+    assert_sync::<MyWeak<i32>>();
+
+    crate::assert_not_impl!(MyArc<Rc<i32>>: Send);
+    crate::assert_not_impl!(MyArc<Rc<i32>>: Sync);
+    crate::assert_not_impl!(MyArc<Cell<i32>>: Send);
+    crate::assert_not_impl!(MyArc<Cell<i32>>: Sync);
+    crate::assert_not_impl!(MyArc<MutexGuard<'static, i32>>: Send);
+    crate::assert_not_impl!(MyArc<MutexGuard<'static, i32>>: Sync);
+    crate::assert_not_impl!(MyWeak<Cell<i32>>: Send);
+    crate::assert_not_impl!(MyWeak<Cell<i32>>: Sync);
+};
+
+impl<T> MyArc<T> {
+    pub fn new(data: T) -> Self {
+        let inner = Box::new(ArcInner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            data: ManuallyDrop::new(data),
+        });
+        Self {
+            ptr: NonNull::new(Box::into_raw(inner)).unwrap(),
+        }
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        // SAFETY: 只要还有 MyArc 存在，ArcInner 就一定没有被释放
+        unsafe { self.ptr.as_ref() }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        let cnt = this.inner().weak.load(Ordering::Acquire);
+        // 被 is_unique 锁住时说明此刻没有其他弱引用
+        if cnt == usize::MAX {
+            0
+        } else {
+            // 减去所有强引用共享的那个隐式弱引用
+            cnt - 1
+        }
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    pub fn downgrade(this: &Self) -> MyWeak<T> {
+        let inner = this.inner();
+        let mut cur = inner.weak.load(Ordering::Relaxed);
+        loop {
+            // 弱计数被 is_unique 锁住了，自旋等它恢复
+            if cur == usize::MAX {
+                hint::spin_loop();
+                cur = inner.weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if cur > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire 与 is_unique 中的 Release 配对
+            match inner.weak.compare_exchange_weak(
+                cur,
+                cur + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return MyWeak { ptr: this.ptr },
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// 判断当前是否是唯一持有者（没有其他强引用，也没有弱引用）
+    fn is_unique(&mut self) -> bool {
+        let inner = self.inner();
+        // 先把弱计数从 1 "锁"成 usize::MAX，防止检查期间有人 downgrade
+        if inner
+            .weak
+            .compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // Acquire 与 Drop 里 strong 的 Release 配对，
+            // 保证其他线程在放手之前对数据的访问都已经完成
+            let unique = inner.strong.load(Ordering::Acquire) == 1;
+            // 解锁：Release 与 downgrade 中的 Acquire 配对
+            inner.weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
+    }
+
+    /// 仅在唯一持有时返回可变引用
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.is_unique() {
+            // SAFETY: 已确认没有其他强/弱引用，独占访问
+            unsafe { Some(&mut (*this.ptr.as_ptr()).data) }
+        } else {
+            None
+        }
+    }
+
+    /// 若恰好只剩一个强引用，则取回内部数据；否则原样返回
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        let inner = this.inner();
+        // 抢占式地把 strong 从 1 改为 0：多个线程同时 try_unwrap 时只有一个能成功
+        if inner
+            .strong
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        atomic::fence(Ordering::Acquire);
+
+        let this = ManuallyDrop::new(this);
+        // SAFETY: strong 已归零，数据只会在这里被读出一次
+        let data = unsafe { ptr::read(&*this.inner().data) };
+        // 释放强引用们共享的那个隐式弱引用
+        drop(MyWeak { ptr: this.ptr });
+        Ok(data)
+    }
+}
+
+impl<T: Clone> MyArc<T> {
+    /// 写时复制 (Clone-on-Write)：
+    /// - 唯一持有：直接返回可变引用
+    /// - 还有其他强引用：克隆一份数据，自己指向新分配
+    /// - 只有弱引用：把数据搬到新分配，旧的弱引用从此 upgrade 失败
+    pub fn make_mut(this: &mut Self) -> &mut T {
+        let inner = this.inner();
+        if inner
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // 还有其他强引用，只能克隆
+            *this = MyArc::new((**this).clone());
+        } else if inner.weak.load(Ordering::Relaxed) != 1 {
+            // 没有其他强引用，但有弱引用：把数据"搬走"
+            let weak = MyWeak { ptr: this.ptr };
+            // SAFETY: strong 已被我们置 0，数据不会再被别人读到
+            let data = unsafe { ptr::read(&*weak.inner().data) };
+            // 不能走 MyArc 的 Drop（strong 已经是 0），直接覆盖
+            unsafe { ptr::write(this, MyArc::new(data)) };
+            // 旧分配的隐式弱引用由 weak 负责释放
+            drop(weak);
+        } else {
+            // 确实是唯一持有者，恢复 strong
+            inner.strong.store(1, Ordering::Release);
+        }
+        // SAFETY: 以上三个分支结束后，this 都是唯一持有者
+        unsafe { &mut (*this.ptr.as_ptr()).data }
+    }
+}
+
+impl<T> Clone for MyArc<T> {
+    fn clone(&self) -> Self {
+        // 使用 Relaxed 是因为我们不需要同步这里之前或之后的操作，
+        // 只要保证计数器本身增加是原子性的即可。
+        let old = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        // 和 std 一样：溢出时直接 abort。
+        // 不能用 panic，因为 panic 期间其他线程仍在继续 clone。
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        MyArc { ptr: self.ptr }
+    }
+}
+
+impl<T> Deref for MyArc<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.inner().data
+    }
+}
+
+impl<T> Drop for MyArc<T> {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        // 使用 Release 确保当前线程对数据的访问/修改对其他线程可见
+        if inner.strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // 当计数降到 0 时，我们需要 Acquire 屏障，以确保我们能看到
+        // 之前所有其他线程对数据的操作（与上面的 Release 配对）
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: 强引用计数为 0，data 只会在这里被析构一次。
+        // 注意此时内存还不能释放，弱引用可能仍指向它。
+        unsafe {
+            ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).data);
+        }
+        // 释放所有强引用共享的隐式弱引用，若它是最后一个则释放内存
+        drop(MyWeak { ptr: self.ptr });
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MyArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> MyWeak<T> {
+    fn inner(&self) -> &ArcInner<T> {
+        // SAFETY: 只要还有 MyWeak 存在，ArcInner 的内存就不会被释放
+        //（但 data 可能已被析构，所以这里不能访问 data）
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// 尝试升级为强引用；数据已被销毁时返回 None
+    pub fn upgrade(&self) -> Option<MyArc<T>> {
+        let inner = self.inner();
+        let mut n = inner.strong.load(Ordering::Relaxed);
+        loop {
+            // 不能用 fetch_add：一旦 strong 到过 0，数据已经析构，绝不能"复活"
+            if n == 0 {
+                return None;
+            }
+            if n > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            match inner
+                .strong
+                .compare_exchange_weak(n, n + 1, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => return Some(MyArc { ptr: self.ptr }),
+                Err(old) => n = old,
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.load(Ordering::Acquire)
+    }
+
+    /// 与 `std::sync::Weak::weak_count` 一致：强引用全部消失后返回 0
+    pub fn weak_count(&self) -> usize {
+        let weak = self.inner().weak.load(Ordering::Acquire);
+        let strong = self.inner().strong.load(Ordering::Acquire);
+        // 被 is_unique 锁住时弱计数是哨兵值 usize::MAX，不能当成计数减一
+        if strong == 0 || weak == usize::MAX {
+            0
+        } else {
+            // 减去所有强引用共享的那个隐式弱引用
+            weak - 1
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T> Clone for MyWeak<T> {
+    fn clone(&self) -> Self {
+        let old = self.inner().weak.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        MyWeak { ptr: self.ptr }
+    }
+}
+
+impl<T> fmt::Debug for MyWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 数据可能已被析构，不能打印内容
+        write!(f, "(MyWeak)")
+    }
+}
+
+impl<T> Drop for MyWeak<T> {
+    fn drop(&mut self) {
+        let inner = self.inner();
+        if inner.weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: 弱计数归零意味着强计数早已归零、data 已析构，
+        // data 被 ManuallyDrop 包裹，这里只释放内存不会重复析构。
+        unsafe {
+            drop(Box::from_raw(self.ptr.as_ptr()));
+        }
+    }
+}
+
+/// atomic_arc 里的测试是普通的多线程测试；这里是针对引用计数内存序的模型检查版本
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+    use crate::model::cell::UnsafeCell;
+    use crate::model::sync::Arc;
+    use crate::model::{self, thread};
+
+    /// 析构时改写数据并计数：析构与其他线程的读取之间缺少 happens-before 时 loom 会报错
+    struct Payload {
+        value: UnsafeCell<usize>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    // SAFETY: value 只在 drop 或独占 (&mut) 时写入
+    unsafe impl Sync for Payload {}
+
+    impl Payload {
+        fn new(value: usize) -> (MyArc<Self>, Arc<AtomicUsize>) {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let payload = Payload {
+                value: UnsafeCell::new(value),
+                drops: Arc::clone(&drops),
+            };
+            (MyArc::new(payload), drops)
+        }
+
+        fn get(&self) -> usize {
+            self.value.with(|v| unsafe { *v })
+        }
+    }
+
+    impl Drop for Payload {
+        fn drop(&mut self) {
+            self.value.with_mut(|v| unsafe { *v = 0 });
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// 与 atomic_arc 里的 test_arc_multithreaded 互补：不论哪个线程最后放手，数据都只析构一次
+    #[test]
+    fn model_arc_multithreaded() {
+        model::check(|| {
+            let (val, drops) = Payload::new(100);
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let v = val.clone();
+                    thread::spawn(move || assert_eq!(v.get(), 100))
+                })
+                .collect();
+            drop(val);
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    /// upgrade 与最后一个强引用的 drop 竞争：要么失败，要么拿到仍然完好的数据
+    #[test]
+    fn model_upgrade_races_last_drop() {
+        model::check(|| {
+            let (strong, drops) = Payload::new(7);
+            let weak = MyArc::downgrade(&strong);
+            let upgrader = thread::spawn(move || {
+                if let Some(arc) = weak.upgrade() {
+                    assert_eq!(arc.get(), 7);
+                }
+            });
+            drop(strong);
+            upgrader.join().unwrap();
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    /// get_mut 只有在其他线程的强、弱引用都放手之后才能成功，之后的写入不会与它们的读取竞争
+    #[test]
+    fn model_get_mut_waits_for_other_handles() {
+        model::check(|| {
+            let (mut a, drops) = Payload::new(1);
+            let b = a.clone();
+            let other = thread::spawn(move || {
+                let weak = MyArc::downgrade(&b);
+                drop(b);
+                if let Some(arc) = weak.upgrade() {
+                    assert_eq!(arc.get(), 1);
+                }
+            });
+            if let Some(payload) = MyArc::get_mut(&mut a) {
+                payload.value.with_mut(|v| unsafe { *v = 2 });
+            }
+            other.join().unwrap();
+            drop(a);
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+        });
+    }
+}
@@ -0,0 +1,63 @@
+//! 编译期的 Send / Sync 检查
+//!
+//! 手写 `unsafe impl Send/Sync` 的类型（[`MyArc`](crate::arc::MyArc)、
+//! [`SpinLock`](crate::spinlock::SpinLock) 等）一旦约束写错，编译器不会报任何错，
+//! 只会在运行时出现数据竞争。这里的工具把"应该实现"和"绝不能实现"都写成编译期断言，
+//! 改错约束时直接编译失败。
+//!
+//! - [`assert_send`] / [`assert_sync`]：`const fn`，可以放在 `const _: () = ...` 里做正向检查
+//! - [`assert_not_impl!`](crate::assert_not_impl)：反向检查，类型实现了给出的 trait 时编译失败
+//!
+//! 反过来"不满足约束的类型确实会被拒绝"由 `tests/compile_fail` 下的 trybuild 用例覆盖。
+
+/// 类型不是 Send 时编译失败
+///
+/// ```
+/// use learning_concurrency::assert::assert_send;
+/// const _: () = assert_send::<std::sync::Arc<i32>>();
+/// ```
+///
+/// ```compile_fail
+/// use learning_concurrency::assert::assert_send;
+/// const _: () = assert_send::<std::rc::Rc<i32>>();
+/// ```
+pub const fn assert_send<T: ?Sized + Send>() {}
+
+/// 类型不是 Sync 时编译失败
+///
+/// ```compile_fail
+/// use learning_concurrency::assert::assert_sync;
+/// const _: () = assert_sync::<std::cell::Cell<i32>>();
+/// ```
+pub const fn assert_sync<T: ?Sized + Sync>() {}
+
+/// 断言类型**没有**同时实现给出的所有 trait，实现了则编译失败
+///
+/// 原理：为所有类型实现 `Ambiguous<()>`，再为满足约束的类型额外实现 `Ambiguous<Invalid>`。
+/// 类型满足约束时两个 impl 都适用，`<T as Ambiguous<_>>` 无法推断而报错；否则只有一个候选。
+///
+/// ```
+/// use learning_concurrency::assert_not_impl;
+/// assert_not_impl!(std::rc::Rc<i32>: Send);
+/// assert_not_impl!(std::cell::Cell<i32>: Sync);
+/// ```
+///
+/// ```compile_fail
+/// use learning_concurrency::assert_not_impl;
+/// assert_not_impl!(std::sync::Arc<i32>: Send);
+/// ```
+#[macro_export]
+macro_rules! assert_not_impl {
+    ($ty:ty: $($tr:path),+ $(,)?) => {
+        const _: fn() = || {
+            trait Ambiguous<A> {
+                fn item() {}
+            }
+            impl<T: ?Sized> Ambiguous<()> for T {}
+            #[allow(dead_code)]
+            struct Invalid;
+            impl<T: ?Sized $(+ $tr)+> Ambiguous<Invalid> for T {}
+            let _ = <$ty as Ambiguous<_>>::item;
+        };
+    };
+}
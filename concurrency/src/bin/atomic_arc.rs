@@ -1,87 +1,59 @@
-use std::ops::Deref;
-use std::ptr::NonNull;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use learning_concurrency::arc::MyArc;
 
-struct ArcInner<T> {
-    rc: AtomicUsize,
-    data: T,
-}
+fn example_myarc() {
+    let s1 = MyArc::new(String::from("Hello World"));
+    let s2 = s1.clone();
 
-pub struct MyArc<T> {
-    ptr: NonNull<ArcInner<T>>,
+    println!("s1 地址: {:p}", &*s1);
+    println!("s2 地址: {:p}", &*s2);
+    println!("内容: {:?}", *s2);
 }
 
-// 必须实现 Send 和 Sync 才能在多线程间传递
-unsafe impl<T: Send + Sync> Sync for MyArc<T> {}
-unsafe impl<T: Send + Sync> Send for MyArc<T> {}
+fn example_weak_and_cow() {
+    let mut a = MyArc::new(vec![1, 2, 3]);
+    let weak = MyArc::downgrade(&a);
+    println!(
+        "strong = {}, weak = {}",
+        MyArc::strong_count(&a),
+        MyArc::weak_count(&a)
+    );
 
-impl<T> MyArc<T> {
-    pub fn new(data: T) -> Self {
-        let inner = Box::new(ArcInner {
-            rc: AtomicUsize::new(1),
-            data,
-        });
-        Self {
-            ptr: NonNull::new(Box::into_raw(inner)).unwrap(),
-        }
-    }
-}
+    // 有弱引用时 get_mut 失败
+    println!("get_mut (有弱引用): {:?}", MyArc::get_mut(&mut a).is_some());
 
-impl<T> Clone for MyArc<T> {
-    fn clone(&self) -> Self {
-        let inner = unsafe { self.ptr.as_ref() };
-        // 使用 Relaxed 是因为我们不需要同步这里之前或之后的操作，
-        // 只要保证计数器本身增加是原子性的即可。
-        inner.rc.fetch_add(1, Ordering::Relaxed);
-        MyArc { ptr: self.ptr }
-    }
-}
+    let b = a.clone();
+    // 写时复制：a 与 b 共享数据，make_mut 会克隆出一份新的
+    MyArc::make_mut(&mut a).push(4);
+    println!("a = {:?}, b = {:?}", *a, *b);
+    println!("a 与 b 是否同一分配: {}", MyArc::ptr_eq(&a, &b));
 
-impl<T> Deref for MyArc<T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        let inner = unsafe { self.ptr.as_ref() };
-        &inner.data
-    }
-}
+    drop(b);
+    println!(
+        "b 释放后旧分配的 weak.upgrade(): {:?}",
+        weak.upgrade().map(|v| v.len())
+    );
 
-impl<T> Drop for MyArc<T> {
-    fn drop(&mut self) {
-        let inner = unsafe { self.ptr.as_ref() };
-        // 使用 Release 确保当前线程对数据的访问/修改对其他线程可见
-        if inner.rc.fetch_sub(1, Ordering::Release) != 1 {
-            return;
-        }
-        // 当计数降到 0 时，我们需要 Acquire 屏障，以确保我们能看到
-        // 之前所有其他线程对数据的操作（与上面的 Release 配对）
-        std::sync::atomic::fence(Ordering::Acquire);
-
-        // SAFETY: 引用计数为 0，且我们有独占访问权（通过 fence 保证），
-        // 销毁代码只执行一次。
-        unsafe {
-            drop(Box::from_raw(self.ptr.as_ptr()));
-        }
+    match MyArc::try_unwrap(a) {
+        Ok(v) => println!("try_unwrap 成功取回: {:?}", v),
+        Err(_) => println!("try_unwrap 失败"),
     }
 }
-fn example_myarc() {
-    let s1 = MyArc::new(String::from("Hello World"));
-    let s2 = s1.clone();
-
-    println!("s1 地址: {:p}", &*s1);
-    println!("s2 地址: {:p}", &*s2);
-    println!("内容: {:?}", *s2);
-}
 
 fn main() {
     println!("=== 示例: 实现原子引用计数 ===");
     example_myarc();
+
+    println!("\n=== 示例: 弱引用与写时复制 ===");
+    example_weak_and_cow();
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "model-check")))]
 mod tests {
     use super::*;
-    use std::sync::atomic::AtomicBool;
     use learning_concurrency::spawn_workers; // 引用库中的通用工具
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
 
     #[test]
     fn test_arc_basic() {
@@ -112,13 +84,308 @@ mod tests {
     }
 
     #[test]
-    fn test_arc_multithreaded() {
+    fn test_arc_shared_by_workers() {
         let val = MyArc::new(100);
-        
+
         // 使用通用的 spawn_workers 来测试我们手写的 MyArc
         // 这证明了 MyArc 满足 Clone + Send，且行为符合预期
         spawn_workers(val, 10, |v, _| {
             assert_eq!(*v, 100);
         });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_counts() {
+        let a = MyArc::new(1);
+        let b = a.clone();
+        let w1 = MyArc::downgrade(&a);
+        let w2 = w1.clone();
+        assert_eq!(MyArc::strong_count(&a), 2);
+        assert_eq!(MyArc::weak_count(&a), 2);
+        assert_eq!(w1.strong_count(), 2);
+        assert_eq!(w1.weak_count(), 2);
+
+        drop(b);
+        drop(w2);
+        assert_eq!(MyArc::strong_count(&a), 1);
+        assert_eq!(MyArc::weak_count(&a), 1);
+
+        drop(a);
+        assert_eq!(w1.strong_count(), 0);
+        // 与 std 一致：没有强引用时弱计数报告为 0
+        assert_eq!(w1.weak_count(), 0);
+    }
+
+    #[test]
+    fn test_weak_upgrade() {
+        let a = MyArc::new(String::from("hi"));
+        let w = MyArc::downgrade(&a);
+        let up = w.upgrade().expect("数据仍存活时应能 upgrade");
+        assert!(MyArc::ptr_eq(&a, &up));
+        drop(up);
+        drop(a);
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_weak_does_not_keep_data_alive() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Trap;
+        impl Drop for Trap {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let a = MyArc::new(Trap);
+        let w = MyArc::downgrade(&a);
+        drop(a);
+        // 强引用消失时数据立即析构，哪怕还有弱引用
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+        drop(w);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_ptr_eq() {
+        let a = MyArc::new(5);
+        let b = a.clone();
+        let c = MyArc::new(5);
+        assert!(MyArc::ptr_eq(&a, &b));
+        assert!(!MyArc::ptr_eq(&a, &c));
+        assert!(MyArc::downgrade(&a).ptr_eq(&MyArc::downgrade(&b)));
+        assert!(!MyArc::downgrade(&a).ptr_eq(&MyArc::downgrade(&c)));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut a = MyArc::new(1);
+        *MyArc::get_mut(&mut a).unwrap() = 2;
+        assert_eq!(*a, 2);
+
+        let b = a.clone();
+        assert!(MyArc::get_mut(&mut a).is_none());
+        drop(b);
+
+        let w = MyArc::downgrade(&a);
+        assert!(MyArc::get_mut(&mut a).is_none());
+        drop(w);
+        assert!(MyArc::get_mut(&mut a).is_some());
+    }
+
+    #[test]
+    fn test_make_mut() {
+        // 唯一持有：原地修改，不重新分配
+        let mut a = MyArc::new(vec![1]);
+        let before = &*a as *const Vec<i32>;
+        MyArc::make_mut(&mut a).push(2);
+        assert_eq!(before, &*a as *const Vec<i32>);
+
+        // 共享：克隆一份
+        let b = a.clone();
+        MyArc::make_mut(&mut a).push(3);
+        assert_eq!(*a, vec![1, 2, 3]);
+        assert_eq!(*b, vec![1, 2]);
+        assert!(!MyArc::ptr_eq(&a, &b));
+
+        // 仅有弱引用：数据被搬走，旧弱引用失效
+        let w = MyArc::downgrade(&a);
+        MyArc::make_mut(&mut a).push(4);
+        assert_eq!(*a, vec![1, 2, 3, 4]);
+        assert!(w.upgrade().is_none());
+        assert_eq!(MyArc::weak_count(&a), 0);
+    }
+
+    #[test]
+    fn test_try_unwrap() {
+        let a = MyArc::new(String::from("x"));
+        let b = a.clone();
+        let a = MyArc::try_unwrap(a).unwrap_err();
+        drop(b);
+
+        let w = MyArc::downgrade(&a);
+        assert_eq!(MyArc::try_unwrap(a).unwrap(), "x");
+        assert!(w.upgrade().is_none());
+    }
+
+    // ---------- 高并发压力测试 ----------
+
+    const STRESS_THREADS: usize = 8;
+    const STRESS_ITERS: usize = 2_000;
+
+    /// 所有线程疯狂 clone / downgrade / upgrade / drop，
+    /// 最终数据必须恰好析构一次，且内存正常释放。
+    #[test]
+    fn stress_clone_upgrade_drop() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted(usize);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        for _ in 0..20 {
+            DROPS.store(0, Ordering::Relaxed);
+            let root = MyArc::new(Counted(7));
+            let weak = MyArc::downgrade(&root);
+            let barrier = Arc::new(Barrier::new(STRESS_THREADS + 1));
+
+            let handles: Vec<_> = (0..STRESS_THREADS)
+                .map(|_| {
+                    let arc = root.clone();
+                    let weak = weak.clone();
+                    let barrier = Arc::clone(&barrier);
+                    thread::spawn(move || {
+                        barrier.wait();
+                        for _ in 0..STRESS_ITERS {
+                            let a = arc.clone();
+                            let w = MyArc::downgrade(&a);
+                            if let Some(up) = w.upgrade() {
+                                assert_eq!(up.0, 7);
+                            }
+                            if let Some(up) = weak.upgrade() {
+                                assert_eq!(up.0, 7);
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            // 主线程在其他线程仍在运行时放弃自己的强引用
+            barrier.wait();
+            drop(root);
+            for h in handles {
+                h.join().unwrap();
+            }
+
+            assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+            assert!(weak.upgrade().is_none());
+            assert_eq!(weak.weak_count(), 0);
+        }
+    }
+
+    /// 多个线程同时 try_unwrap 最后两个引用：恰好一个线程拿到数据
+    #[test]
+    fn stress_try_unwrap_race() {
+        for _ in 0..500 {
+            let a = MyArc::new(42);
+            let b = a.clone();
+            let barrier = Arc::new(Barrier::new(2));
+
+            let handles: Vec<_> = [a, b]
+                .into_iter()
+                .map(|arc| {
+                    let barrier = Arc::clone(&barrier);
+                    thread::spawn(move || {
+                        barrier.wait();
+                        MyArc::try_unwrap(arc).ok()
+                    })
+                })
+                .collect();
+
+            let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            // 两个都失败是允许的（与 std 语义一致：双方各自看到 strong == 2），
+            // 但绝不能两个都成功
+            assert!(results.iter().filter(|r| r.is_some()).count() <= 1);
+        }
+    }
+
+    /// 一边 upgrade 一边释放最后一个强引用：upgrade 要么拿到完整的数据，要么返回 None，
+    /// 绝不能"复活"已析构的数据
+    #[test]
+    fn stress_upgrade_vs_last_drop() {
+        static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked;
+        impl Tracked {
+            fn new() -> Self {
+                LIVE.fetch_add(1, Ordering::Relaxed);
+                Tracked
+            }
+            fn check(&self) {
+                assert_eq!(LIVE.load(Ordering::Relaxed), 1, "访问到了已析构的数据");
+            }
+        }
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                LIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        for _ in 0..500 {
+            let a = MyArc::new(Tracked::new());
+            let w = MyArc::downgrade(&a);
+            let barrier = Arc::new(Barrier::new(2));
+
+            let b2 = Arc::clone(&barrier);
+            let upgrader = thread::spawn(move || {
+                b2.wait();
+                for _ in 0..50 {
+                    match w.upgrade() {
+                        Some(up) => up.check(),
+                        None => return,
+                    }
+                }
+            });
+
+            barrier.wait();
+            drop(a);
+            upgrader.join().unwrap();
+            assert_eq!(LIVE.load(Ordering::Relaxed), 0);
+        }
+    }
+
+    /// get_mut 与并发 downgrade 竞争：get_mut 成功时不可能存在其他弱引用
+    #[test]
+    fn stress_get_mut_vs_downgrade() {
+        for _ in 0..200 {
+            let mut a = MyArc::new(0usize);
+            let shared = a.clone();
+            let barrier = Arc::new(Barrier::new(2));
+
+            let b2 = Arc::clone(&barrier);
+            let t = thread::spawn(move || {
+                b2.wait();
+                let w = MyArc::downgrade(&shared);
+                drop(shared);
+                w
+            });
+
+            barrier.wait();
+            let w = t.join().unwrap();
+            // 对方持有弱引用，get_mut 必须失败
+            assert!(MyArc::get_mut(&mut a).is_none());
+            drop(w);
+            *MyArc::get_mut(&mut a).unwrap() += 1;
+            assert_eq!(*a, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+
+    /// 开启 `model-check` 时在 loom 下穷举克隆、读取和释放的所有交错
+    #[test]
+    fn test_arc_multithreaded() {
+        use learning_concurrency::model;
+
+        model::check(|| {
+            let val = MyArc::new(100);
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let v = val.clone();
+                    model::thread::spawn(move || assert_eq!(*v, 100))
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            assert_eq!(MyArc::strong_count(&val), 1);
+        });
+    }
+}
@@ -1,67 +1,65 @@
-use std::sync::{Arc, Condvar, Mutex};
+use learning_concurrency::bounded_buffer::BoundedBuffer;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+/// 多槽位的生产者/消费者
+///
+/// 早先的版本用 `Mutex<bool>` 表示"空/满"，同一时刻只能有一个数据在途。
+/// BoundedBuffer 内部同样是 Mutex + Condvar，但用两个条件变量 (not_full / not_empty)
+/// 分别唤醒生产者和消费者，最多允许 capacity 个数据排队。
 fn example_condvar() {
-    // 数据对：锁 + 条件变量
-    let pair = Arc::new((Mutex::new(false), Condvar::new())); // false 代表"空"，true 代表"满"
-    let pair2 = Arc::clone(&pair);
-    // 生产者
-    let producer = thread::spawn(move || {
-        let (lock, cvar) = &*pair;
-
-        for i in 1..5 {
-            let mut started = lock.lock().unwrap();
-
-            while *started {
-                started = cvar.wait(started).unwrap();
+    let buffer = Arc::new(BoundedBuffer::new(3));
+    let mut producers = vec![];
+    let mut consumers = vec![];
+
+    for p in 1..=2 {
+        let buffer = Arc::clone(&buffer);
+        producers.push(thread::spawn(move || {
+            for i in 1..5 {
+                let item = p * 100 + i;
+                // 缓冲区满时在 not_full 上等待
+                buffer.push(item).unwrap();
+                println!(
+                    "生产者{}: 生产数据 {} (缓冲区 {} 个)",
+                    p,
+                    item,
+                    buffer.len()
+                );
             }
+        }));
+    }
 
-            // 2. 醒来发现 false，生产数据
-            println!("生产者: 生产数据 {}", i);
-            thread::sleep(Duration::from_millis(50)); // 模拟耗时
-
-            // 3. 修改状态为 true (满)
-            *started = true;
-
-            // 4. 通知消费者 (饭做好了)
-            cvar.notify_one();
-        }
-    });
-    // 消费者
-    let consumer = thread::spawn(move || {
-        let (lock, cvar) = &*pair2;
-
-        for i in 1..5 {
-            let mut started = lock.lock().unwrap();
-            // 1. 等待数据：只要是 false (没数据)，就睡着
-            while !*started {
-                started = cvar.wait(started).unwrap();
+    for c in 1..=2 {
+        let buffer = Arc::clone(&buffer);
+        consumers.push(thread::spawn(move || {
+            // 缓冲区空时在 not_empty 上等待；关闭且取完后 pop 返回 None
+            while let Some(item) = buffer.pop() {
+                println!("消费者{}: 消费数据 {}", c, item);
+                thread::sleep(Duration::from_millis(20)); // 模拟耗时
             }
+        }));
+    }
 
-            // 2. 醒来发现 true，消费数据
-            println!("消费者: 消费数据 {}", i);
-
-            // 3. 修改状态为 false (空)
-            *started = false;
-
-            // 4. 通知生产者
-            cvar.notify_one();
-        }
-    });
-
-    producer.join().unwrap();
-    consumer.join().unwrap();
+    for p in producers {
+        p.join().unwrap();
+    }
+    // 生产结束：关闭缓冲区，唤醒所有还在等待的消费者
+    buffer.close();
+    for c in consumers {
+        c.join().unwrap();
+    }
 }
 
 fn main() {
-    println!("=== 示例: 条件变量 (Condvar) ===");
+    println!("=== 示例: 条件变量 (Condvar) 与有界缓冲区 ===");
     example_condvar();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Condvar, Mutex};
 
     #[test]
     fn test_condvar_notification() {
@@ -0,0 +1,130 @@
+use learning_concurrency::pool::ThreadPool;
+use learning_concurrency::spawn_workers_cancellable;
+use learning_concurrency::sync::CancellationToken;
+use learning_concurrency::timer::Timer;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// 示例 1: 定时器到点后取消一组轮询工作线程
+fn example_workers(timer: &Timer) {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    timer.schedule_after(Duration::from_millis(50), move || cancel.cancel());
+
+    let polls = Arc::new(AtomicUsize::new(0));
+    spawn_workers_cancellable(Arc::clone(&polls), 3, &token, |polls, i, token| {
+        // 用 wait_timeout 代替 sleep：取消时立刻醒来
+        while !token.wait_timeout(Duration::from_millis(10)) {
+            polls.fetch_add(1, Ordering::Relaxed);
+        }
+        println!("  工作线程 {} 收到取消信号，退出", i);
+    });
+    println!("  共轮询 {} 次", polls.load(Ordering::Relaxed));
+}
+
+/// 示例 2: 线程池取消后，排队中的任务被丢弃
+fn example_pool() {
+    let pool = ThreadPool::new(2);
+    let finished = Arc::new(AtomicUsize::new(0));
+    for _ in 0..20 {
+        let token = pool.cancellation_token();
+        let finished = Arc::clone(&finished);
+        pool.execute(move || {
+            // 模拟一个可以中途放弃的长任务
+            if !token.wait_timeout(Duration::from_millis(20)) {
+                finished.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+    thread::sleep(Duration::from_millis(50));
+    pool.cancel();
+    println!(
+        "  20 个任务中完成了 {} 个，其余被取消或丢弃",
+        finished.load(Ordering::Relaxed)
+    );
+}
+
+/// 示例 3: 可以从外部停止的 TCP 服务端（每个连接一个线程，都挂在同一个令牌下）
+fn example_tcp_server(timer: &Timer) -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    // 非阻塞 accept，这样主循环才有机会检查令牌
+    listener.set_nonblocking(true)?;
+    println!("  监听在 {}", addr);
+
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    timer.schedule_after(Duration::from_millis(200), move || cancel.cancel());
+    timer.schedule_after(Duration::from_millis(30), move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all("你好，服务端！".as_bytes()).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        println!("  客户端收到: {}", reply);
+    });
+
+    let mut connections = Vec::new();
+    while !token.is_cancelled() {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                println!("  新连接: {}", peer);
+                let token = token.child_token();
+                connections.push(thread::spawn(move || handle_client(stream, &token)));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                token.wait_timeout(Duration::from_millis(10));
+            }
+            Err(e) => eprintln!("  连接错误: {}", e),
+        }
+    }
+    for connection in connections {
+        if let Err(e) = connection.join().unwrap() {
+            eprintln!("  客户端处理错误: {}", e);
+        }
+    }
+    println!("  服务端已停止");
+    Ok(())
+}
+
+fn handle_client(mut stream: TcpStream, token: &CancellationToken) -> io::Result<()> {
+    // 读超时让连接线程定期回来看一眼令牌
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(20)))?;
+    let mut buffer = [0; 1024];
+    let bytes_read = loop {
+        token
+            .check()
+            .map_err(|e| io::Error::new(io::ErrorKind::Interrupted, e))?;
+        match stream.read(&mut buffer) {
+            Ok(n) => break n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e),
+        }
+    };
+    println!(
+        "  收到消息: {}",
+        String::from_utf8_lossy(&buffer[..bytes_read])
+    );
+    stream.write_all("你好，客户端！".as_bytes())
+}
+
+fn main() -> io::Result<()> {
+    let timer = Timer::new(2);
+
+    println!("=== 示例 1: 定时取消工作线程 ===");
+    example_workers(&timer);
+
+    println!("\n=== 示例 2: 取消线程池 ===");
+    example_pool();
+
+    println!("\n=== 示例 3: 可停止的 TCP 服务端 ===");
+    example_tcp_server(&timer)
+}
@@ -1,20 +1,33 @@
-use std::sync::mpsc;
-use std::thread;
+use learning_concurrency::actor::{self, Actor, Context, ExitReason, ReplyTo, RestartPolicy};
+use std::time::Duration;
 
 /// 定义消息类型枚举
+///
+/// 不再需要 `Quit` 变体：关闭由 ActorRef::stop 统一处理。
 #[derive(Debug)]
 enum Task {
     Compute(i32, i32),
     Log(String),
-    Quit,
+    /// 请求/应答：计算结果通过 ReplyTo 发回给调用方
+    Sum(Vec<i32>, ReplyTo<i32>),
+    /// 模拟处理过程中出现 bug
+    Crash,
 }
 
-/// 模拟一个处理不同任务的逻辑
-fn handle_tasks(rx: mpsc::Receiver<Task>) {
-    println!("工作线程: 已启动");
+/// 工作者 Actor：原先 handle_tasks 中的 match 现在放在 Actor::handle 里
+struct Worker {
+    processed: usize,
+}
+
+impl Actor for Worker {
+    type Message = Task;
+
+    fn started(&mut self, ctx: &mut Context) {
+        println!("工作线程: 已启动 (重启次数 {})", ctx.restarts());
+    }
 
-    // 使用 while let 接收消息
-    while let Ok(task) = rx.recv() {
+    fn handle(&mut self, task: Task, _ctx: &mut Context) {
+        self.processed += 1;
         match task {
             Task::Compute(a, b) => {
                 println!("工作线程: 收到计算任务，结果为 {}", a + b);
@@ -22,43 +35,55 @@ fn handle_tasks(rx: mpsc::Receiver<Task>) {
             Task::Log(msg) => {
                 println!("工作线程: 记录日志 -> \"{}\"", msg);
             }
-            Task::Quit => {
-                println!("工作线程: 收到退出指令");
-                break;
+            Task::Sum(values, reply) => {
+                reply.send(values.iter().sum());
             }
+            Task::Crash => panic!("处理任务时出错"),
         }
     }
-    println!("工作线程: 已停止接收消息");
+
+    fn stopped(&mut self) {
+        println!("工作线程: 已停止接收消息 (本实例处理了 {} 条)", self.processed);
+    }
 }
 
 fn main() {
-    println!("=== Channel 传递枚举消息 ===");
+    println!("=== Channel 传递枚举消息 (Actor 版本) ===");
 
-    let (tx, rx) = mpsc::channel();
-
-    let handle = thread::spawn(move || {
-        handle_tasks(rx);
-    });
+    // panic 后最多重启 3 次（1 分钟内）
+    let handle = actor::spawn_supervised(
+        || Worker { processed: 0 },
+        RestartPolicy::Limited {
+            max_restarts: 3,
+            within: Duration::from_secs(60),
+        },
+    );
+    let worker = handle.actor_ref();
 
     let send_task = |task: Task| {
-        if tx.send(task).is_err() {
-            eprintln!("发送失败: 通道可能已关闭");
+        if worker.send(task).is_err() {
+            eprintln!("发送失败: Actor 可能已停止");
         }
     };
 
     // 发送任务
     send_task(Task::Log(String::from("系统初始化...")));
     send_task(Task::Compute(10, 20));
+
+    // 请求/应答
+    match worker.ask(|reply| Task::Sum(vec![1, 2, 3, 4], reply)) {
+        Ok(sum) => println!("主线程: 收到求和结果 {}", sum),
+        Err(e) => eprintln!("主线程: 请求失败: {}", e),
+    }
+
+    // 触发 panic：监督者会重建 Worker，后续消息照常处理
+    send_task(Task::Crash);
     send_task(Task::Log(String::from("正在执行中间步骤...")));
     send_task(Task::Compute(100, 200));
 
-    // 发送退出指令
-    send_task(Task::Quit);
-
-    // 等待子线程结束
-    if handle.join().is_err() {
-        eprintln!("子线程发生 panic");
+    // 优雅关闭：之前发送的任务都会先处理完
+    match handle.shutdown() {
+        ExitReason::Stopped => println!("主线程: 演示结束"),
+        other => eprintln!("主线程: Actor 异常退出: {:?}", other),
     }
-
-    println!("主线程: 演示结束");
 }
@@ -0,0 +1,99 @@
+use learning_concurrency::channel::{self, Select};
+use learning_concurrency::select;
+use std::thread;
+use std::time::Duration;
+
+/// 多个消费者 clone 同一个 Receiver，竞争消费同一个队列
+fn demo_multi_consumer() {
+    println!("--- 多消费者 ---");
+    let (tx, rx) = channel::bounded(2);
+
+    let consumers: Vec<_> = (1..=3)
+        .map(|id| {
+            let rx = rx.clone();
+            thread::spawn(move || {
+                // 发送端全部断开且队列清空后，迭代结束
+                for job in rx.iter() {
+                    println!("  消费者{} 处理任务 {}", id, job);
+                    thread::sleep(Duration::from_millis(10));
+                }
+            })
+        })
+        .collect();
+    drop(rx);
+
+    for job in 1..=6 {
+        // 容量为 2：消费者跟不上时这里会阻塞（背压）
+        tx.send(job).unwrap();
+    }
+    drop(tx);
+
+    for c in consumers {
+        c.join().unwrap();
+    }
+}
+
+/// 发送与接收的超时
+fn demo_timeout() {
+    println!("\n--- 超时 ---");
+    let (tx, rx) = channel::bounded(1);
+    tx.send("占位").unwrap();
+    println!(
+        "  send_timeout: {:?}",
+        tx.send_timeout("挤不进去", Duration::from_millis(20))
+    );
+    println!("  recv: {:?}", rx.recv());
+    println!(
+        "  recv_timeout: {:?}",
+        rx.recv_timeout(Duration::from_millis(20))
+    );
+}
+
+/// 同时等待多个通道
+fn demo_select() {
+    println!("\n--- select ---");
+    let (fast_tx, fast_rx) = channel::unbounded();
+    let (slow_tx, slow_rx) = channel::unbounded();
+
+    thread::spawn(move || {
+        for i in 0..3 {
+            thread::sleep(Duration::from_millis(10));
+            fast_tx.send(i).unwrap();
+        }
+    });
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        slow_tx.send(String::from("慢速通道的消息")).unwrap();
+    });
+
+    let (mut fast_done, mut slow_done) = (false, false);
+    while !(fast_done && slow_done) {
+        select! {
+            recv(fast_rx) -> msg => match msg {
+                Ok(v) => println!("  快速通道: {}", v),
+                Err(_) => fast_done = true,
+            },
+            recv(slow_rx) -> msg => match msg {
+                Ok(s) => println!("  慢速通道: {}", s),
+                Err(_) => slow_done = true,
+            },
+            default(Duration::from_millis(15)) => println!("  (15ms 内没有消息)"),
+        }
+    }
+
+    // 不使用宏时，也可以直接操作 Select
+    let (_tx, idle_rx) = channel::unbounded::<i32>();
+    let mut sel = Select::new();
+    sel.recv(&idle_rx);
+    println!(
+        "  空通道 select_timeout: {:?}",
+        sel.select_timeout(Duration::from_millis(10))
+    );
+}
+
+fn main() {
+    println!("=== 多生产者多消费者通道 ===");
+    demo_multi_consumer();
+    demo_timeout();
+    demo_select();
+}
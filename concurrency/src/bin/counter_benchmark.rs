@@ -0,0 +1,81 @@
+use learning_concurrency::metrics::StripedCounter;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INCREMENTS_PER_THREAD: usize = 1_000_000;
+const THREAD_COUNTS: [usize; 5] = [1, 2, 4, 8, 16];
+
+type Bench = (&'static str, fn(usize) -> Duration);
+
+/// 每个线程调用 INCREMENTS_PER_THREAD 次 inc，返回总耗时
+fn run<C: Send + Sync + 'static>(counter: C, threads: usize, inc: fn(&C)) -> Duration {
+    let counter = Arc::new(counter);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..INCREMENTS_PER_THREAD {
+                    inc(&counter);
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn bench_mutex(threads: usize) -> Duration {
+    run(Mutex::new(0i64), threads, |c| *c.lock().unwrap() += 1)
+}
+
+fn bench_atomic(threads: usize) -> Duration {
+    run(AtomicI64::new(0), threads, |c| {
+        c.fetch_add(1, Ordering::Relaxed);
+    })
+}
+
+fn bench_striped(threads: usize) -> Duration {
+    run(StripedCounter::new(), threads, StripedCounter::inc)
+}
+
+fn main() {
+    println!("=== 计数器扩展性对比 ===");
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    println!(
+        "每线程 {} 次自增，CPU 核数 {}（单核上看不出缓存行争用的差别）\n",
+        INCREMENTS_PER_THREAD, cpus
+    );
+
+    let benches: [Bench; 3] = [
+        ("Mutex<i64>", bench_mutex),
+        ("AtomicI64", bench_atomic),
+        ("StripedCounter", bench_striped),
+    ];
+
+    print!("{:>6}", "线程");
+    for (name, _) in &benches {
+        print!(" {:>18}", name);
+    }
+    println!("   (Mops/s)");
+    for threads in THREAD_COUNTS {
+        print!("{:>6}", threads);
+        for (_, bench) in &benches {
+            let elapsed = bench(threads);
+            let mops = (threads * INCREMENTS_PER_THREAD) as f64 / elapsed.as_secs_f64() / 1e6;
+            print!(" {:>18.2}", mops);
+        }
+        println!();
+    }
+
+    println!("\n提示: 请使用 --release 运行以获得有意义的数据");
+}
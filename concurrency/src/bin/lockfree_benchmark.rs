@@ -0,0 +1,166 @@
+use learning_concurrency::lockfree::{ArrayQueue, MsQueue, TreiberStack};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PRODUCERS: usize = 4;
+const CONSUMERS: usize = 4;
+const PER_PRODUCER: usize = 200_000;
+
+type Bench = (&'static str, fn() -> Duration);
+
+/// 通用的 MPMC 吞吐测试：
+/// 生产者各推入 PER_PRODUCER 个元素，消费者不断 try-pop 直到全部取完。
+/// 传入的是"工厂"闭包，每个线程拿到自己的 push/pop 句柄（例如各自持有一个 Sender）。
+fn run_mpmc<P, C>(new_producer: impl Fn() -> P, new_consumer: impl Fn() -> C) -> Duration
+where
+    P: FnMut(usize) + Send + 'static,
+    C: FnMut() -> Option<usize> + Send + 'static,
+{
+    let total = PRODUCERS * PER_PRODUCER;
+    let consumed = Arc::new(AtomicUsize::new(0));
+    // 所有线程就绪后再统一开跑，排除线程创建的开销
+    let barrier = Arc::new(Barrier::new(PRODUCERS + CONSUMERS + 1));
+    let mut handles = vec![];
+
+    for _ in 0..PRODUCERS {
+        let mut push = new_producer();
+        let barrier = Arc::clone(&barrier);
+        handles.push(thread::spawn(move || {
+            barrier.wait();
+            for i in 0..PER_PRODUCER {
+                push(i);
+            }
+        }));
+    }
+    for _ in 0..CONSUMERS {
+        let mut pop = new_consumer();
+        let consumed = Arc::clone(&consumed);
+        let barrier = Arc::clone(&barrier);
+        handles.push(thread::spawn(move || {
+            barrier.wait();
+            while consumed.load(Ordering::Relaxed) < total {
+                match pop() {
+                    Some(_) => {
+                        consumed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // 让出 CPU：核数少时空转会饿死生产者
+                    None => thread::yield_now(),
+                }
+            }
+        }));
+    }
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn bench_treiber_stack() -> Duration {
+    let stack = Arc::new(TreiberStack::new());
+    run_mpmc(
+        || {
+            let s = Arc::clone(&stack);
+            move |v| s.push(v)
+        },
+        || {
+            let s = Arc::clone(&stack);
+            move || s.pop()
+        },
+    )
+}
+
+fn bench_ms_queue() -> Duration {
+    let q = Arc::new(MsQueue::new());
+    run_mpmc(
+        || {
+            let q = Arc::clone(&q);
+            move |v| q.push(v)
+        },
+        || {
+            let q = Arc::clone(&q);
+            move || q.pop()
+        },
+    )
+}
+
+fn bench_array_queue() -> Duration {
+    let q = Arc::new(ArrayQueue::new(1024));
+    run_mpmc(
+        || {
+            let q = Arc::clone(&q);
+            move |v| {
+                let mut item = v;
+                // 有界队列：满了就让出 CPU 等消费者
+                while let Err(back) = q.push(item) {
+                    item = back;
+                    thread::yield_now();
+                }
+            }
+        },
+        || {
+            let q = Arc::clone(&q);
+            move || q.pop()
+        },
+    )
+}
+
+fn bench_mutex_vecdeque() -> Duration {
+    let q = Arc::new(Mutex::new(VecDeque::new()));
+    run_mpmc(
+        || {
+            let q = Arc::clone(&q);
+            move |v| q.lock().unwrap().push_back(v)
+        },
+        || {
+            let q = Arc::clone(&q);
+            move || q.lock().unwrap().pop_front()
+        },
+    )
+}
+
+fn bench_std_mpsc() -> Duration {
+    let (tx, rx) = mpsc::channel();
+    // mpsc 只允许一个接收者，多消费者只能共享一把锁后的 Receiver
+    let rx = Arc::new(Mutex::new(rx));
+    run_mpmc(
+        || {
+            let tx = tx.clone();
+            move |v| tx.send(v).unwrap()
+        },
+        || {
+            let rx = Arc::clone(&rx);
+            move || rx.lock().unwrap().try_recv().ok()
+        },
+    )
+}
+
+fn main() {
+    println!("=== 无锁数据结构性能对比 ===");
+    println!(
+        "{} 生产者 / {} 消费者，每个生产者 {} 个元素\n",
+        PRODUCERS, CONSUMERS, PER_PRODUCER
+    );
+
+    let benches: [Bench; 5] = [
+        ("TreiberStack", bench_treiber_stack),
+        ("MsQueue", bench_ms_queue),
+        ("ArrayQueue(1024)", bench_array_queue),
+        ("Mutex<VecDeque>", bench_mutex_vecdeque),
+        ("std::sync::mpsc", bench_std_mpsc),
+    ];
+
+    let total = (PRODUCERS * PER_PRODUCER) as f64;
+    println!("{:<18} {:>12} {:>16}", "实现", "耗时", "吞吐 (Mops/s)");
+    for (name, bench) in benches {
+        let elapsed = bench();
+        let mops = total / elapsed.as_secs_f64() / 1e6;
+        println!("{:<18} {:>12.2?} {:>16.2}", name, elapsed, mops);
+    }
+    println!("\n提示: 请使用 --release 运行以获得有意义的数据");
+}
@@ -0,0 +1,81 @@
+use learning_concurrency::pipeline::Pipeline;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Record {
+    id: u32,
+    name: String,
+    score: u32,
+}
+
+/// 模拟一行 CSV 文本
+fn raw_lines() -> impl Iterator<Item = String> + Send {
+    (1..=12).map(|id| format!("{},user{},{}", id, id, id * 7 % 100))
+}
+
+fn parse(line: String) -> Result<Record, String> {
+    let mut parts = line.split(',');
+    let (Some(id), Some(name), Some(score)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!("字段不足: {}", line));
+    };
+    Ok(Record {
+        id: id
+            .parse()
+            .map_err(|e| format!("id 非法 ({}): {}", e, line))?,
+        name: name.to_string(),
+        score: score
+            .parse()
+            .map_err(|e| format!("分数非法 ({}): {}", e, line))?,
+    })
+}
+
+fn enrich(mut record: Record) -> Result<Record, String> {
+    // 模拟一次较慢的外部查询
+    std::thread::sleep(Duration::from_millis(20));
+    record.name = record.name.to_uppercase();
+    Ok(record)
+}
+
+fn example_ordered() {
+    let start = Instant::now();
+    let result = Pipeline::source(raw_lines())
+        .capacity(4)
+        .stage(2, parse)
+        .stage(4, enrich)
+        .sink(|r| {
+            println!("  #{:<2} {:<7} {:>3}", r.id, r.name, r.score);
+            Ok(())
+        });
+    println!(
+        "结果: {:?}，耗时 {:?}（enrich 4 线程并行）",
+        result,
+        start.elapsed()
+    );
+}
+
+fn example_error_stops_pipeline() {
+    let lines = raw_lines().map(|l| {
+        if l.starts_with("5,") {
+            "5,坏行".to_string()
+        } else {
+            l
+        }
+    });
+    let result = Pipeline::source(lines)
+        .unordered()
+        .stage(2, parse)
+        .stage(4, enrich)
+        .collect();
+    match result {
+        Ok(records) => println!("意外成功: {} 条", records.len()),
+        Err(e) => println!("流水线已停止: {}", e),
+    }
+}
+
+fn main() {
+    println!("=== 示例: 保序的多级流水线 ===");
+    example_ordered();
+
+    println!("\n=== 示例: 某一级出错时整条流水线停止 ===");
+    example_error_stops_pipeline();
+}
@@ -0,0 +1,216 @@
+//! 读多写少场景：RwLock 与 SeqLock / SnapshotCell 的读吞吐对比
+//!
+//! 用法（所有参数都可省略）：
+//!
+//! ```text
+//! cargo run --release --bin read_mostly_benchmark -- \
+//!     --threads 1,2,4,8 --write-every-us 100 --millis 300
+//! ```
+//!
+//! - `--threads`：逗号分隔的读线程数列表，每个值单独跑一轮
+//! - `--write-every-us`：唯一的写线程每隔多少微秒更新一次（0 表示不停地写）
+//! - `--millis`：每轮持续的时间
+//!
+//! 受保护的数据是 4 个总是一起更新的 u64，读者每次都会校验它们是否一致。
+
+use learning_concurrency::rwlock;
+use learning_concurrency::seqlock::SeqLock;
+use learning_concurrency::snapshot_cell::SnapshotCell;
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Data = [u64; 4];
+
+#[derive(Debug, Clone)]
+struct Config {
+    threads: Vec<usize>,
+    write_every: Duration,
+    run_for: Duration,
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config {
+        threads: vec![1, 2, 4, 8],
+        write_every: Duration::from_micros(100),
+        run_for: Duration::from_millis(300),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("参数 {} 缺少取值", arg))?;
+        let parse = |v: &str| {
+            v.parse::<u64>()
+                .map_err(|e| format!("{} 的取值 {:?} 无效: {}", arg, v, e))
+        };
+        match arg.as_str() {
+            "--threads" => {
+                config.threads = value
+                    .split(',')
+                    .map(|v| parse(v).map(|n| n as usize))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--write-every-us" => config.write_every = Duration::from_micros(parse(&value)?),
+            "--millis" => config.run_for = Duration::from_millis(parse(&value)?),
+            _ => return Err(format!("未知参数: {}", arg)),
+        }
+    }
+    if config.threads.contains(&0) || config.run_for.is_zero() {
+        return Err("--threads 和 --millis 必须大于 0".to_string());
+    }
+    Ok(config)
+}
+
+trait Target: Send + Sync {
+    fn read(&self) -> Data;
+    fn write(&self, value: u64);
+}
+
+impl Target for RwLock<Data> {
+    fn read(&self) -> Data {
+        *RwLock::read(self).unwrap()
+    }
+    fn write(&self, value: u64) {
+        *RwLock::write(self).unwrap() = [value; 4];
+    }
+}
+
+impl Target for rwlock::RwLock<Data> {
+    fn read(&self) -> Data {
+        *rwlock::RwLock::read(self)
+    }
+    fn write(&self, value: u64) {
+        *rwlock::RwLock::write(self) = [value; 4];
+    }
+}
+
+impl Target for SeqLock<Data> {
+    fn read(&self) -> Data {
+        SeqLock::read(self)
+    }
+    fn write(&self, value: u64) {
+        SeqLock::write(self, [value; 4]);
+    }
+}
+
+/// 每次读取都克隆 Arc（引用计数上仍有一次共享写）
+struct SnapshotLoad(SnapshotCell<Data>);
+
+impl Target for SnapshotLoad {
+    fn read(&self) -> Data {
+        *self.0.load()
+    }
+    fn write(&self, value: u64) {
+        self.0.store([value; 4]);
+    }
+}
+
+/// 在 pin 状态下直接读，不碰引用计数
+struct SnapshotRead(SnapshotCell<Data>);
+
+impl Target for SnapshotRead {
+    fn read(&self) -> Data {
+        self.0.read(|data| *data)
+    }
+    fn write(&self, value: u64) {
+        self.0.store([value; 4]);
+    }
+}
+
+type Factory = fn() -> Arc<dyn Target>;
+
+const TARGETS: [(&str, Factory); 5] = [
+    ("std RwLock", || Arc::new(RwLock::new([0; 4]))),
+    ("RwLock", || Arc::new(rwlock::RwLock::new([0; 4]))),
+    ("SeqLock", || Arc::new(SeqLock::new([0; 4]))),
+    ("Snapshot load", || {
+        Arc::new(SnapshotLoad(SnapshotCell::new([0; 4])))
+    }),
+    ("Snapshot read", || {
+        Arc::new(SnapshotRead(SnapshotCell::new([0; 4])))
+    }),
+];
+
+/// 返回 (读线程总读取次数, 写入次数, 实际耗时)
+fn run(target: Arc<dyn Target>, readers: usize, config: &Config) -> (u64, u64, Duration) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let barrier = Arc::new(Barrier::new(readers + 2));
+
+    let reader_handles: Vec<_> = (0..readers)
+        .map(|_| {
+            let (target, stop, barrier) =
+                (Arc::clone(&target), Arc::clone(&stop), Arc::clone(&barrier));
+            thread::spawn(move || {
+                barrier.wait();
+                let mut reads = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let [a, b, c, d] = target.read();
+                    assert!(a == b && b == c && c == d, "读到了不一致的数据");
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+
+    let writer = {
+        let (target, stop, barrier) =
+            (Arc::clone(&target), Arc::clone(&stop), Arc::clone(&barrier));
+        let every = config.write_every;
+        thread::spawn(move || {
+            barrier.wait();
+            let mut writes = 0u64;
+            while !stop.load(Ordering::Relaxed) {
+                writes += 1;
+                target.write(writes);
+                if !every.is_zero() {
+                    thread::sleep(every);
+                }
+            }
+            writes
+        })
+    };
+
+    barrier.wait();
+    let start = Instant::now();
+    thread::sleep(config.run_for);
+    stop.store(true, Ordering::Relaxed);
+    let reads = reader_handles.into_iter().map(|h| h.join().unwrap()).sum();
+    let writes = writer.join().unwrap();
+    (reads, writes, start.elapsed())
+}
+
+fn main() {
+    let config = parse_args().unwrap_or_else(|e| {
+        eprintln!("错误: {}", e);
+        process::exit(2);
+    });
+
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    println!("=== 读多写少: 读吞吐对比 ===");
+    println!(
+        "CPU 核数 {}，写线程每 {:?} 写一次，每轮 {:?}（单核上读者之间没有真正的并行）\n",
+        cpus, config.write_every, config.run_for
+    );
+
+    print!("{:>6}", "读线程");
+    for (name, _) in &TARGETS {
+        print!(" {:>14}", name);
+    }
+    println!("   (读 Mops/s，括号内为写入次数)");
+    for &readers in &config.threads {
+        print!("{:>6}", readers);
+        for (_, factory) in &TARGETS {
+            let (reads, writes, elapsed) = run(factory(), readers, &config);
+            let mops = reads as f64 / elapsed.as_secs_f64() / 1e6;
+            print!(" {:>14}", format!("{:.2} ({})", mops, writes));
+        }
+        println!();
+    }
+
+    println!("\n提示: 请使用 --release 运行以获得有意义的数据");
+}
@@ -1,61 +1,321 @@
-use std::sync::{Arc, Mutex, RwLock};
+//! 锁争用基准测试：Mutex / RwLock / SpinLock / 原子变量
+//!
+//! 用法（所有参数都可省略）：
+//!
+//! ```text
+//! cargo run --release --bin rwlock_performance -- \
+//!     --threads 1,2,4,8 --read-ratio 90 --cs-len 16 --data-size 1024 --ops 20000 [--csv]
+//! ```
+//!
+//! - `--threads`：逗号分隔的线程数列表，每个值单独跑一轮
+//! - `--read-ratio`：读操作所占百分比 (0-100)
+//! - `--cs-len`：每次操作在临界区内访问的元素个数，用来模拟临界区长度
+//! - `--data-size`：受保护的 `Vec<u64>` 长度
+//! - `--ops`：每个线程执行的操作数
+//! - `--csv`：输出 CSV 而不是表格
+//!
+//! 计时期间线程不做任何打印，每次操作的延迟记录在线程本地，结束后再合并计算分位数。
+
+use learning_concurrency::rwlock::{self, Policy};
+use learning_concurrency::spinlock::SpinLock;
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Barrier, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-/// 通用的性能测试函数，负责线程调度和计时
-fn run_benchmark<F>(label: &str, num_threads: usize, iters: usize, task: F) -> Duration
-where
-    F: Fn() + Send + Sync + 'static + Clone,
-{
-    let start = Instant::now();
-    let mut handles = vec![];
-    for i in 0..num_threads {
-        let task = task.clone();
-        let label = label.to_string();
-        let handle = thread::spawn(move || {
-            (0..iters).for_each(|_| task());
-            println!("{} 写者 {} 完成", label, i);
-        });
-        handles.push(handle);
+#[derive(Debug, Clone)]
+struct Config {
+    threads: Vec<usize>,
+    read_ratio: u32,
+    cs_len: usize,
+    data_size: usize,
+    ops: usize,
+    csv: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            threads: vec![1, 2, 4, 8],
+            read_ratio: 90,
+            cs_len: 16,
+            data_size: 1024,
+            ops: 20_000,
+            csv: false,
+        }
     }
+}
 
-    for handle in handles {
-        handle.join().unwrap();
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--csv" {
+            config.csv = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("参数 {} 缺少取值", arg))?;
+        let parse = |v: &str| {
+            v.parse::<usize>()
+                .map_err(|e| format!("{} 的取值 {:?} 无效: {}", arg, v, e))
+        };
+        match arg.as_str() {
+            "--threads" => {
+                config.threads = value.split(',').map(parse).collect::<Result<_, _>>()?;
+            }
+            "--read-ratio" => {
+                let ratio = parse(&value)?;
+                if ratio > 100 {
+                    return Err("--read-ratio 必须在 0 到 100 之间".to_string());
+                }
+                config.read_ratio = ratio as u32;
+            }
+            "--cs-len" => config.cs_len = parse(&value)?,
+            "--data-size" => config.data_size = parse(&value)?,
+            "--ops" => config.ops = parse(&value)?,
+            _ => return Err(format!("未知参数: {}", arg)),
+        }
+    }
+    if config.data_size == 0 || config.cs_len == 0 || config.ops == 0 || config.threads.contains(&0)
+    {
+        return Err("--threads、--cs-len、--data-size 和 --ops 必须大于 0".to_string());
     }
+    Ok(config)
+}
 
-    start.elapsed()
+/// 被测对象：读操作对 `len` 个连续元素求和，写操作把它们各加 1
+trait Target: Send + Sync {
+    fn read(&self, start: usize, len: usize) -> u64;
+    fn write(&self, start: usize, len: usize);
 }
 
-/// 比较 RwLock 和 Mutex 在高频率读操作场景下的性能差异
-fn compare_rwlock_vs_mutex_performance() {
-    let rwlock = Arc::new(RwLock::new(0));
-    let mutex = Arc::new(Mutex::new(0));
-    let threads = 10;
-    let iterations = 1000;
+fn sum_range(data: &[u64], start: usize, len: usize) -> u64 {
+    (0..len).map(|i| data[(start + i) % data.len()]).sum()
+}
 
-    // 1. RwLock 性能测试
-    let rwlock_duration = run_benchmark("RwLock", threads, iterations, {
-        let lock = Arc::clone(&rwlock);
-        move || {
-            let _guard = lock.read().unwrap();
-        }
-    });
+fn add_range(data: &mut [u64], start: usize, len: usize) {
+    let n = data.len();
+    for i in 0..len {
+        data[(start + i) % n] += 1;
+    }
+}
+
+impl Target for Mutex<Vec<u64>> {
+    fn read(&self, start: usize, len: usize) -> u64 {
+        sum_range(&self.lock().unwrap(), start, len)
+    }
+    fn write(&self, start: usize, len: usize) {
+        add_range(&mut self.lock().unwrap(), start, len)
+    }
+}
+
+impl Target for RwLock<Vec<u64>> {
+    fn read(&self, start: usize, len: usize) -> u64 {
+        sum_range(&self.read().unwrap(), start, len)
+    }
+    fn write(&self, start: usize, len: usize) {
+        add_range(&mut self.write().unwrap(), start, len)
+    }
+}
+
+impl Target for rwlock::RwLock<Vec<u64>> {
+    fn read(&self, start: usize, len: usize) -> u64 {
+        sum_range(&self.read(), start, len)
+    }
+    fn write(&self, start: usize, len: usize) {
+        add_range(&mut self.write(), start, len)
+    }
+}
 
-    // 2. Mutex 性能测试
-    let mutex_duration = run_benchmark("Mutex", threads, iterations, {
-        let lock = Arc::clone(&mutex);
-        move || {
-            let _guard = lock.lock().unwrap();
+impl Target for SpinLock<Vec<u64>> {
+    fn read(&self, start: usize, len: usize) -> u64 {
+        sum_range(&self.lock(), start, len)
+    }
+    fn write(&self, start: usize, len: usize) {
+        add_range(&mut self.lock(), start, len)
+    }
+}
+
+/// 原子变量：没有锁，但多元素的读写也不再是一个整体（读者可能看到写了一半的范围）
+impl Target for Vec<AtomicU64> {
+    fn read(&self, start: usize, len: usize) -> u64 {
+        (0..len)
+            .map(|i| self[(start + i) % self.len()].load(Ordering::Relaxed))
+            .sum()
+    }
+    fn write(&self, start: usize, len: usize) {
+        for i in 0..len {
+            self[(start + i) % self.len()].fetch_add(1, Ordering::Relaxed);
         }
-    });
+    }
+}
 
-    println!("\n结果对比:");
-    println!("RwLock 读操作总耗时: {:?}", rwlock_duration);
-    println!("Mutex 读操作总耗时: {:?}", mutex_duration);
-    println!("结论: 在多读者场景下，RwLock 由于允许并发读取，性能通常优于 Mutex。");
+type Factory = fn(usize) -> Arc<dyn Target>;
+
+const TARGETS: [(&str, Factory); 7] = [
+    ("Mutex", |n| Arc::new(Mutex::new(vec![0u64; n]))),
+    ("std RwLock", |n| Arc::new(RwLock::new(vec![0u64; n]))),
+    ("RwLock(reader)", |n| {
+        Arc::new(rwlock::RwLock::with_policy(
+            vec![0u64; n],
+            Policy::ReaderPreferring,
+        ))
+    }),
+    ("RwLock(writer)", |n| {
+        Arc::new(rwlock::RwLock::with_policy(
+            vec![0u64; n],
+            Policy::WriterPreferring,
+        ))
+    }),
+    ("RwLock(phase-fair)", |n| {
+        Arc::new(rwlock::RwLock::with_policy(
+            vec![0u64; n],
+            Policy::PhaseFair,
+        ))
+    }),
+    ("SpinLock", |n| Arc::new(SpinLock::new(vec![0u64; n]))),
+    ("Atomics", |n| {
+        Arc::new((0..n).map(|_| AtomicU64::new(0)).collect::<Vec<_>>())
+    }),
+];
+
+struct Stats {
+    throughput: f64,
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+    p999: Duration,
+    max: Duration,
+}
+
+/// 简单的 xorshift，避免在热循环里引入外部随机数依赖
+fn next_rand(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn run_benchmark(target: Arc<dyn Target>, threads: usize, config: &Config) -> Stats {
+    // 所有线程就绪后同时开始，避免把线程创建时间算进去
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            let target = Arc::clone(&target);
+            let barrier = Arc::clone(&barrier);
+            let config = config.clone();
+            thread::spawn(move || {
+                let mut rng = 0x9E37_79B9_7F4A_7C15 ^ (i as u64 + 1);
+                let mut latencies = Vec::with_capacity(config.ops);
+                let mut sink = 0u64;
+                barrier.wait();
+                let started = Instant::now();
+                for _ in 0..config.ops {
+                    let r = next_rand(&mut rng);
+                    let start = r as usize % config.data_size;
+                    let begin = Instant::now();
+                    if (r >> 32) % 100 < config.read_ratio as u64 {
+                        sink = sink.wrapping_add(target.read(start, config.cs_len));
+                    } else {
+                        target.write(start, config.cs_len);
+                    }
+                    latencies.push(begin.elapsed());
+                }
+                std::hint::black_box(sink);
+                (started, Instant::now(), latencies)
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    // 墙钟时间取最早开始到最晚结束，由工作线程自己记录，主线程被调度的早晚不影响结果
+    let mut first_start = None::<Instant>;
+    let mut last_end = None::<Instant>;
+    let mut latencies = Vec::with_capacity(threads * config.ops);
+    for handle in handles {
+        let (started, ended, thread_latencies) = handle.join().unwrap();
+        first_start = Some(first_start.map_or(started, |s| s.min(started)));
+        last_end = Some(last_end.map_or(ended, |e| e.max(ended)));
+        latencies.extend(thread_latencies);
+    }
+    let elapsed = last_end.unwrap() - first_start.unwrap();
+
+    latencies.sort_unstable();
+    let percentile = |p: f64| {
+        let idx = ((latencies.len() as f64 * p) as usize).min(latencies.len() - 1);
+        latencies[idx]
+    };
+    Stats {
+        throughput: latencies.len() as f64 / elapsed.as_secs_f64(),
+        p50: percentile(0.50),
+        p90: percentile(0.90),
+        p99: percentile(0.99),
+        p999: percentile(0.999),
+        max: *latencies.last().unwrap(),
+    }
 }
 
 fn main() {
-    println!("=== RwLock<T> 性能对比示例 ===");
-    compare_rwlock_vs_mutex_performance();
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(2);
+        }
+    };
+
+    if config.csv {
+        println!("primitive,threads,read_ratio,cs_len,data_size,ops_per_sec,p50_ns,p90_ns,p99_ns,p999_ns,max_ns");
+    } else {
+        println!("=== 锁争用基准测试 ===");
+        println!(
+            "读比例 {}%，临界区 {} 个元素，数据 {} 个元素，每线程 {} 次操作\n",
+            config.read_ratio, config.cs_len, config.data_size, config.ops
+        );
+        println!(
+            "{:<18} {:>7} {:>14} {:>10} {:>10} {:>10} {:>10} {:>12}",
+            "primitive", "threads", "ops/s", "p50", "p90", "p99", "p99.9", "max"
+        );
+    }
+
+    for &threads in &config.threads {
+        for (name, factory) in TARGETS {
+            let stats = run_benchmark(factory(config.data_size), threads, &config);
+            if config.csv {
+                println!(
+                    "{},{},{},{},{},{:.0},{},{},{},{},{}",
+                    name,
+                    threads,
+                    config.read_ratio,
+                    config.cs_len,
+                    config.data_size,
+                    stats.throughput,
+                    stats.p50.as_nanos(),
+                    stats.p90.as_nanos(),
+                    stats.p99.as_nanos(),
+                    stats.p999.as_nanos(),
+                    stats.max.as_nanos()
+                );
+            } else {
+                println!(
+                    "{:<18} {:>7} {:>14.0} {:>10?} {:>10?} {:>10?} {:>10?} {:>12?}",
+                    name,
+                    threads,
+                    stats.throughput,
+                    stats.p50,
+                    stats.p90,
+                    stats.p99,
+                    stats.p999,
+                    stats.max
+                );
+            }
+        }
+        if !config.csv {
+            println!();
+        }
+    }
 }
@@ -1,3 +1,7 @@
+use learning_concurrency::assert::{assert_send, assert_sync};
+use learning_concurrency::assert_not_impl;
+use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
 
@@ -7,17 +11,16 @@ use std::thread;
 fn example_explicit_bounds() {
     println!("--- 示例 1: 使用泛型约束进行显式检查 ---");
 
-    fn is_send<T: Send>(_value: &T) {
-        println!("类型实现过 Send");
-    }
-
-    fn is_sync<T: Sync>(_value: &T) {
-        println!("类型实现了 Sync");
-    }
+    // 库里的 assert_send / assert_sync 是 const fn，类型不满足约束时直接编译失败
+    assert_send::<i32>();
+    println!("i32 实现了 Send");
+    assert_sync::<Arc<Vec<i32>>>();
+    println!("Arc<Vec<i32>> 实现了 Sync");
 
-    let data = 42;
-    is_send(&data);
-    is_sync(&data);
+    // 也可以在编译期断言某个类型"没有"实现 Send / Sync
+    assert_not_impl!(Rc<i32>: Send);
+    assert_not_impl!(Cell<i32>: Sync);
+    println!("Rc 不是 Send，Cell 不是 Sync（编译期已验证）");
 }
 
 /// 2. 在线程派发中使用 Trait Bounds
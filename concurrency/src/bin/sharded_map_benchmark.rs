@@ -0,0 +1,107 @@
+use learning_concurrency::sharded_map::ShardedMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Barrier, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const KEYS: u64 = 10_000;
+const OPS_PER_THREAD: usize = 200_000;
+/// 每 100 次操作中写操作的次数
+const WRITE_PERCENT: u64 = 20;
+const THREAD_COUNTS: [usize; 5] = [1, 2, 4, 8, 16];
+
+/// 对两种实现统一的操作接口
+trait Map: Send + Sync + 'static {
+    fn get(&self, key: u64) -> Option<u64>;
+    fn insert(&self, key: u64, value: u64);
+}
+
+impl Map for ShardedMap<u64, u64> {
+    fn get(&self, key: u64) -> Option<u64> {
+        ShardedMap::get(self, &key)
+    }
+    fn insert(&self, key: u64, value: u64) {
+        ShardedMap::insert(self, key, value);
+    }
+}
+
+impl Map for RwLock<HashMap<u64, u64>> {
+    fn get(&self, key: u64) -> Option<u64> {
+        self.read().unwrap().get(&key).copied()
+    }
+    fn insert(&self, key: u64, value: u64) {
+        self.write().unwrap().insert(key, value);
+    }
+}
+
+/// 每个线程执行 OPS_PER_THREAD 次随机的读/写，返回总耗时
+fn run(map: Arc<dyn Map>, threads: usize) -> Duration {
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            let map = Arc::clone(&map);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                // xorshift 伪随机数
+                let mut rng = 0x2545_F491_4F6C_DD1D ^ (i as u64 + 1);
+                let mut hits = 0u64;
+                barrier.wait();
+                for _ in 0..OPS_PER_THREAD {
+                    rng ^= rng << 13;
+                    rng ^= rng >> 7;
+                    rng ^= rng << 17;
+                    let key = rng % KEYS;
+                    if (rng >> 40) % 100 < WRITE_PERCENT {
+                        map.insert(key, rng);
+                    } else if map.get(key).is_some() {
+                        hits += 1;
+                    }
+                }
+                std::hint::black_box(hits);
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn prefilled<M: Map>(map: M) -> Arc<dyn Map> {
+    for key in (0..KEYS).step_by(2) {
+        map.insert(key, key);
+    }
+    Arc::new(map)
+}
+
+fn main() {
+    println!("=== ShardedMap 与 RwLock<HashMap> 扩展性对比 ===");
+    // 单核机器上线程只能轮流运行，分片带来的并行度无从体现
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    println!(
+        "{} 个键，{}% 写，每线程 {} 次操作，CPU 核数 {}\n",
+        KEYS, WRITE_PERCENT, OPS_PER_THREAD, cpus
+    );
+
+    println!(
+        "{:>6} {:>20} {:>20} {:>8}",
+        "线程", "RwLock<HashMap>", "ShardedMap", "加速比"
+    );
+    for threads in THREAD_COUNTS {
+        let total = (threads * OPS_PER_THREAD) as f64;
+        let single = run(prefilled(RwLock::new(HashMap::new())), threads);
+        let sharded = run(prefilled(ShardedMap::new()), threads);
+        let mops = |d: Duration| total / d.as_secs_f64() / 1e6;
+        println!(
+            "{:>6} {:>14.2} Mops {:>14.2} Mops {:>7.2}x",
+            threads,
+            mops(single),
+            mops(sharded),
+            single.as_secs_f64() / sharded.as_secs_f64()
+        );
+    }
+    println!("\n提示: 请使用 --release 运行以获得有意义的数据");
+}
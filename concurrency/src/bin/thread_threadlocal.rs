@@ -2,7 +2,10 @@
 // 线程 (Threads) - 线程局部存储 (TLS)
 // ============================================================================
 
+use learning_concurrency::spawn_workers;
+use learning_concurrency::thread_local::{spawn_workers_reduce, ThreadLocal};
 use std::cell::Cell;
+use std::sync::Arc;
 use std::thread;
 
 thread_local! {
@@ -13,6 +16,8 @@ thread_local! {
 
 fn main() {
     show_isolation();
+    gather_per_thread();
+    reduce_with_spawn_workers();
 }
 
 fn show_isolation() {
@@ -39,3 +44,45 @@ fn update_tls(label: &str, val: i32) {
         println!("{}: 修改 = {}", label, tls.get());
     });
 }
+
+/// `thread_local!` 的值线程结束就没了；ThreadLocal<T> 是普通对象，
+/// 每个线程写自己的那份，结束后由持有者收集
+fn gather_per_thread() {
+    let counts = Arc::new(ThreadLocal::<Cell<i32>>::new());
+
+    spawn_workers(Arc::clone(&counts), 4, |counts, i| {
+        let local = counts.get_or_default();
+        for _ in 0..=i {
+            // 只有本线程访问这个 Cell，不需要锁也不需要原子操作
+            local.set(local.get() + 1);
+        }
+    });
+
+    // 所有工作线程都已结束，Arc 只剩这一个引用
+    let counts = Arc::try_unwrap(counts).expect("工作线程已全部结束");
+    // 线程编号会复用：先结束的线程留下的值可能被后启动的线程接着累加，
+    // 所以条目数可能少于线程数，但合计一定是 1+2+3+4
+    let values: Vec<i32> = counts.into_iter().map(Cell::into_inner).collect();
+    println!(
+        "收集各线程的值: {:?}，合计 {}",
+        values,
+        values.iter().sum::<i32>()
+    );
+}
+
+/// spawn_workers 的归约版本：直接拿到每个线程独占的累加器
+fn reduce_with_spawn_workers() {
+    let partial_sums = spawn_workers_reduce(Arc::new((1..=100).collect::<Vec<u64>>()), 4, {
+        |data: Arc<Vec<u64>>, i, sum: &mut u64| {
+            // 每个线程处理一段数据
+            for v in data.iter().skip(i * 25).take(25) {
+                *sum += v;
+            }
+        }
+    });
+    println!(
+        "各线程的部分和: {:?}，总和 {}",
+        partial_sums,
+        partial_sums.iter().sum::<u64>()
+    );
+}
@@ -0,0 +1,53 @@
+use learning_concurrency::timer::Timer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn example_delayed_and_cancel(timer: &Timer) {
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    for ms in [300u64, 100, 200] {
+        let tx = tx.clone();
+        timer.schedule_after(Duration::from_millis(ms), move || {
+            tx.send(format!("{}ms 的任务在 {:?} 执行", ms, start.elapsed()))
+                .unwrap();
+        });
+    }
+    let handle = timer.schedule_after(Duration::from_millis(150), || {
+        println!("不会执行：已被取消");
+    });
+    println!("取消 150ms 的任务: {}", handle.cancel());
+
+    drop(tx);
+    for msg in rx {
+        println!("{}", msg);
+    }
+}
+
+fn example_periodic(timer: &Timer) {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let handle = {
+        let runs = Arc::clone(&runs);
+        timer.schedule_periodic(Duration::ZERO, Duration::from_millis(50), move || {
+            let n = runs.fetch_add(1, Ordering::Relaxed) + 1;
+            println!("周期任务第 {} 次", n);
+        })
+    };
+    thread::sleep(Duration::from_millis(230));
+    handle.cancel();
+    println!("取消后共执行 {} 次", runs.load(Ordering::Relaxed));
+}
+
+fn main() {
+    // 一个定时线程 + 两个工作线程，代替到处 thread::sleep 的写法
+    let timer = Timer::new(2);
+
+    println!("=== 示例: 延迟任务与取消 ===");
+    example_delayed_and_cancel(&timer);
+
+    println!("\n=== 示例: 周期任务 ===");
+    example_periodic(&timer);
+
+    timer.shutdown();
+}
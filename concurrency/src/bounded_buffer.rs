@@ -0,0 +1,382 @@
+//! 多槽位的有界缓冲区（经典的"生产者-消费者"问题）
+//!
+//! 与只用一个 `Mutex<bool>` 的演示不同，这里同时允许最多 `capacity` 个元素在途，
+//! 并且用两个条件变量分别表示两种等待原因：
+//! - `not_full`：生产者等待"有空位"
+//! - `not_empty`：消费者等待"有数据"
+//!
+//! 如果只用一个条件变量，`notify_one` 可能恰好唤醒了同类线程（生产者唤醒生产者），
+//! 真正能继续的那一方却一直睡着——这就是"丢失唤醒"。两个条件变量从根本上避免了这个问题。
+//!
+//! 锁和条件变量来自 [`crate::model`]，开启 `model-check` 后 loom 会穷举唤醒顺序，丢失唤醒表现为死锁报告。
+
+use crate::model::sync::{Condvar, Mutex, MutexGuard};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// push 失败时把元素原样返回
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum PushError<T> {
+    /// 缓冲区已关闭
+    Closed(T),
+    /// 超时仍没有空位
+    Timeout(T),
+}
+
+impl<T> PushError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Closed(v) | PushError::Timeout(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Closed(_) => f.write_str("Closed(..)"),
+            PushError::Timeout(_) => f.write_str("Timeout(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Closed(_) => f.write_str("buffer is closed"),
+            PushError::Timeout(_) => f.write_str("timed out waiting for a free slot"),
+        }
+    }
+}
+
+impl<T> Error for PushError<T> {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PopError {
+    /// 缓冲区已关闭且数据已取完
+    Closed,
+    /// 超时仍没有数据
+    Timeout,
+}
+
+impl fmt::Display for PopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PopError::Closed => f.write_str("buffer is closed and empty"),
+            PopError::Timeout => f.write_str("timed out waiting for an item"),
+        }
+    }
+}
+
+impl Error for PopError {}
+
+struct Inner<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+pub struct BoundedBuffer<T> {
+    inner: Mutex<Inner<T>>,
+    not_full: Condvar,
+    not_empty: Condvar,
+    capacity: usize,
+}
+
+impl<T> BoundedBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "容量必须大于 0");
+        Self {
+            inner: Mutex::new(Inner {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
+            capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap()
+    }
+
+    /// 放入一个元素，缓冲区满时阻塞。缓冲区关闭后返回 `PushError::Closed`
+    pub fn push(&self, item: T) -> Result<(), PushError<T>> {
+        self.push_deadline(item, None)
+    }
+
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`push`](Self::push) 一样一直等
+    pub fn push_timeout(&self, item: T, timeout: Duration) -> Result<(), PushError<T>> {
+        self.push_deadline(item, Instant::now().checked_add(timeout))
+    }
+
+    /// 取出一个元素，缓冲区空时阻塞。关闭且取完后返回 None
+    pub fn pop(&self) -> Option<T> {
+        self.pop_deadline(None).ok()
+    }
+
+    /// 超时大到 `Instant` 表示不了时和 [`pop`](Self::pop) 一样一直等
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<T, PopError> {
+        self.pop_deadline(Instant::now().checked_add(timeout))
+    }
+
+    fn push_deadline(&self, item: T, deadline: Option<Instant>) -> Result<(), PushError<T>> {
+        let mut inner = self.lock();
+        // 必须用循环：醒来时空位可能已被其他生产者抢走，也可能是虚假唤醒
+        while !inner.closed && inner.items.len() >= self.capacity {
+            inner = match wait(&self.not_full, inner, deadline) {
+                Some(inner) => inner,
+                None => return Err(PushError::Timeout(item)),
+            };
+        }
+        if inner.closed {
+            return Err(PushError::Closed(item));
+        }
+        inner.items.push_back(item);
+        // 只需要唤醒一个消费者：新增的数据只够一个消费者取
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    fn pop_deadline(&self, deadline: Option<Instant>) -> Result<T, PopError> {
+        let mut inner = self.lock();
+        loop {
+            // 关闭后也要先把剩余数据取完
+            if let Some(item) = inner.items.pop_front() {
+                self.not_full.notify_one();
+                return Ok(item);
+            }
+            if inner.closed {
+                return Err(PopError::Closed);
+            }
+            inner = match wait(&self.not_empty, inner, deadline) {
+                Some(inner) => inner,
+                None => return Err(PopError::Timeout),
+            };
+        }
+    }
+
+    /// 关闭缓冲区并唤醒所有等待者：
+    /// 阻塞的生产者返回 `Closed`，阻塞的消费者取完剩余数据后返回 None
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_full.notify_all();
+        self.not_empty.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// 在条件变量上等待一次；到达 deadline 时返回 None
+fn wait<'a, T>(
+    cvar: &Condvar,
+    guard: MutexGuard<'a, T>,
+    deadline: Option<Instant>,
+) -> Option<MutexGuard<'a, T>> {
+    match deadline {
+        None => Some(cvar.wait(guard).unwrap()),
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            Some(cvar.wait_timeout(guard, deadline - now).unwrap().0)
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "model-check")))]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    #[test]
+    fn test_fifo_and_capacity() {
+        let buf = BoundedBuffer::new(2);
+        buf.push(1).unwrap();
+        buf.push(2).unwrap();
+        assert_eq!(buf.len(), 2);
+        assert_eq!(
+            buf.push_timeout(3, Duration::from_millis(10)),
+            Err(PushError::Timeout(3))
+        );
+        assert_eq!(buf.pop(), Some(1));
+        assert_eq!(buf.pop(), Some(2));
+        assert_eq!(
+            buf.pop_timeout(Duration::from_millis(10)),
+            Err(PopError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_close_drains_then_stops() {
+        let buf = BoundedBuffer::new(4);
+        buf.push("a").unwrap();
+        buf.close();
+        assert!(buf.is_closed());
+        assert_eq!(buf.push("b"), Err(PushError::Closed("b")));
+        assert_eq!(buf.pop(), Some("a"));
+        assert_eq!(buf.pop(), None);
+        assert_eq!(
+            buf.pop_timeout(Duration::from_secs(1)),
+            Err(PopError::Closed)
+        );
+    }
+
+    #[test]
+    fn test_huge_timeouts_wait_forever() {
+        let buf = Arc::new(BoundedBuffer::new(1));
+        let producer = {
+            let buf = Arc::clone(&buf);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                buf.push_timeout(1, Duration::MAX).unwrap();
+                buf.push_timeout(2, Duration::MAX).unwrap();
+            })
+        };
+        assert_eq!(buf.pop_timeout(Duration::MAX), Ok(1));
+        assert_eq!(buf.pop_timeout(Duration::MAX), Ok(2));
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn test_close_wakes_every_waiter() {
+        // 一个缓冲区上阻塞多个消费者，另一个已满的缓冲区上阻塞多个生产者
+        let empty = Arc::new(BoundedBuffer::<i32>::new(1));
+        let full = Arc::new(BoundedBuffer::new(1));
+        full.push(0).unwrap();
+
+        let mut handles = vec![];
+        for _ in 0..4 {
+            let empty = Arc::clone(&empty);
+            handles.push(thread::spawn(move || assert_eq!(empty.pop(), None)));
+            let full = Arc::clone(&full);
+            handles.push(thread::spawn(move || {
+                assert_eq!(full.push(1), Err(PushError::Closed(1)));
+            }));
+        }
+        thread::sleep(Duration::from_millis(20));
+        empty.close();
+        full.close();
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    /// 多生产者、多消费者、容量为 1：最容易暴露丢失唤醒的配置。
+    /// 若出现丢失唤醒，某些线程会永久阻塞，用看门狗把"卡死"变成测试失败。
+    #[test]
+    fn stress_no_lost_wakeups() {
+        const PRODUCERS: usize = 8;
+        const CONSUMERS: usize = 8;
+        const PER_PRODUCER: usize = 2_000;
+
+        for capacity in [1, 3] {
+            let buf = Arc::new(BoundedBuffer::new(capacity));
+            let sum = Arc::new(AtomicUsize::new(0));
+            let (done_tx, done_rx) = mpsc::channel();
+
+            let worker = {
+                let buf = Arc::clone(&buf);
+                let sum = Arc::clone(&sum);
+                thread::spawn(move || {
+                    spawn_workers(
+                        (Arc::clone(&buf), sum),
+                        PRODUCERS + CONSUMERS,
+                        |(buf, sum), i| {
+                            if i < PRODUCERS {
+                                for v in 1..=PER_PRODUCER {
+                                    buf.push(v).unwrap();
+                                }
+                            } else {
+                                // 每个消费者恰好取 PER_PRODUCER 个，不依赖 close 结束
+                                for _ in 0..PER_PRODUCER {
+                                    let v = buf.pop().unwrap();
+                                    sum.fetch_add(v, Ordering::Relaxed);
+                                }
+                            }
+                        },
+                    );
+                    done_tx.send(()).unwrap();
+                })
+            };
+
+            done_rx
+                .recv_timeout(Duration::from_secs(30))
+                .expect("疑似丢失唤醒：生产者/消费者卡死");
+            worker.join().unwrap();
+            assert_eq!(
+                sum.load(Ordering::Relaxed),
+                PRODUCERS * PER_PRODUCER * (PER_PRODUCER + 1) / 2
+            );
+            assert!(buf.is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+
+    /// 容量为 1 的生产者/消费者：任何一种交错下都不会丢失唤醒（loom 会把永久阻塞报告为死锁）
+    #[test]
+    fn model_producer_consumer() {
+        use crate::model::{self, sync::Arc, thread};
+
+        model::check(|| {
+            let buf = Arc::new(BoundedBuffer::new(1));
+            let producer = {
+                let buf = Arc::clone(&buf);
+                thread::spawn(move || {
+                    buf.push(1).unwrap();
+                    buf.push(2).unwrap();
+                })
+            };
+            assert_eq!(buf.pop(), Some(1));
+            assert_eq!(buf.pop(), Some(2));
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn model_close_wakes_blocked_threads() {
+        use crate::model::{self, sync::Arc, thread};
+
+        model::check(|| {
+            let buf = Arc::new(BoundedBuffer::new(1));
+            let consumer = {
+                let buf = Arc::clone(&buf);
+                thread::spawn(move || {
+                    let mut got = Vec::new();
+                    while let Some(v) = buf.pop() {
+                        got.push(v);
+                    }
+                    got
+                })
+            };
+            buf.push(7).unwrap();
+            buf.close();
+            // 关闭前放进去的数据一定会先被取走，之后消费者被唤醒并退出
+            assert_eq!(consumer.join().unwrap(), [7]);
+        });
+    }
+}
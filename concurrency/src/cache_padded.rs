@@ -0,0 +1,42 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// 按缓存行 (64 字节) 对齐的包装器
+///
+/// 多个线程频繁写入的原子变量如果挤在同一个缓存行里，
+/// 即使逻辑上互不相干，也会因为缓存一致性协议互相"踢"对方的缓存（伪共享, false sharing）。
+/// 把它们各自放进独立的缓存行可以消除这种争用。
+#[derive(Default)]
+#[repr(align(64))]
+pub struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    pub const fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CachePadded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
@@ -0,0 +1,947 @@
+//! 基于 Mutex + Condvar 的多生产者多消费者通道
+//!
+//! 与 `std::sync::mpsc` 的区别：
+//! - `Receiver` 可以 clone，多个消费者竞争同一个队列（每条消息只会被一个消费者拿到）
+//! - 支持有界 / 无界两种模式，以及发送端的超时
+//! - 可以通过 [`Select`] 或 [`select!`](crate::select) 同时等待多个接收端
+//!
+//! 错误类型直接复用 `std::sync::mpsc` 中的定义，方便从标准库迁移过来。
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// `send_timeout` 的错误：超时或所有接收端都已断开，消息原样返回
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    Timeout(T),
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(v) | SendTimeoutError::Disconnected(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => f.write_str("Timeout(..)"),
+            SendTimeoutError::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => f.write_str("timed out waiting on send operation"),
+            SendTimeoutError::Disconnected(_) => f.write_str("sending on a disconnected channel"),
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+/// 一个简单的"唤醒信号"：Select 在等待时把它挂到每个被监听的通道上
+struct Signal {
+    fired: Mutex<bool>,
+    cvar: Condvar,
+}
+
+impl Signal {
+    fn new() -> Self {
+        Self {
+            fired: Mutex::new(false),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn notify(&self) {
+        *self.fired.lock().unwrap() = true;
+        self.cvar.notify_one();
+    }
+
+    /// 等待信号被触发，或到达 deadline。返回 false 表示超时
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut fired = self.fired.lock().unwrap();
+        while !*fired {
+            match deadline {
+                None => fired = self.cvar.wait(fired).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    fired = self.cvar.wait_timeout(fired, deadline - now).unwrap().0;
+                }
+            }
+        }
+        // 消费掉这次通知，下一轮重新等待
+        *fired = false;
+        true
+    }
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receivers: usize,
+    // 正在 select 中等待这个通道的线程
+    watchers: Vec<Arc<Signal>>,
+}
+
+impl<T> State<T> {
+    fn notify_watchers(&self) {
+        for signal in &self.watchers {
+            signal.notify();
+        }
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    // 队列从空变为非空，或所有发送端断开
+    not_empty: Condvar,
+    // 队列从满变为不满，或所有接收端断开
+    not_full: Condvar,
+    // None 表示无界
+    cap: Option<usize>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+
+    fn is_full(&self, state: &State<T>) -> bool {
+        self.cap.is_some_and(|cap| state.queue.len() >= cap)
+    }
+
+    /// 调用方已确认有空位，入队并唤醒一个消费者
+    fn push(&self, mut state: MutexGuard<'_, State<T>>, value: T) {
+        state.queue.push_back(value);
+        self.not_empty.notify_one();
+        state.notify_watchers();
+    }
+
+    fn pop(&self, state: &mut State<T>) -> Option<T> {
+        let value = state.queue.pop_front()?;
+        self.not_full.notify_one();
+        Some(value)
+    }
+}
+
+fn new_channel<T>(cap: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            senders: 1,
+            receivers: 1,
+            watchers: Vec::new(),
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        cap,
+    });
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver { shared },
+    )
+}
+
+/// 创建无界通道：send 永远不会因为队列满而阻塞
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(None)
+}
+
+/// 创建有界通道：队列中最多容纳 `cap` 条消息，满了之后 send 会阻塞（背压）
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(cap > 0, "容量必须大于 0");
+    new_channel(Some(cap))
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// 阻塞发送：有界通道满时等待；所有接收端都断开时返回错误
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self.send_deadline(value, None) {
+            Ok(()) => Ok(()),
+            Err(SendTimeoutError::Disconnected(v)) => Err(SendError(v)),
+            Err(SendTimeoutError::Timeout(_)) => unreachable!("没有 deadline 不会超时"),
+        }
+    }
+
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`send`](Self::send) 一样一直等
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_deadline(value, Instant::now().checked_add(timeout))
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let state = self.shared.lock();
+        if state.receivers == 0 {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if self.shared.is_full(&state) {
+            return Err(TrySendError::Full(value));
+        }
+        self.shared.push(state, value);
+        Ok(())
+    }
+
+    fn send_deadline(
+        &self,
+        value: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        let mut state = self.shared.lock();
+        loop {
+            if state.receivers == 0 {
+                return Err(SendTimeoutError::Disconnected(value));
+            }
+            if !self.shared.is_full(&state) {
+                break;
+            }
+            // 醒来后必须回到循环开头重新检查：可能是虚假唤醒，或空位已被其他生产者抢先
+            state = match deadline {
+                None => self.shared.not_full.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(SendTimeoutError::Timeout(value));
+                    }
+                    self.shared
+                        .not_full
+                        .wait_timeout(state, deadline - now)
+                        .unwrap()
+                        .0
+                }
+            };
+        }
+        self.shared.push(state, value);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.cap
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // 最后一个发送端断开：叫醒所有等待中的消费者，让它们看到"断开"
+            self.shared.not_empty.notify_all();
+            state.notify_watchers();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Sender { .. }")
+    }
+}
+
+/// 接收端，可以 clone 出多个消费者
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// 阻塞接收：队列为空且所有发送端都已断开时返回错误
+    pub fn recv(&self) -> Result<T, RecvError> {
+        match self.recv_deadline(None) {
+            Ok(v) => Ok(v),
+            Err(RecvTimeoutError::Disconnected) => Err(RecvError),
+            Err(RecvTimeoutError::Timeout) => unreachable!("没有 deadline 不会超时"),
+        }
+    }
+
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`recv`](Self::recv) 一样一直等
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_deadline(Instant::now().checked_add(timeout))
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        match self.shared.pop(&mut state) {
+            Some(v) => Ok(v),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn recv_deadline(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut state = self.shared.lock();
+        loop {
+            // 即使发送端全部断开，也要先把队列里剩余的消息取完
+            if let Some(v) = self.shared.pop(&mut state) {
+                return Ok(v);
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            state = match deadline {
+                None => self.shared.not_empty.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.shared
+                        .not_empty
+                        .wait_timeout(state, deadline - now)
+                        .unwrap()
+                        .0
+                }
+            };
+        }
+    }
+
+    /// 阻塞迭代，直到通道断开且消息取完
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.recv().ok())
+    }
+
+    /// 非阻塞迭代，取完当前已有的消息就结束
+    pub fn try_iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.try_recv().ok())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.shared.cap
+    }
+
+    /// 所有发送端是否都已断开
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.lock().senders == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().receivers += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            // 最后一个接收端断开：阻塞在 send 上的生产者应当立刻返回错误
+            self.shared.not_full.notify_all();
+        }
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Receiver { .. }")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Select：同时等待多个接收端
+// ---------------------------------------------------------------------------
+
+/// 类型擦除后的接收端，使不同消息类型的 Receiver 可以放进同一个 Select
+trait SelectHandle {
+    /// 尝试取出一条消息（或"已断开"的结果），装箱后以裸指针形式返回
+    fn try_take(&self) -> Option<*mut ()>;
+    fn watch(&self, signal: &Arc<Signal>);
+    fn unwatch(&self, signal: &Arc<Signal>);
+    fn addr(&self) -> *const ();
+    fn drop_payload(&self) -> unsafe fn(*mut ());
+}
+
+unsafe fn drop_payload<T>(payload: *mut ()) {
+    drop(unsafe { Box::from_raw(payload as *mut Result<T, RecvError>) });
+}
+
+impl<T> SelectHandle for Receiver<T> {
+    fn try_take(&self) -> Option<*mut ()> {
+        let result = match self.try_recv() {
+            Ok(v) => Ok(v),
+            Err(TryRecvError::Disconnected) => Err(RecvError),
+            Err(TryRecvError::Empty) => return None,
+        };
+        Some(Box::into_raw(Box::new(result)) as *mut ())
+    }
+
+    fn watch(&self, signal: &Arc<Signal>) {
+        self.shared.lock().watchers.push(Arc::clone(signal));
+    }
+
+    fn unwatch(&self, signal: &Arc<Signal>) {
+        self.shared
+            .lock()
+            .watchers
+            .retain(|s| !Arc::ptr_eq(s, signal));
+    }
+
+    fn addr(&self) -> *const () {
+        Arc::as_ptr(&self.shared) as *const ()
+    }
+
+    fn drop_payload(&self) -> unsafe fn(*mut ()) {
+        drop_payload::<T>
+    }
+}
+
+/// `Select::try_select` 在没有就绪通道时返回的错误
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TrySelectError;
+
+impl fmt::Display for TrySelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("all operations in select would block")
+    }
+}
+
+impl Error for TrySelectError {}
+
+/// `Select::select_timeout` 超时返回的错误
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SelectTimeoutError;
+
+impl fmt::Display for SelectTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting on select")
+    }
+}
+
+impl Error for SelectTimeoutError {}
+
+/// 在多个接收端上等待，返回第一个"有消息或已断开"的接收操作
+///
+/// 消息在 select 内部就已经从通道中取出并放进 [`SelectedOperation`]，
+/// 所以即使有其他消费者在竞争同一个通道，选中的结果也不会被"抢走"。
+///
+/// ```
+/// use learning_concurrency::channel::{unbounded, Select};
+///
+/// let (tx1, rx1) = unbounded::<i32>();
+/// let (tx2, rx2) = unbounded::<&str>();
+/// tx2.send("hello").unwrap();
+///
+/// let mut sel = Select::new();
+/// sel.recv(&rx1);
+/// sel.recv(&rx2);
+/// let op = sel.select();
+/// assert_eq!(op.index(), 1);
+/// assert_eq!(op.recv(&rx2), Ok("hello"));
+/// # drop(tx1);
+/// ```
+#[derive(Default)]
+pub struct Select<'a> {
+    handles: Vec<&'a dyn SelectHandle>,
+}
+
+/// 每次 select 的起始扫描位置都不同，避免总是偏向第一个通道
+static SELECT_ROTATION: AtomicUsize = AtomicUsize::new(0);
+
+impl<'a> Select<'a> {
+    pub fn new() -> Self {
+        Self {
+            handles: Vec::new(),
+        }
+    }
+
+    /// 加入一个接收端，返回它在本次 select 中的编号
+    pub fn recv<T>(&mut self, rx: &'a Receiver<T>) -> usize {
+        self.handles.push(rx);
+        self.handles.len() - 1
+    }
+
+    pub fn try_select(&mut self) -> Result<SelectedOperation<'a>, TrySelectError> {
+        self.try_take_any().ok_or(TrySelectError)
+    }
+
+    pub fn select(&mut self) -> SelectedOperation<'a> {
+        self.select_deadline(None).expect("没有 deadline 不会超时")
+    }
+
+    /// 超时大到 `Instant` 表示不了时和 [`select`](Self::select) 一样一直等
+    pub fn select_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<SelectedOperation<'a>, SelectTimeoutError> {
+        self.select_deadline(Instant::now().checked_add(timeout))
+            .ok_or(SelectTimeoutError)
+    }
+
+    fn try_take_any(&self) -> Option<SelectedOperation<'a>> {
+        let n = self.handles.len();
+        if n == 0 {
+            return None;
+        }
+        let start = SELECT_ROTATION.fetch_add(1, Ordering::Relaxed) % n;
+        (0..n).map(|i| (start + i) % n).find_map(|index| {
+            let handle = self.handles[index];
+            handle.try_take().map(|payload| SelectedOperation {
+                index,
+                payload,
+                owner: handle.addr(),
+                drop_payload: handle.drop_payload(),
+                _marker: PhantomData,
+            })
+        })
+    }
+
+    fn select_deadline(&mut self, deadline: Option<Instant>) -> Option<SelectedOperation<'a>> {
+        assert!(!self.handles.is_empty(), "Select 中至少要有一个接收端");
+        if let Some(op) = self.try_take_any() {
+            return Some(op);
+        }
+
+        // 先把信号挂到所有通道上，再检查一次：
+        // 这样"检查为空"和"开始等待"之间到达的消息也一定会触发信号，不会丢失唤醒
+        let signal = Arc::new(Signal::new());
+        for handle in &self.handles {
+            handle.watch(&signal);
+        }
+        let result = loop {
+            if let Some(op) = self.try_take_any() {
+                break Some(op);
+            }
+            if !signal.wait(deadline) {
+                // 超时后最后再试一次
+                break self.try_take_any();
+            }
+        };
+        for handle in &self.handles {
+            handle.unwatch(&signal);
+        }
+        result
+    }
+}
+
+/// select 选中的接收操作，内部已经持有取出的消息
+///
+/// 必须用同一个 Receiver 调用 [`SelectedOperation::recv`] 取回结果；
+/// 若直接丢弃，其中的消息也会随之丢弃。
+pub struct SelectedOperation<'a> {
+    index: usize,
+    payload: *mut (),
+    owner: *const (),
+    drop_payload: unsafe fn(*mut ()),
+    _marker: PhantomData<&'a ()>,
+}
+
+impl SelectedOperation<'_> {
+    /// 选中的接收端在 Select 中的编号
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// 选中的是否是这个接收端（同一通道的不同 Receiver 视为同一个）
+    pub fn is<T>(&self, rx: &Receiver<T>) -> bool {
+        SelectHandle::addr(rx) == self.owner
+    }
+
+    /// 取回消息；若所有发送端都已断开则返回 `RecvError`
+    pub fn recv<T>(mut self, rx: &Receiver<T>) -> Result<T, RecvError> {
+        assert!(self.is(rx), "传入的 Receiver 与选中的操作不匹配");
+        // SAFETY: addr 相同说明是同一个 Shared<T>，payload 正是 Box<Result<T, RecvError>>
+        let result = unsafe { Box::from_raw(self.payload as *mut Result<T, RecvError>) };
+        self.payload = std::ptr::null_mut();
+        *result
+    }
+}
+
+impl Drop for SelectedOperation<'_> {
+    fn drop(&mut self) {
+        if !self.payload.is_null() {
+            unsafe { (self.drop_payload)(self.payload) };
+        }
+    }
+}
+
+impl fmt::Debug for SelectedOperation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectedOperation")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 同时等待多个接收端，类似 Go 的 `select` 语句
+///
+/// 每个分支的 `$msg` 绑定为 `Result<T, RecvError>`：通道断开时为 `Err(RecvError)`。
+/// 可选的 `default` 分支在没有就绪通道时立即执行，`default(timeout)` 则在超时后执行。
+/// 分支体不在宏内部的循环中执行，因此 `break` / `continue` / `return` 都作用于外层代码。
+///
+/// 注意：`recv(...)` 中的表达式会被求值多次，应当传入一个 Receiver 变量。
+///
+/// ```
+/// use learning_concurrency::channel::unbounded;
+/// use learning_concurrency::select;
+/// use std::time::Duration;
+///
+/// let (tx1, rx1) = unbounded::<i32>();
+/// let (_tx2, rx2) = unbounded::<String>();
+/// tx1.send(7).unwrap();
+///
+/// let got = select! {
+///     recv(rx1) -> msg => msg.unwrap(),
+///     recv(rx2) -> msg => msg.unwrap().len() as i32,
+///     default(Duration::from_millis(10)) => -1,
+/// };
+/// assert_eq!(got, 7);
+/// ```
+#[macro_export]
+macro_rules! select {
+    // 无 default：一直阻塞
+    ($(recv($rx:expr) -> $msg:pat => $body:expr),+ $(,)?) => {{
+        let mut __sel = $crate::channel::Select::new();
+        $( __sel.recv(&$rx); )+
+        let __op = __sel.select();
+        $crate::select!(@dispatch __op; $(recv($rx) -> $msg => $body),+)
+    }};
+    // default：不阻塞
+    ($(recv($rx:expr) -> $msg:pat => $body:expr,)+ default => $default:expr $(,)?) => {{
+        let mut __sel = $crate::channel::Select::new();
+        $( __sel.recv(&$rx); )+
+        match __sel.try_select() {
+            Ok(__op) => $crate::select!(@dispatch __op; $(recv($rx) -> $msg => $body),+),
+            Err(_) => $default,
+        }
+    }};
+    // default(timeout)：最多等待 timeout
+    ($(recv($rx:expr) -> $msg:pat => $body:expr,)+ default($timeout:expr) => $default:expr $(,)?) => {{
+        let mut __sel = $crate::channel::Select::new();
+        $( __sel.recv(&$rx); )+
+        match __sel.select_timeout($timeout) {
+            Ok(__op) => $crate::select!(@dispatch __op; $(recv($rx) -> $msg => $body),+),
+            Err(_) => $default,
+        }
+    }};
+    (@dispatch $op:ident; $(recv($rx:expr) -> $msg:pat => $body:expr),+) => {
+        $(
+            if $op.is(&$rx) {
+                let $msg = $op.recv(&$rx);
+                $body
+            } else
+        )+
+        {
+            unreachable!("select 选中了未注册的接收端")
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::collections::HashSet;
+    use std::thread;
+
+    #[test]
+    fn test_unbounded_fifo() {
+        let (tx, rx) = unbounded();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 5);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_bounded_try_send_full() {
+        let (tx, rx) = bounded(2);
+        assert_eq!(tx.capacity(), Some(2));
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.recv(), Ok(1));
+        tx.try_send(3).unwrap();
+    }
+
+    #[test]
+    fn test_bounded_send_blocks_until_space() {
+        let (tx, rx) = bounded(1);
+        tx.send(1).unwrap();
+        let handle = thread::spawn(move || {
+            // 队列已满，这里会阻塞到主线程取走一条
+            tx.send(2).unwrap();
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let (tx, rx) = bounded(1);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        tx.send(1).unwrap();
+        assert_eq!(
+            tx.send_timeout(2, Duration::from_millis(10)),
+            Err(SendTimeoutError::Timeout(2))
+        );
+    }
+
+    #[test]
+    fn test_huge_timeouts_wait_forever() {
+        let (tx, rx) = bounded(1);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send_timeout(1, Duration::MAX).unwrap();
+            tx.send_timeout(2, Duration::MAX).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::MAX), Ok(1));
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert_eq!(sel.select_timeout(Duration::MAX).unwrap().recv(&rx), Ok(2));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_disconnect_detection() {
+        let (tx, rx) = unbounded();
+        tx.send(1).unwrap();
+        drop(tx);
+        // 断开后仍然能取完剩余消息
+        assert!(rx.is_disconnected());
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = bounded(1);
+        drop(rx);
+        assert_eq!(tx.send(5), Err(SendError(5)));
+        assert_eq!(tx.try_send(6), Err(TrySendError::Disconnected(6)));
+    }
+
+    #[test]
+    fn test_blocked_sender_wakes_on_receiver_drop() {
+        let (tx, rx) = bounded(1);
+        tx.send(1).unwrap();
+        let handle = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(handle.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[test]
+    fn test_cloned_receivers_share_messages() {
+        const TOTAL: usize = 10_000;
+        let (tx, rx) = bounded(16);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let consumers: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                let seen = Arc::clone(&seen);
+                thread::spawn(move || {
+                    let local: Vec<usize> = rx.iter().collect();
+                    seen.lock().unwrap().extend(local);
+                })
+            })
+            .collect();
+        drop(rx);
+
+        spawn_workers(tx, 4, |tx, i| {
+            for v in (i..TOTAL).step_by(4) {
+                tx.send(v).unwrap();
+            }
+        });
+        for c in consumers {
+            c.join().unwrap();
+        }
+
+        // 每条消息恰好被一个消费者拿到
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), TOTAL);
+        assert_eq!(seen.iter().collect::<HashSet<_>>().len(), TOTAL);
+    }
+
+    #[test]
+    fn test_select_picks_ready_receiver() {
+        let (tx1, rx1) = unbounded::<i32>();
+        let (tx2, rx2) = unbounded::<&str>();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx2.send("late").unwrap();
+        });
+
+        let mut sel = Select::new();
+        let i1 = sel.recv(&rx1);
+        let i2 = sel.recv(&rx2);
+        let op = sel.select();
+        assert_ne!(op.index(), i1);
+        assert_eq!(op.index(), i2);
+        assert_eq!(op.recv(&rx2), Ok("late"));
+        handle.join().unwrap();
+        drop(tx1);
+    }
+
+    #[test]
+    fn test_select_timeout_and_try() {
+        let (_tx, rx) = unbounded::<i32>();
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        assert!(sel.try_select().is_err());
+        assert!(sel.select_timeout(Duration::from_millis(10)).is_err());
+    }
+
+    #[test]
+    fn test_select_reports_disconnect() {
+        let (tx, rx) = unbounded::<i32>();
+        drop(tx);
+        let result = select! {
+            recv(rx) -> msg => msg,
+        };
+        assert_eq!(result, Err(RecvError));
+    }
+
+    #[test]
+    fn test_select_macro_default_branches() {
+        let (tx, rx) = unbounded::<i32>();
+        let r = select! {
+            recv(rx) -> msg => msg.unwrap(),
+            default => -1,
+        };
+        assert_eq!(r, -1);
+
+        tx.send(3).unwrap();
+        let r = select! {
+            recv(rx) -> msg => msg.unwrap(),
+            default(Duration::from_millis(10)) => -1,
+        };
+        assert_eq!(r, 3);
+    }
+
+    #[test]
+    fn test_select_body_can_break_outer_loop() {
+        let (tx, rx) = unbounded();
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        let mut sum = 0;
+        loop {
+            select! {
+                recv(rx) -> msg => match msg {
+                    Ok(v) => sum += v,
+                    Err(_) => break,
+                },
+            }
+        }
+        assert_eq!(sum, 3);
+    }
+
+    #[test]
+    fn stress_select_no_lost_messages() {
+        const PER_CHANNEL: usize = 2_000;
+        let (tx_a, rx_a) = bounded::<usize>(4);
+        let (tx_b, rx_b) = bounded::<usize>(4);
+
+        let pa = thread::spawn(move || (0..PER_CHANNEL).for_each(|v| tx_a.send(v).unwrap()));
+        let pb = thread::spawn(move || (0..PER_CHANNEL).for_each(|v| tx_b.send(v).unwrap()));
+
+        // 两个消费者同时对同一对通道做 select，彼此竞争
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let (rx_a, rx_b) = (rx_a.clone(), rx_b.clone());
+                thread::spawn(move || {
+                    let (mut a, mut b) = (0, 0);
+                    let (mut a_done, mut b_done) = (false, false);
+                    while !(a_done && b_done) {
+                        select! {
+                            recv(rx_a) -> msg => match msg {
+                                Ok(_) => a += 1,
+                                Err(_) => a_done = true,
+                            },
+                            recv(rx_b) -> msg => match msg {
+                                Ok(_) => b += 1,
+                                Err(_) => b_done = true,
+                            },
+                        }
+                    }
+                    (a, b)
+                })
+            })
+            .collect();
+        drop((rx_a, rx_b));
+
+        pa.join().unwrap();
+        pb.join().unwrap();
+        let (a, b) = consumers
+            .into_iter()
+            .map(|c| c.join().unwrap())
+            .fold((0, 0), |acc, x| (acc.0 + x.0, acc.1 + x.1));
+        assert_eq!((a, b), (PER_CHANNEL, PER_CHANNEL));
+    }
+}
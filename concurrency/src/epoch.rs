@@ -0,0 +1,414 @@
+//! 基于纪元的内存回收 (Epoch-Based Reclamation, EBR)
+//!
+//! 无锁数据结构最大的难题不是 CAS 本身，而是"何时可以释放被摘下的节点"：
+//! 线程 A 把节点从链表摘下的瞬间，线程 B 可能刚刚读到指向它的指针、正准备解引用。
+//!
+//! EBR 的思路：
+//! 1. 全局维护一个纪元计数器 `epoch`。
+//! 2. 线程访问共享结构前先 `pin()`，把"我正处在纪元 e"登记到自己的 Participant 槽位。
+//! 3. 摘下的节点不立即释放，而是带着当前纪元号放进垃圾袋 (`defer_destroy`)。
+//! 4. 只有当所有已 pin 的线程都追上了当前纪元，全局纪元才能 +1。
+//!    因此纪元 r 产生的垃圾，在全局纪元到达 r + 2 时，已经不可能被任何线程引用，可以安全释放。
+
+use std::cell::{Cell, UnsafeCell};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Participant::state 的最低位：1 表示已 pin，其余位存放纪元号
+const PINNED: usize = 1;
+/// 每 pin 这么多次，顺便尝试推进纪元并回收一次
+const PINS_BETWEEN_COLLECT: usize = 64;
+/// 本地垃圾袋超过这个数量时立即尝试回收
+const GARBAGE_THRESHOLD: usize = 128;
+
+/// 一件延迟执行的析构任务（类型擦除后的 Box<T>）
+struct Deferred {
+    ptr: *mut (),
+    dtor: unsafe fn(*mut ()),
+    epoch: usize,
+}
+
+// SAFETY: Deferred 只是"将来在某个线程上释放这块内存"的凭证，
+// defer_destroy 的调用者保证被释放的对象可以跨线程析构。
+unsafe impl Send for Deferred {}
+
+/// 每个线程在全局链表里占用的槽位。槽位只增不减，线程退出后可被新线程复用。
+struct Participant {
+    in_use: AtomicBool,
+    // (epoch << 1) | PINNED；未 pin 时为 0。只有这个字段会被其他线程读取。
+    state: AtomicUsize,
+    // 以下字段只会被持有该槽位的线程访问
+    guards: Cell<usize>,
+    pins: Cell<usize>,
+    // TLS 已销毁时临时借用的槽位，最后一个 Guard 释放时归还
+    detached: Cell<bool>,
+    // 全局纪元单调递增，所以本地垃圾天然按纪元有序，回收时只需从队头弹出
+    garbage: UnsafeCell<VecDeque<Deferred>>,
+    // 发布到全局链表之前写入，之后只读
+    next: *const Participant,
+}
+
+// SAFETY: 除 state / in_use 外的字段只被当前持有槽位的线程访问，
+// 槽位的归属通过 in_use 的 Acquire/Release 交接。
+unsafe impl Sync for Participant {}
+
+struct Global {
+    epoch: AtomicUsize,
+    participants: AtomicPtr<Participant>,
+    // 线程退出时还没来得及回收的垃圾
+    orphans: Mutex<Vec<Deferred>>,
+}
+
+static GLOBAL: Global = Global {
+    epoch: AtomicUsize::new(0),
+    participants: AtomicPtr::new(ptr::null_mut()),
+    orphans: Mutex::new(Vec::new()),
+};
+
+struct LocalHandle {
+    participant: &'static Participant,
+}
+
+impl Drop for LocalHandle {
+    fn drop(&mut self) {
+        let p = self.participant;
+        if p.guards.get() == 0 {
+            release(p);
+        } else {
+            // 还有 Guard 活着（例如在其他 TLS 的析构里），交给最后一个 Guard 归还
+            p.detached.set(true);
+        }
+    }
+}
+
+thread_local! {
+    static HANDLE: LocalHandle = LocalHandle {
+        participant: acquire_participant(),
+    };
+}
+
+fn acquire_participant() -> &'static Participant {
+    // 优先复用已退出线程留下的空槽位
+    let mut cur = GLOBAL.participants.load(Ordering::Acquire);
+    while let Some(p) = unsafe { cur.as_ref() } {
+        if !p.in_use.load(Ordering::Relaxed)
+            && p.in_use
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            return p;
+        }
+        cur = p.next as *mut Participant;
+    }
+
+    // 没有空槽位：新建一个并压入链表头（与 Treiber 栈的 push 完全相同）
+    let new = Box::into_raw(Box::new(Participant {
+        in_use: AtomicBool::new(true),
+        state: AtomicUsize::new(0),
+        guards: Cell::new(0),
+        pins: Cell::new(0),
+        detached: Cell::new(false),
+        garbage: UnsafeCell::new(VecDeque::new()),
+        next: ptr::null(),
+    }));
+    let mut head = GLOBAL.participants.load(Ordering::Relaxed);
+    loop {
+        // SAFETY: new 尚未发布，只有我们能访问
+        unsafe { (*new).next = head };
+        match GLOBAL.participants.compare_exchange_weak(
+            head,
+            new,
+            Ordering::Release,
+            Ordering::Relaxed,
+        ) {
+            // 槽位永不释放，所以可以当作 'static 引用
+            Ok(_) => return unsafe { &*new },
+            Err(cur) => head = cur,
+        }
+    }
+}
+
+fn release(p: &'static Participant) {
+    // SAFETY: 只有槽位持有者会调用 release
+    let garbage = mem::take(unsafe { &mut *p.garbage.get() });
+    if !garbage.is_empty() {
+        GLOBAL
+            .orphans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(garbage);
+    }
+    p.state.store(0, Ordering::Release);
+    p.pins.set(0);
+    p.detached.set(false);
+    p.in_use.store(false, Ordering::Release);
+}
+
+/// 尝试推进全局纪元，返回推进后（或当前）的纪元
+fn try_advance() -> usize {
+    let global = GLOBAL.epoch.load(Ordering::Relaxed);
+    fence(Ordering::SeqCst);
+
+    let mut cur = GLOBAL.participants.load(Ordering::Acquire);
+    while let Some(p) = unsafe { cur.as_ref() } {
+        let state = p.state.load(Ordering::Relaxed);
+        // 有线程还停留在旧纪元：不能推进
+        if state & PINNED != 0 && state >> 1 != global & (usize::MAX >> 1) {
+            return global;
+        }
+        cur = p.next as *mut Participant;
+    }
+    fence(Ordering::Acquire);
+
+    match GLOBAL.epoch.compare_exchange(
+        global,
+        global.wrapping_add(1),
+        Ordering::Release,
+        Ordering::Relaxed,
+    ) {
+        Ok(_) => global.wrapping_add(1),
+        Err(cur) => cur,
+    }
+}
+
+fn is_expired(d: &Deferred, global: usize) -> bool {
+    global.wrapping_sub(d.epoch) >= 2
+}
+
+/// 从本地垃圾袋的队头取出所有"已过两个纪元"的垃圾
+fn take_ready(bag: &mut VecDeque<Deferred>, global: usize) -> Vec<Deferred> {
+    let n = bag.iter().take_while(|d| is_expired(d, global)).count();
+    bag.drain(..n).collect()
+}
+
+/// 孤儿垃圾来自不同线程，纪元无序，只能整体筛选
+fn take_ready_orphans(bag: &mut Vec<Deferred>, global: usize) -> Vec<Deferred> {
+    let (ready, keep) = mem::take(bag)
+        .into_iter()
+        .partition(|d| is_expired(d, global));
+    *bag = keep;
+    ready
+}
+
+fn run(ready: Vec<Deferred>) {
+    for d in ready {
+        // SAFETY: 对象已从数据结构摘下，且所有可能引用它的线程都已离开临界区
+        unsafe { (d.dtor)(d.ptr) };
+    }
+}
+
+fn collect(p: &Participant) {
+    let global = try_advance();
+
+    // 先把可回收的垃圾取出来，再执行析构：
+    // 析构函数里可能再次 pin / defer_destroy，不能在持有 &mut 垃圾袋时调用
+    let ready = take_ready(unsafe { &mut *p.garbage.get() }, global);
+    run(ready);
+
+    // 顺便回收已退出线程留下的垃圾；拿不到锁就下次再说
+    let orphans = match GLOBAL.orphans.try_lock() {
+        Ok(mut orphans) if !orphans.is_empty() => take_ready_orphans(&mut orphans, global),
+        _ => return,
+    };
+    run(orphans);
+}
+
+/// 进入临界区：在 Guard 存活期间，读到的共享指针不会被释放
+pub fn pin() -> Guard {
+    let participant = HANDLE.try_with(|h| h.participant).unwrap_or_else(|_| {
+        // 线程局部存储已销毁（在其他 TLS 的析构函数中调用），临时借用一个槽位
+        let p = acquire_participant();
+        p.detached.set(true);
+        p
+    });
+
+    let guards = participant.guards.get();
+    participant.guards.set(guards + 1);
+    if guards == 0 {
+        let epoch = GLOBAL.epoch.load(Ordering::Relaxed);
+        participant
+            .state
+            .store((epoch << 1) | PINNED, Ordering::Relaxed);
+        // SeqCst 屏障：保证"我已 pin"对随后 try_advance 扫描的线程可见，
+        // 并且先于本线程之后对共享指针的所有读取
+        fence(Ordering::SeqCst);
+
+        let pins = participant.pins.get().wrapping_add(1);
+        participant.pins.set(pins);
+        if pins.is_multiple_of(PINS_BETWEEN_COLLECT) {
+            collect(participant);
+        }
+    }
+
+    Guard {
+        participant,
+        _not_send: PhantomData,
+    }
+}
+
+/// pin() 返回的守卫，drop 时离开临界区
+///
+/// Guard 不能跨线程传递：它代表的是"当前线程"处于临界区。
+pub struct Guard {
+    participant: &'static Participant,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Guard {
+    /// 延迟释放一个由 `Box::into_raw` 得到的指针
+    ///
+    /// # Safety
+    /// - `ptr` 必须来自 `Box::<T>::into_raw`，且只能被 defer 一次
+    /// - 调用前 `ptr` 必须已从共享结构中摘下，之后 pin 的线程不可能再读到它
+    /// - `T` 的析构可能在其他线程上执行，因此 `T` 应当是 `Send` 的
+    /// - 析构会在之后任意时刻执行，届时 `T` 借用的数据可能已经失效；
+    ///   析构会运行 `T` 的 drop 时，`T` 必须是 `'static` 的
+    pub unsafe fn defer_destroy<T>(&self, ptr: *mut T) {
+        unsafe fn drop_box<T>(p: *mut ()) {
+            drop(unsafe { Box::from_raw(p as *mut T) });
+        }
+
+        fence(Ordering::SeqCst);
+        let epoch = GLOBAL.epoch.load(Ordering::Relaxed);
+        let len = {
+            // SAFETY: 垃圾袋只被本线程访问，且此处不会重入
+            let garbage = unsafe { &mut *self.participant.garbage.get() };
+            garbage.push_back(Deferred {
+                ptr: ptr as *mut (),
+                dtor: drop_box::<T>,
+                epoch,
+            });
+            garbage.len()
+        };
+        if len >= GARBAGE_THRESHOLD {
+            collect(self.participant);
+        }
+    }
+
+    /// 立即尝试推进纪元并回收已到期的垃圾
+    pub fn flush(&self) {
+        collect(self.participant);
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let p = self.participant;
+        let guards = p.guards.get();
+        p.guards.set(guards - 1);
+        if guards == 1 {
+            // Release：临界区内的所有读取都发生在"离开临界区"之前
+            p.state.store(0, Ordering::Release);
+            if p.detached.get() {
+                release(p);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    struct Counted(Arc<AtomicUsize>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush_until(drops: &AtomicUsize, expected: usize) -> bool {
+        for _ in 0..10_000 {
+            pin().flush();
+            if drops.load(Ordering::Relaxed) == expected {
+                return true;
+            }
+            thread::yield_now();
+        }
+        false
+    }
+
+    #[test]
+    fn test_deferred_eventually_runs() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        {
+            let guard = pin();
+            for _ in 0..10 {
+                let p = Box::into_raw(Box::new(Counted(Arc::clone(&drops))));
+                unsafe { guard.defer_destroy(p) };
+            }
+        }
+        assert!(flush_until(&drops, 10));
+    }
+
+    #[test]
+    fn test_pinned_thread_blocks_reclamation() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (pinned_tx, pinned_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        // 另一个线程一直处于临界区
+        let holder = thread::spawn(move || {
+            let _guard = pin();
+            pinned_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        pinned_rx.recv().unwrap();
+
+        {
+            let guard = pin();
+            let p = Box::into_raw(Box::new(Counted(Arc::clone(&drops))));
+            unsafe { guard.defer_destroy(p) };
+        }
+        for _ in 0..1_000 {
+            pin().flush();
+        }
+        assert_eq!(
+            drops.load(Ordering::Relaxed),
+            0,
+            "仍有线程处于临界区，不应回收"
+        );
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        assert!(flush_until(&drops, 1));
+    }
+
+    #[test]
+    fn test_nested_pin() {
+        let outer = pin();
+        let inner = pin();
+        drop(outer);
+        // 内层 Guard 仍然存活，槽位必须保持 pin 状态
+        assert_ne!(inner.participant.state.load(Ordering::Relaxed) & PINNED, 0);
+        drop(inner);
+    }
+
+    #[test]
+    fn test_garbage_of_exited_threads_is_collected() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let drops = Arc::clone(&drops);
+                thread::spawn(move || {
+                    let guard = pin();
+                    for _ in 0..10 {
+                        let p = Box::into_raw(Box::new(Counted(Arc::clone(&drops))));
+                        unsafe { guard.defer_destroy(p) };
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(flush_until(&drops, 40));
+    }
+}
@@ -1,3 +1,25 @@
+pub mod actor;
+pub mod arc;
+pub mod assert;
+pub mod bounded_buffer;
+pub mod cache_padded;
+pub mod channel;
+pub mod epoch;
+pub mod lock_order;
+pub mod lockfree;
+pub mod metrics;
+pub mod model;
+pub mod pipeline;
+pub mod pool;
+pub mod rwlock;
+pub mod seqlock;
+pub mod sharded_map;
+pub mod snapshot_cell;
+pub mod spinlock;
+pub mod sync;
+pub mod thread_local;
+pub mod timer;
+
 use std::thread;
 
 // 通用并发执行器：启动指定数量的线程并执行指定的逻辑
@@ -27,3 +49,91 @@ where
         handle.join().unwrap();
     }
 }
+
+// 可以从外部叫停的 spawn_workers
+//
+// 每个线程额外拿到一个取消令牌，工作循环应当定期检查它（或用 wait_timeout 代替 sleep）。
+// 所有线程共用 token 的同一个子令牌：外部取消 token 时全部线程收到信号；
+// 任何一个线程 panic 时也会取消这个子令牌，让其余线程尽快退出，
+// 等所有线程结束后再把第一个 panic 重新抛出，而不会影响 token 本身和它的其他后代。
+pub fn spawn_workers_cancellable<T, F>(
+    shared_data: T,
+    count: usize,
+    token: &sync::CancellationToken,
+    task: F,
+) where
+    T: Send + Clone + 'static,
+    F: Fn(T, usize, &sync::CancellationToken) + Send + Sync + 'static + Clone,
+{
+    let group = token.child_token();
+    let mut handles = vec![];
+    for i in 0..count {
+        let data_clone = shared_data.clone();
+        let task_clone = task.clone();
+        let group = group.clone();
+        let handle = thread::spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                task_clone(data_clone, i, &group)
+            }));
+            if result.is_err() {
+                group.cancel();
+            }
+            result
+        });
+        handles.push(handle);
+    }
+
+    let mut first_panic = None;
+    for handle in handles {
+        if let Err(payload) = handle.join().unwrap() {
+            first_panic.get_or_insert(payload);
+        }
+    }
+    if let Some(payload) = first_panic {
+        std::panic::resume_unwind(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_spawn_workers_cancellable_stops_on_external_cancel() {
+        let token = sync::CancellationToken::new();
+        let rounds = Arc::new(AtomicUsize::new(0));
+        {
+            let token = token.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(30));
+                token.cancel();
+            });
+        }
+        spawn_workers_cancellable(Arc::clone(&rounds), 4, &token, |rounds, _, token| {
+            while !token.wait_timeout(Duration::from_millis(1)) {
+                rounds.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        assert!(rounds.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn test_spawn_workers_cancellable_panic_stops_siblings() {
+        let token = sync::CancellationToken::new();
+        let result = std::panic::catch_unwind(|| {
+            spawn_workers_cancellable((), 3, &token, |_, i, token| {
+                if i == 0 {
+                    panic!("工作线程 0 出错");
+                }
+                token.wait();
+            });
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"工作线程 0 出错"));
+        // 只取消了本组的子令牌
+        assert!(!token.is_cancelled());
+    }
+}
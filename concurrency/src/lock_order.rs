@@ -0,0 +1,86 @@
+//! 锁顺序跟踪（调试用，需开启 `lock-order-debug` 特性）
+//!
+//! 两个线程分别以 A→B 和 B→A 的顺序加锁，只有在时机恰好重叠时才会死锁，测试往往发现不了。
+//! 开启特性后，本 crate 的 [`SpinLock`](crate::spinlock::SpinLock) 和
+//! [`RwLock`](crate::rwlock::RwLock) 会在每次加锁时记录"持有 X 时又去获取 Y"，
+//! 把所有线程的记录汇总成一张全局的锁顺序图：
+//!
+//! - 新增的一条边若使图中出现环，说明存在潜在的锁顺序反转，报告环上每把锁的名字和加锁位置
+//! - 线程去获取自己已经持有的锁会死锁，报告后直接 panic。读锁重入只在读者优先的 `RwLock` 上放行：
+//!   其他策略下新读者要排在等待的写者后面，而写者又在等这个线程释放读锁
+//!
+//! 锁的名字通过 `SpinLock::named` / `RwLock::named` 设置，未命名的锁显示为 `lock#编号`。
+//! 不开启特性时所有跟踪代码都是空函数，锁里也不会多出任何字段。
+//!
+//! ```text
+//! cargo test -p learning-concurrency --features lock-order-debug
+//! ```
+
+#[cfg(feature = "lock-order-debug")]
+mod tracker;
+
+#[cfg(feature = "lock-order-debug")]
+pub use tracker::{set_violation_handler, LockRef, OrderEdge, Violation};
+
+#[cfg(feature = "lock-order-debug")]
+pub(crate) use tracker::LockTracker;
+
+/// 一次加锁请求的访问方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Shared,
+    Upgradable,
+    Exclusive,
+}
+
+impl Access {
+    /// 同一线程已经以 `self` 方式持有时，再以 `other` 方式请求是否可能永远阻塞自己。
+    /// `readers_wait_for_writers` 表示新读者会给排队的写者让路，这时任何重入都可能卡死
+    #[cfg_attr(not(feature = "lock-order-debug"), allow(dead_code))]
+    pub(crate) fn conflicts_with(self, other: Access, readers_wait_for_writers: bool) -> bool {
+        readers_wait_for_writers
+            || !matches!(
+                (self, other),
+                (Access::Shared, Access::Shared)
+                    | (Access::Shared, Access::Upgradable)
+                    | (Access::Upgradable, Access::Shared)
+            )
+    }
+}
+
+#[cfg(not(feature = "lock-order-debug"))]
+pub(crate) use noop::LockTracker;
+
+#[cfg(not(feature = "lock-order-debug"))]
+mod noop {
+    use super::Access;
+    use std::panic::Location;
+
+    /// 关闭特性时的空实现：零大小，所有方法都会被内联掉
+    #[derive(Debug, Default)]
+    pub(crate) struct LockTracker;
+
+    impl LockTracker {
+        pub(crate) const fn new() -> Self {
+            LockTracker
+        }
+
+        #[inline(always)]
+        pub(crate) fn set_name(&mut self, _name: &'static str) {}
+
+        #[inline(always)]
+        pub(crate) fn set_readers_wait_for_writers(&mut self) {}
+
+        #[inline(always)]
+        pub(crate) fn before_wait(&self, _access: Access, _site: &'static Location<'static>) {}
+
+        #[inline(always)]
+        pub(crate) fn acquired(&self, _access: Access, _site: &'static Location<'static>) {}
+
+        #[inline(always)]
+        pub(crate) fn changed(&self, _access: Access) {}
+
+        #[inline(always)]
+        pub(crate) fn released(&self) {}
+    }
+}
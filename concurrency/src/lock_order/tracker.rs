@@ -0,0 +1,443 @@
+use super::Access;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::panic::Location;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+
+type Site = &'static Location<'static>;
+
+/// 报告中对一把锁的引用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockRef {
+    pub id: usize,
+    pub name: Option<&'static str>,
+}
+
+impl fmt::Display for LockRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "\"{}\"", name),
+            None => write!(f, "lock#{}", self.id),
+        }
+    }
+}
+
+/// 锁顺序图中的一条边：在 `held_at` 处获取了 `from`，之后在 `acquired_at` 处请求 `to`
+#[derive(Debug, Clone)]
+pub struct OrderEdge {
+    pub from: LockRef,
+    pub to: LockRef,
+    pub held_at: Site,
+    pub acquired_at: Site,
+}
+
+#[derive(Debug, Clone)]
+pub enum Violation {
+    /// 锁顺序图中出现环。第一条边是刚刚新增的那条，沿着列表走一圈回到起点
+    Cycle(Vec<OrderEdge>),
+    /// 线程请求一把自己已经持有的锁
+    Recursive {
+        lock: LockRef,
+        held_at: Site,
+        requested_at: Site,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Cycle(edges) => {
+                writeln!(f, "检测到潜在死锁：锁顺序出现环")?;
+                for e in edges {
+                    writeln!(
+                        f,
+                        "  {} -> {}: 持有 {} ({}) 时获取 {} ({})",
+                        e.from, e.to, e.from, e.held_at, e.to, e.acquired_at
+                    )?;
+                }
+                Ok(())
+            }
+            Violation::Recursive {
+                lock,
+                held_at,
+                requested_at,
+            } => write!(
+                f,
+                "检测到死锁：线程在 {} 请求锁 {}，但它已在 {} 持有这把锁",
+                requested_at, lock, held_at
+            ),
+        }
+    }
+}
+
+/// 默认处理：打印到 stderr。`Recursive` 在处理函数返回后总会 panic，否则线程会永远等下去
+fn default_handler(violation: &Violation) {
+    eprintln!("[lock-order] {}", violation);
+}
+
+static HANDLER: RwLock<fn(&Violation)> = RwLock::new(default_handler);
+
+/// 替换违规处理函数（例如在测试里改为收集报告，或者遇到环就 panic）
+pub fn set_violation_handler(handler: fn(&Violation)) {
+    *HANDLER.write().unwrap() = handler;
+}
+
+fn report(violation: &Violation) {
+    let handler = *HANDLER.read().unwrap();
+    handler(violation);
+}
+
+struct Held {
+    lock: LockRef,
+    access: Access,
+    site: Site,
+}
+
+thread_local! {
+    // 本线程当前持有的锁，按获取顺序排列
+    static HELD: RefCell<Vec<Held>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Default)]
+struct Graph {
+    // from -> (to -> 边)
+    edges: HashMap<usize, HashMap<usize, OrderEdge>>,
+}
+
+impl Graph {
+    /// 加入一条边；如果它让图中出现了环，返回这个环
+    fn add_edge(&mut self, edge: OrderEdge) -> Option<Vec<OrderEdge>> {
+        let (from, to) = (edge.from.id, edge.to.id);
+        if self.edges.get(&from).is_some_and(|m| m.contains_key(&to)) {
+            // 已知的边在加入时检查过了，不重复报告
+            return None;
+        }
+        let cycle = self.path(to, from).map(|path| {
+            let mut cycle = vec![edge.clone()];
+            cycle.extend(path);
+            cycle
+        });
+        self.edges.entry(from).or_default().insert(to, edge);
+        cycle
+    }
+
+    /// 广度优先搜索 start 到 goal 的路径
+    fn path(&self, start: usize, goal: usize) -> Option<Vec<OrderEdge>> {
+        let mut parent: HashMap<usize, &OrderEdge> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            if node == goal {
+                let mut path = vec![];
+                let mut cur = goal;
+                while cur != start {
+                    let e = parent[&cur];
+                    path.push(e.clone());
+                    cur = e.from.id;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, e) in self.edges.get(&node).into_iter().flatten() {
+                if *next != start && !parent.contains_key(next) {
+                    parent.insert(*next, e);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    fn remove_node(&mut self, id: usize) {
+        self.edges.remove(&id);
+        for targets in self.edges.values_mut() {
+            targets.remove(&id);
+        }
+    }
+}
+
+static GRAPH: Mutex<Option<Graph>> = Mutex::new(None);
+
+fn with_graph<R>(f: impl FnOnce(&mut Graph) -> R) -> R {
+    f(GRAPH.lock().unwrap().get_or_insert_with(Graph::default))
+}
+
+/// 嵌在每把锁里的跟踪信息。编号在第一次加锁时才分配，这样锁的构造函数可以保持 const
+#[derive(Debug, Default)]
+pub(crate) struct LockTracker {
+    id: AtomicUsize,
+    name: Option<&'static str>,
+    /// 读写锁的策略让新读者排在等待的写者后面，读锁重入也算自锁
+    readers_wait_for_writers: bool,
+}
+
+impl LockTracker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            id: AtomicUsize::new(0),
+            name: None,
+            readers_wait_for_writers: false,
+        }
+    }
+
+    pub(crate) fn set_name(&mut self, name: &'static str) {
+        self.name = Some(name);
+    }
+
+    pub(crate) fn set_readers_wait_for_writers(&mut self) {
+        self.readers_wait_for_writers = true;
+    }
+
+    fn lock_ref(&self) -> LockRef {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+        let mut id = self.id.load(Ordering::Relaxed);
+        if id == 0 {
+            let new = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            id = match self
+                .id
+                .compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => new,
+                Err(existing) => existing,
+            };
+        }
+        LockRef {
+            id,
+            name: self.name,
+        }
+    }
+
+    /// 在可能阻塞之前调用：检查重入，并记录"已持有的锁 -> 这把锁"的顺序
+    pub(crate) fn before_wait(&self, access: Access, site: Site) {
+        let lock = self.lock_ref();
+        let mut violations = vec![];
+        let _ = HELD.try_with(|held| {
+            let held = held.borrow();
+            if let Some(h) = held.iter().find(|h| {
+                h.lock.id == lock.id
+                    && h.access
+                        .conflicts_with(access, self.readers_wait_for_writers)
+            }) {
+                violations.push(Violation::Recursive {
+                    lock,
+                    held_at: h.site,
+                    requested_at: site,
+                });
+                return;
+            }
+            with_graph(|graph| {
+                for h in held.iter().filter(|h| h.lock.id != lock.id) {
+                    let edge = OrderEdge {
+                        from: h.lock,
+                        to: lock,
+                        held_at: h.site,
+                        acquired_at: site,
+                    };
+                    if let Some(cycle) = graph.add_edge(edge) {
+                        violations.push(Violation::Cycle(cycle));
+                    }
+                }
+            });
+        });
+        // 释放图的锁之后再调用处理函数，处理函数里可以放心加锁或 panic
+        for v in &violations {
+            report(v);
+            if matches!(v, Violation::Recursive { .. }) {
+                panic!("{}", v);
+            }
+        }
+    }
+
+    pub(crate) fn acquired(&self, access: Access, site: Site) {
+        let lock = self.lock_ref();
+        let _ = HELD.try_with(|held| held.borrow_mut().push(Held { lock, access, site }));
+    }
+
+    /// 升级/降级：仍然持有同一把锁，只是访问方式变了
+    pub(crate) fn changed(&self, access: Access) {
+        let id = self.lock_ref().id;
+        let _ = HELD.try_with(|held| {
+            if let Some(h) = held.borrow_mut().iter_mut().rev().find(|h| h.lock.id == id) {
+                h.access = access;
+            }
+        });
+    }
+
+    /// guard 不一定按获取的逆序释放，所以从后往前找到对应的那一项删除
+    pub(crate) fn released(&self) {
+        let id = self.lock_ref().id;
+        let _ = HELD.try_with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(pos) = held.iter().rposition(|h| h.lock.id == id) {
+                held.remove(pos);
+            }
+        });
+    }
+}
+
+impl Drop for LockTracker {
+    fn drop(&mut self) {
+        let id = *self.id.get_mut();
+        if id != 0 {
+            with_graph(|graph| graph.remove_node(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rwlock::{Policy, RwLock};
+    use crate::spinlock::SpinLock;
+    use std::panic;
+    use std::sync::{Arc, Barrier, Once};
+    use std::thread;
+
+    // 处理函数是全局的，测试并行运行：统一收集所有报告，各测试按锁名过滤
+    static REPORTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn collect_reports() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| set_violation_handler(|v| REPORTS.lock().unwrap().push(v.to_string())));
+    }
+
+    fn reports_mentioning(name: &str) -> Vec<String> {
+        REPORTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.contains(name))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_abba_inversion_reported_with_sites() {
+        collect_reports();
+        let a = Arc::new(SpinLock::new(0).named("abba-A"));
+        let b = Arc::new(RwLock::new(0).named("abba-B"));
+
+        // 两个线程先后运行，实际上不会死锁，但顺序相反
+        {
+            let _ga = a.lock();
+            let _gb = b.write();
+        }
+        let (a2, b2) = (Arc::clone(&a), Arc::clone(&b));
+        thread::spawn(move || {
+            let _gb = b2.read();
+            let _ga = a2.lock();
+        })
+        .join()
+        .unwrap();
+
+        let reports = reports_mentioning("abba-A");
+        assert_eq!(reports.len(), 1, "{:?}", reports);
+        let report = &reports[0];
+        assert!(report.contains("\"abba-B\" -> \"abba-A\""));
+        assert!(report.contains("\"abba-A\" -> \"abba-B\""));
+        assert!(report.contains(file!()), "报告中应包含加锁位置: {}", report);
+
+        // 相同的顺序再走一遍不会重复报告
+        thread::spawn(move || {
+            let _gb = b.read();
+            let _ga = a.lock();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(reports_mentioning("abba-A").len(), 1);
+    }
+
+    #[test]
+    fn test_three_lock_cycle() {
+        collect_reports();
+        let locks: Vec<_> = ["tri-0", "tri-1", "tri-2"]
+            .into_iter()
+            .map(|n| SpinLock::new(()).named(n))
+            .collect();
+        for i in 0..3 {
+            let _first = locks[i].lock();
+            let _second = locks[(i + 1) % 3].lock();
+        }
+        let reports = reports_mentioning("tri-0");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].lines().count(), 4, "{}", reports[0]);
+    }
+
+    #[test]
+    fn test_consistent_order_is_silent() {
+        collect_reports();
+        let outer = Arc::new(RwLock::with_policy((), Policy::ReaderPreferring).named("ok-outer"));
+        let inner = Arc::new(SpinLock::new(()).named("ok-inner"));
+        let barrier = Arc::new(Barrier::new(4));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let (outer, inner, barrier) =
+                    (Arc::clone(&outer), Arc::clone(&inner), Arc::clone(&barrier));
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..100 {
+                        let _o = outer.read();
+                        let _i = inner.lock();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        // 读者优先时读锁重入不是违规，升级后降级也不是
+        let r1 = outer.read();
+        let r2 = outer.read();
+        drop((r1, r2));
+        let w = outer.upgradable_read().upgrade().downgrade();
+        drop(w);
+        assert!(reports_mentioning("ok-").is_empty());
+    }
+
+    #[test]
+    fn test_recursive_lock_panics_instead_of_hanging() {
+        collect_reports();
+        let lock = SpinLock::new(1).named("self-deadlock");
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _g1 = lock.lock();
+            let _g2 = lock.lock();
+        }));
+        assert!(result.is_err());
+        let reports = reports_mentioning("self-deadlock");
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].contains("已在"));
+
+        // 写锁持有时再读同样是自锁
+        let rw = RwLock::new(0).named("self-deadlock-rw");
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _w = rw.write();
+            let _r = rw.read();
+        }));
+        assert!(result.is_err());
+        // panic 时 guard 已释放，锁仍然可用
+        assert_eq!(*lock.lock(), 1);
+        assert_eq!(*rw.read(), 0);
+    }
+
+    #[test]
+    fn test_reentrant_read_behind_queued_writer_panics() {
+        collect_reports();
+        // 默认的写者优先策略：写者排队后第二次 read 会永远等下去
+        let lock = Arc::new(RwLock::new(0).named("reentrant-read"));
+        let first = lock.read();
+        let writer = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || *lock.write() += 1)
+        };
+        thread::sleep(std::time::Duration::from_millis(20));
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _second = lock.read();
+        }));
+        assert!(result.is_err());
+        assert_eq!(reports_mentioning("reentrant-read").len(), 1);
+        drop(first);
+        writer.join().unwrap();
+        assert_eq!(*lock.read(), 1);
+    }
+}
@@ -0,0 +1,16 @@
+//! 无锁数据结构
+//!
+//! - [`TreiberStack`]：无锁栈，单个 CAS 修改栈顶
+//! - [`MsQueue`]：Michael–Scott 无界 MPMC 队列，头尾两个指针分别 CAS
+//! - [`ArrayQueue`]：Vyukov 有界环形 MPMC 队列，每个槽位带圈数戳记，无需回收内存
+//!
+//! 前两者的节点在出栈/出队后交给 [`crate::epoch`] 延迟释放，
+//! 这同时解决了"释放后使用"和 ABA 问题：只要还有线程 pin 着，被摘下的节点地址就不会被复用。
+
+mod array_queue;
+mod ms_queue;
+mod treiber_stack;
+
+pub use array_queue::ArrayQueue;
+pub use ms_queue::MsQueue;
+pub use treiber_stack::TreiberStack;
@@ -0,0 +1,291 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+use crate::cache_padded::CachePadded;
+
+struct Slot<T> {
+    // 戳记 = 圈数 | 下标，与 head/tail 的编码相同（见 ArrayQueue::one_lap）：
+    // - stamp == tail          ：空槽，等待这一圈的生产者写入
+    // - stamp == head + 1      ：已写入，等待这一圈的消费者读取
+    // - stamp == head + one_lap：已读取，轮到下一圈的生产者
+    stamp: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// 基于数组的有界 MPMC 队列（Dmitry Vyukov 算法，crossbeam 式的圈数/下标戳记）
+///
+/// 和链表队列不同，槽位在创建时一次性分配、循环复用，不涉及内存回收。
+///
+/// 位置不是简单的递增计数：低位是槽位下标，高位是圈数，每圈的步长 `one_lap` 是大于容量的
+/// 2 的幂。如果直接用 `pos % capacity` 取槽位、用 `pos + capacity` 表示下一圈，容量为 1 时
+/// "已写入"（pos + 1）和"下一圈的空槽"（pos + capacity）是同一个值，第二次 push 会覆盖
+/// 还没被读走的元素。
+pub struct ArrayQueue<T> {
+    buffer: Box<[Slot<T>]>,
+    one_lap: usize,
+    // 下一次 push 的位置
+    tail: CachePadded<AtomicUsize>,
+    // 下一次 pop 的位置
+    head: CachePadded<AtomicUsize>,
+}
+
+unsafe impl<T: Send> Send for ArrayQueue<T> {}
+unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
+impl<T> ArrayQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "容量必须大于 0");
+        let buffer = (0..capacity)
+            .map(|i| Slot {
+                // 第 0 圈，下标 i
+                stamp: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            buffer,
+            one_lap: (capacity + 1).next_power_of_two(),
+            tail: CachePadded::new(AtomicUsize::new(0)),
+            head: CachePadded::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// 位置往后挪一格：到了本圈末尾就进入下一圈的下标 0
+    fn advance(&self, pos: usize) -> usize {
+        let index = pos & (self.one_lap - 1);
+        if index + 1 < self.capacity() {
+            pos + 1
+        } else {
+            (pos & !(self.one_lap - 1)).wrapping_add(self.one_lap)
+        }
+    }
+
+    /// 队列已满时把元素原样返回
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[tail & (self.one_lap - 1)];
+            // Acquire：与消费者释放槽位时的 Release 配对，确保旧值已被完整读走
+            let stamp = slot.stamp.load(Ordering::Acquire);
+
+            if stamp == tail {
+                // 槽位空闲，抢占位置 tail
+                match self.tail.compare_exchange_weak(
+                    tail,
+                    self.advance(tail),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(value) };
+                        // Release：发布写入的数据
+                        slot.stamp.store(tail + 1, Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(cur) => tail = cur,
+                }
+            } else if stamp.wrapping_add(self.one_lap) == tail + 1 {
+                // 槽位里还是上一圈写入的数据；head 也还停在上一圈的同一位置时队列已满
+                atomic::fence(Ordering::SeqCst);
+                let head = self.head.load(Ordering::Relaxed);
+                if head.wrapping_add(self.one_lap) == tail {
+                    return Err(value);
+                }
+                tail = self.tail.load(Ordering::Relaxed);
+            } else {
+                // 其他生产者已抢先占用了这个位置，重新读取 tail
+                tail = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[head & (self.one_lap - 1)];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+
+            if stamp == head + 1 {
+                match self.head.compare_exchange_weak(
+                    head,
+                    self.advance(head),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+                        // 把槽位交给下一圈的生产者
+                        slot.stamp
+                            .store(head.wrapping_add(self.one_lap), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(cur) => head = cur,
+                }
+            } else if stamp == head {
+                // 该位置的生产者还没写入；tail 也停在这里时队列为空
+                atomic::fence(Ordering::SeqCst);
+                let tail = self.tail.load(Ordering::Relaxed);
+                if tail == head {
+                    return None;
+                }
+                head = self.head.load(Ordering::Relaxed);
+            } else {
+                head = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 近似长度：并发修改时只是一个快照
+    pub fn len(&self) -> usize {
+        loop {
+            let tail = self.tail.load(Ordering::SeqCst);
+            let head = self.head.load(Ordering::SeqCst);
+            // 读 head 期间 tail 没变，两者才是同一时刻的快照
+            if self.tail.load(Ordering::SeqCst) != tail {
+                continue;
+            }
+            let head_index = head & (self.one_lap - 1);
+            let tail_index = tail & (self.one_lap - 1);
+            return if head_index < tail_index {
+                tail_index - head_index
+            } else if head_index > tail_index {
+                self.capacity() - head_index + tail_index
+            } else if tail == head {
+                0
+            } else {
+                // 下标相同、圈数不同：满了
+                self.capacity()
+            };
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+}
+
+impl<T> Drop for ArrayQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_push_pop_and_full() {
+        let q = ArrayQueue::new(3);
+        assert!(q.is_empty());
+        assert_eq!(q.push(1), Ok(()));
+        assert_eq!(q.push(2), Ok(()));
+        assert_eq!(q.push(3), Ok(()));
+        assert!(q.is_full());
+        assert_eq!(q.push(4), Err(4));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.push(4), Ok(()));
+        assert_eq!(q.len(), 3);
+        assert_eq!(q.pop(), Some(2));
+        assert_eq!(q.pop(), Some(3));
+        assert_eq!(q.pop(), Some(4));
+        assert_eq!(q.pop(), None);
+    }
+
+    /// 容量为 1 时第二次 push 必须报满，而不是覆盖还没被读走的元素
+    #[test]
+    fn test_capacity_one() {
+        let item = Arc::new(());
+        let q = ArrayQueue::new(1);
+        assert_eq!(q.push(Arc::clone(&item)), Ok(()));
+        assert!(q.is_full());
+        assert!(q.push(Arc::clone(&item)).is_err());
+        assert_eq!(Arc::strong_count(&item), 2);
+        assert!(q.pop().is_some());
+        assert_eq!(q.pop(), None);
+        drop(q);
+        assert_eq!(Arc::strong_count(&item), 1);
+
+        let q = ArrayQueue::new(1);
+        for i in 0..100 {
+            assert_eq!(q.push(i), Ok(()));
+            assert_eq!(q.push(i), Err(i));
+            assert_eq!(q.len(), 1);
+            assert_eq!(q.pop(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_wraps_around_many_times() {
+        let q = ArrayQueue::new(2);
+        for i in 0..1_000 {
+            q.push(i).unwrap();
+            assert_eq!(q.pop(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_drop_remaining_items() {
+        let item = Arc::new(());
+        let q = ArrayQueue::new(4);
+        q.push(Arc::clone(&item)).unwrap();
+        q.push(Arc::clone(&item)).unwrap();
+        drop(q);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn stress_mpmc_small_capacity() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        const PER_PRODUCER: usize = 10_000;
+
+        // 容量很小，生产者会频繁遇到"满"，消费者会频繁遇到"空"
+        let q = Arc::new(ArrayQueue::new(8));
+        let sums = Arc::new(Mutex::new(Vec::new()));
+
+        spawn_workers(
+            (Arc::clone(&q), Arc::clone(&sums)),
+            PRODUCERS + CONSUMERS,
+            |(q, sums), i| {
+                if i < PRODUCERS {
+                    for v in 1..=PER_PRODUCER {
+                        let mut item = v;
+                        while let Err(back) = q.push(item) {
+                            item = back;
+                            std::thread::yield_now();
+                        }
+                    }
+                } else {
+                    let (mut sum, mut count) = (0, 0);
+                    while count < PER_PRODUCER {
+                        match q.pop() {
+                            Some(v) => {
+                                sum += v;
+                                count += 1;
+                            }
+                            None => std::thread::yield_now(),
+                        }
+                    }
+                    sums.lock().unwrap().push(sum);
+                }
+            },
+        );
+
+        // 生产者与消费者数量相同，每个消费者恰好取 PER_PRODUCER 个元素
+        let total: usize = sums.lock().unwrap().iter().sum();
+        assert_eq!(total, PRODUCERS * PER_PRODUCER * (PER_PRODUCER + 1) / 2);
+        assert!(q.is_empty());
+    }
+}
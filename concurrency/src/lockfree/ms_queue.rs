@@ -0,0 +1,225 @@
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::cache_padded::CachePadded;
+use crate::epoch;
+
+struct Node<T> {
+    // 哨兵节点的 data 未初始化；其余节点的 data 在出队时被搬走
+    data: MaybeUninit<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn alloc(data: MaybeUninit<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            data,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+/// Michael–Scott 无锁队列（无界，多生产者多消费者）
+///
+/// 链表头部始终是一个哨兵节点：`head` 指向哨兵，真正的队首是 `head.next`。
+/// 出队时把 `head` 前移一格，旧哨兵被回收，队首节点成为新的哨兵。
+pub struct MsQueue<T> {
+    // head 与 tail 分别被消费者和生产者争抢，放进不同的缓存行
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    tail: CachePadded<AtomicPtr<Node<T>>>,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for MsQueue<T> {}
+unsafe impl<T: Send> Sync for MsQueue<T> {}
+
+impl<T> MsQueue<T> {
+    pub fn new() -> Self {
+        let sentinel = Node::alloc(MaybeUninit::uninit());
+        Self {
+            head: CachePadded::new(AtomicPtr::new(sentinel)),
+            tail: CachePadded::new(AtomicPtr::new(sentinel)),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn push(&self, data: T) {
+        let node = Node::alloc(MaybeUninit::new(data));
+        let _guard = epoch::pin();
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: pin 期间 tail 指向的节点不会被释放
+            let next = unsafe { (*tail).next.load(Ordering::Acquire) };
+            if !next.is_null() {
+                // tail 落后了（其他生产者链接成功但还没来得及移动 tail），帮它推进一格
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+            // 第一步：把新节点挂到队尾。这一步成功即代表入队完成（线性化点）
+            if unsafe {
+                (*tail)
+                    .next
+                    .compare_exchange(ptr::null_mut(), node, Ordering::Release, Ordering::Relaxed)
+                    .is_ok()
+            } {
+                // 第二步：移动 tail。失败也没关系，说明已经有人帮我们推进了
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, node, Ordering::Release, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            // SAFETY: pin 期间 head 不会被释放
+            let next = unsafe { (*head).next.load(Ordering::Acquire) };
+            if next.is_null() {
+                return None;
+            }
+            // head 追上了 tail：必须先把 tail 推过去，否则 tail 会指向即将被回收的旧哨兵
+            let tail = self.tail.load(Ordering::Acquire);
+            if head == tail {
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: CAS 成功后 next 成为新哨兵，它的数据只会被我们读走一次
+                let data = unsafe { (*next).data.assume_init_read() };
+                // 旧哨兵已不可达，延迟释放
+                unsafe { guard.defer_destroy(head) };
+                return Some(data);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let _guard = epoch::pin();
+        let head = self.head.load(Ordering::Acquire);
+        unsafe { (*head).next.load(Ordering::Acquire).is_null() }
+    }
+}
+
+impl<T> Default for MsQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for MsQueue<T> {
+    fn drop(&mut self) {
+        // 哨兵节点的数据未初始化或已被取走，只释放内存
+        let sentinel = unsafe { Box::from_raw(*self.head.get_mut()) };
+        let mut cur = sentinel.next.load(Ordering::Relaxed);
+        while !cur.is_null() {
+            let mut node = unsafe { Box::from_raw(cur) };
+            cur = node.next.load(Ordering::Relaxed);
+            unsafe { node.data.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_fifo_order() {
+        let q = MsQueue::new();
+        assert!(q.is_empty());
+        for i in 0..5 {
+            q.push(i);
+        }
+        assert!(!q.is_empty());
+        for i in 0..5 {
+            assert_eq!(q.pop(), Some(i));
+        }
+        assert_eq!(q.pop(), None);
+    }
+
+    #[test]
+    fn test_drop_remaining_items() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let q = MsQueue::new();
+        for _ in 0..3 {
+            q.push(Counted(Arc::clone(&drops)));
+        }
+        drop(q.pop());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        drop(q);
+        assert_eq!(drops.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn stress_mpmc_per_producer_fifo() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        const PER_PRODUCER: usize = 10_000;
+
+        let q = Arc::new(MsQueue::new());
+        let consumed = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        spawn_workers(
+            (Arc::clone(&q), Arc::clone(&consumed), Arc::clone(&results)),
+            PRODUCERS + CONSUMERS,
+            |(q, consumed, results), i| {
+                if i < PRODUCERS {
+                    for seq in 0..PER_PRODUCER {
+                        q.push((i, seq));
+                    }
+                    return;
+                }
+                let mut local = Vec::new();
+                while consumed.load(Ordering::Relaxed) < PRODUCERS * PER_PRODUCER {
+                    match q.pop() {
+                        Some(item) => {
+                            consumed.fetch_add(1, Ordering::Relaxed);
+                            local.push(item);
+                        }
+                        None => std::thread::yield_now(),
+                    }
+                }
+                results.lock().unwrap().push(local);
+            },
+        );
+
+        let results = results.lock().unwrap();
+        let mut seen = vec![vec![false; PER_PRODUCER]; PRODUCERS];
+        for local in results.iter() {
+            // 同一消费者看到的同一生产者的元素必须严格递增（队列的 FIFO 性质）
+            let mut last = [None; PRODUCERS];
+            for &(p, seq) in local {
+                assert!(last[p].is_none_or(|l| l < seq), "FIFO 顺序被破坏");
+                last[p] = Some(seq);
+                assert!(!seen[p][seq], "元素被重复消费");
+                seen[p][seq] = true;
+            }
+        }
+        assert!(seen.iter().flatten().all(|&s| s), "有元素丢失");
+        assert!(q.is_empty());
+    }
+}
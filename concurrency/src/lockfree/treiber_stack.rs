@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::epoch;
+
+struct Node<T> {
+    // 出栈时数据会被 ptr::read 搬走，节点本身稍后由 epoch 释放，
+    // 所以释放节点时不能再析构 data
+    data: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+/// Treiber 无锁栈
+pub struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+    // 告诉编译器我们逻辑上拥有 T（影响 drop check）
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: 数据只会被整体搬入/搬出，不会被多个线程同时访问
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T> TreiberStack<T> {
+    pub fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn push(&self, data: T) {
+        let node = Box::into_raw(Box::new(Node {
+            data: ManuallyDrop::new(data),
+            next: ptr::null_mut(),
+        }));
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: node 尚未发布，只有当前线程能访问
+            unsafe { (*node).next = head };
+            // Release：保证 pop 方 Acquire 读到 node 时，node 的内容已经写好
+            match self
+                .head
+                .compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(cur) => head = cur,
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            if head.is_null() {
+                return None;
+            }
+            // SAFETY: 我们处于 pin 状态，即便 head 已被其他线程弹出，内存也不会被释放
+            let next = unsafe { (*head).next };
+            match self
+                .head
+                .compare_exchange_weak(head, next, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    // SAFETY: CAS 成功说明只有我们摘下了这个节点，数据只会被读出一次
+                    let data = unsafe { ptr::read(&(*head).data) };
+                    unsafe { guard.defer_destroy(head) };
+                    return Some(ManuallyDrop::into_inner(data));
+                }
+                Err(cur) => head = cur,
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        // &mut self：没有其他线程在访问，可以直接逐个释放
+        let mut cur = *self.head.get_mut();
+        while !cur.is_null() {
+            let mut node = unsafe { Box::from_raw(cur) };
+            cur = node.next;
+            unsafe { ManuallyDrop::drop(&mut node.data) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_lifo_order() {
+        let stack = TreiberStack::new();
+        assert!(stack.is_empty());
+        for i in 0..5 {
+            stack.push(i);
+        }
+        for i in (0..5).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_drop_remaining_items() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let stack = TreiberStack::new();
+        for _ in 0..3 {
+            stack.push(Counted(Arc::clone(&drops)));
+        }
+        drop(stack.pop());
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        drop(stack);
+        assert_eq!(drops.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn stress_push_pop() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 5_000;
+
+        let stack = Arc::new(TreiberStack::new());
+        let popped = Arc::new(Mutex::new(Vec::new()));
+
+        spawn_workers(
+            (Arc::clone(&stack), Arc::clone(&popped)),
+            THREADS,
+            |(stack, popped), i| {
+                let mut local = Vec::new();
+                for j in 0..PER_THREAD {
+                    stack.push(i * PER_THREAD + j);
+                    // 一半线程边推边弹，制造最激烈的 CAS 竞争
+                    if i % 2 == 0 {
+                        if let Some(v) = stack.pop() {
+                            local.push(v);
+                        }
+                    }
+                }
+                popped.lock().unwrap().extend(local);
+            },
+        );
+
+        let mut all = popped.lock().unwrap().clone();
+        while let Some(v) = stack.pop() {
+            all.push(v);
+        }
+        // 每个元素恰好出现一次：没有丢失，也没有重复
+        assert_eq!(all.len(), THREADS * PER_THREAD);
+        let unique: HashSet<_> = all.into_iter().collect();
+        assert_eq!(unique.len(), THREADS * PER_THREAD);
+    }
+}
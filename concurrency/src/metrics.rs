@@ -0,0 +1,17 @@
+//! 高并发下的计数与统计
+//!
+//! - [`StripedCounter`]：分条计数器，每个线程写自己的缓存行，读取时再求和
+//! - [`Gauge`]：可增可减、可直接设置的瞬时值
+//! - [`MaxTracker`] / [`MinTracker`]：记录出现过的最大/最小值
+//! - [`Histogram`]：对数-线性分桶的直方图（类似 HdrHistogram），用于多线程记录延迟
+//!
+//! 全部基于原子操作，没有锁。它们只保证每次更新不丢失，
+//! 读取到的是"某一时刻附近"的值，而不是所有线程的一致快照。
+
+mod counter;
+mod histogram;
+mod tracker;
+
+pub use counter::StripedCounter;
+pub use histogram::{Histogram, HistogramSnapshot};
+pub use tracker::{Gauge, MaxTracker, MinTracker};
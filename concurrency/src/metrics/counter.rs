@@ -0,0 +1,145 @@
+use crate::cache_padded::CachePadded;
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::thread;
+
+/// 分条计数器
+///
+/// 单个 `AtomicUsize` 被很多线程同时 `fetch_add` 时，所在缓存行要在核之间来回传递，
+/// 线程越多越慢。这里准备若干个按缓存行对齐的计数单元，每个线程固定写其中一个，
+/// 写操作之间几乎没有争用；代价是 [`sum`](Self::sum) 需要遍历所有单元。
+pub struct StripedCounter {
+    cells: Box<[CachePadded<AtomicI64>]>,
+}
+
+/// 给每个线程分配一个编号，用来选择计数单元
+fn thread_stripe() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static STRIPE: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+    STRIPE.with(|s| match s.get() {
+        Some(i) => i,
+        None => {
+            let i = NEXT.fetch_add(1, Ordering::Relaxed);
+            s.set(Some(i));
+            i
+        }
+    })
+}
+
+impl StripedCounter {
+    /// 单元数取 CPU 核数的 2 倍（向上取整到 2 的幂）
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_stripes(cpus * 2)
+    }
+
+    pub fn with_stripes(stripes: usize) -> Self {
+        assert!(stripes > 0, "单元数必须大于 0");
+        Self {
+            cells: (0..stripes.next_power_of_two())
+                .map(|_| CachePadded::new(AtomicI64::new(0)))
+                .collect(),
+        }
+    }
+
+    fn cell(&self) -> &AtomicI64 {
+        &self.cells[thread_stripe() & (self.cells.len() - 1)]
+    }
+
+    pub fn add(&self, n: i64) {
+        self.cell().fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// 所有单元之和。与并发的写操作同时进行时，结果介于调用开始和结束时的值之间
+    ///
+    /// 和单元上的 `fetch_add` 一样按 2^64 回绕，不会因为溢出而 panic
+    pub fn sum(&self) -> i64 {
+        self.cells
+            .iter()
+            .fold(0i64, |acc, c| acc.wrapping_add(c.load(Ordering::Relaxed)))
+    }
+
+    /// 读取并清零，适合周期性上报"这段时间内的增量"。每个单元的 swap 是原子的，不会丢计数
+    pub fn take(&self) -> i64 {
+        self.cells.iter().fold(0i64, |acc, c| {
+            acc.wrapping_add(c.swap(0, Ordering::Relaxed))
+        })
+    }
+
+    pub fn stripes(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl Default for StripedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StripedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StripedCounter")
+            .field("sum", &self.sum())
+            .field("stripes", &self.cells.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_add_and_take() {
+        let c = StripedCounter::with_stripes(3);
+        assert_eq!(c.stripes(), 4);
+        c.inc();
+        c.add(10);
+        c.dec();
+        assert_eq!(c.sum(), 10);
+        assert_eq!(c.take(), 10);
+        assert_eq!(c.sum(), 0);
+    }
+
+    /// 两个单元各自接近 i64::MAX 时求和要回绕，而不是在 debug 构建下 panic
+    #[test]
+    fn test_sum_wraps_across_stripes() {
+        let c = StripedCounter::with_stripes(2);
+        c.cells[0].store(i64::MAX, Ordering::Relaxed);
+        c.cells[1].store(i64::MAX, Ordering::Relaxed);
+        assert_eq!(c.sum(), i64::MAX.wrapping_add(i64::MAX));
+        assert_eq!(c.sum() as u64, 2 * i64::MAX as u64);
+        assert_eq!(c.take() as u64, 2 * i64::MAX as u64);
+        assert_eq!(c.sum(), 0);
+    }
+
+    #[test]
+    fn stress_no_lost_updates() {
+        let c = Arc::new(StripedCounter::with_stripes(4));
+        let taken = Arc::new(AtomicI64::new(0));
+        spawn_workers((Arc::clone(&c), Arc::clone(&taken)), 12, |(c, taken), i| {
+            for n in 0..5_000 {
+                c.inc();
+                // 同时有线程在 take：被取走的部分加上剩余部分必须等于总数
+                if i == 0 && n % 1_000 == 0 {
+                    taken.fetch_add(c.take(), Ordering::Relaxed);
+                }
+            }
+        });
+        assert_eq!(c.sum() + taken.load(Ordering::Relaxed), 12 * 5_000);
+    }
+}
@@ -0,0 +1,295 @@
+use super::StripedCounter;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 每个 2 的幂区间内保留的有效二进制位数。
+/// 区间 [2^k, 2^(k+1)) 被均分为 2^(BITS-1) 个桶，相对误差不超过 1/2^(BITS-1) ≈ 3%
+const SIGNIFICANT_BITS: u32 = 6;
+const SUB_BUCKETS: u64 = 1 << SIGNIFICANT_BITS;
+const HALF: u64 = SUB_BUCKETS / 2;
+const BUCKETS: usize = bucket_index(u64::MAX) + 1;
+
+/// 小于 SUB_BUCKETS 的值每个值一个桶（精确）；更大的值只保留最高的 SIGNIFICANT_BITS 位
+const fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS {
+        return value as usize;
+    }
+    let msb = 63 - value.leading_zeros();
+    let shift = msb - (SIGNIFICANT_BITS - 1);
+    let mantissa = value >> shift;
+    (shift as u64 * HALF + mantissa) as usize
+}
+
+/// 桶所覆盖的闭区间 [low, high]
+fn bucket_range(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return (index, index);
+    }
+    let shift = index / HALF - 1;
+    let mantissa = index - shift * HALF;
+    let low = mantissa << shift;
+    (low, low + ((1u64 << shift) - 1))
+}
+
+/// 对数-线性分桶的并发直方图（思路来自 HdrHistogram）
+///
+/// 记录一个值只需要对一个桶做一次 `fetch_add`，不同线程记录不同大小的值时碰到的是不同的缓存行。
+/// 统计分位数时再对快照做一次遍历。
+pub struct Histogram {
+    buckets: Box<[AtomicU64]>,
+    sum: StripedCounter,
+    /// 极值按 u64 完整记录，没有"未记录"的哨兵值：是否为空看桶里的计数
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            sum: StripedCounter::new(),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, value: u64) {
+        // 先更新极值再计数：快照里看到了这次计数，就一定也看到了它对极值的更新
+        self.min.fetch_min(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+        // 总和按 2^64 取模累加，转换回 u64 后仍然正确
+        self.sum.add(value as i64);
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Release);
+    }
+
+    /// 以纳秒为单位记录一段耗时
+    pub fn record_duration(&self, duration: Duration) {
+        self.record(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX));
+    }
+
+    /// 复制当前所有计数。与并发的 record 同时进行时，快照中的各项可能来自略微不同的时刻
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|b| b.load(Ordering::Acquire))
+            .collect();
+        HistogramSnapshot::new(
+            counts,
+            self.sum.sum() as u64,
+            self.min.load(Ordering::Relaxed),
+            self.max.load(Ordering::Relaxed),
+        )
+    }
+
+    /// 取出快照并清零，适合按时间窗口上报
+    pub fn take(&self) -> HistogramSnapshot {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|b| b.swap(0, Ordering::Acquire))
+            .collect();
+        HistogramSnapshot::new(
+            counts,
+            self.sum.take() as u64,
+            self.min.swap(u64::MAX, Ordering::Relaxed),
+            self.max.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.snapshot().fmt(f)
+    }
+}
+
+#[derive(Clone)]
+pub struct HistogramSnapshot {
+    counts: Vec<u64>,
+    sum: u64,
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+impl HistogramSnapshot {
+    fn new(counts: Vec<u64>, sum: u64, min: u64, max: u64) -> Self {
+        let empty = counts.iter().all(|&n| n == 0);
+        Self {
+            counts,
+            sum,
+            min: (!empty).then_some(min),
+            max: (!empty).then_some(max),
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| self.sum as f64 / count as f64)
+    }
+
+    /// 第 p 百分位数 (0..=100)。返回所在桶的上界，并限制在 [min, max] 内
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        assert!((0.0..=100.0).contains(&p), "百分位必须在 0 到 100 之间");
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((p / 100.0 * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                let high = bucket_range(index).1;
+                let high = self.max.map_or(high, |max| high.min(max));
+                return Some(self.min.map_or(high, |min| high.max(min)));
+            }
+        }
+        self.max
+    }
+
+    /// 非空的桶：(下界, 上界, 个数)
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n > 0)
+            .map(|(i, &n)| {
+                let (low, high) = bucket_range(i);
+                (low, high, n)
+            })
+    }
+}
+
+impl fmt::Debug for HistogramSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistogramSnapshot")
+            .field("count", &self.count())
+            .field("min", &self.min)
+            .field("p50", &self.percentile(50.0))
+            .field("p99", &self.percentile(99.0))
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_bucket_layout() {
+        // 桶连续、不重叠、覆盖整个 u64 范围
+        let mut expected_low = 0u64;
+        for i in 0..BUCKETS {
+            let (low, high) = bucket_range(i);
+            assert_eq!(low, expected_low, "bucket {}", i);
+            assert_eq!(bucket_index(low), i);
+            assert_eq!(bucket_index(high), i);
+            // 相对误差上界
+            assert!((high - low) as f64 <= low as f64 / HALF as f64);
+            expected_low = high.wrapping_add(1);
+        }
+        assert_eq!(expected_low, 0);
+    }
+
+    #[test]
+    fn test_percentiles_within_error() {
+        let h = Histogram::new();
+        assert_eq!(h.snapshot().percentile(50.0), None);
+        for v in 1..=10_000u64 {
+            h.record(v);
+        }
+        let s = h.snapshot();
+        assert_eq!(s.count(), 10_000);
+        assert_eq!(s.sum(), 10_000 * 10_001 / 2);
+        assert_eq!((s.min(), s.max()), (Some(1), Some(10_000)));
+        assert_eq!(s.percentile(100.0), Some(10_000));
+        assert_eq!(s.percentile(0.0), Some(1));
+        for (p, exact) in [(50.0, 5_000.0), (90.0, 9_000.0), (99.0, 9_900.0)] {
+            let got = s.percentile(p).unwrap() as f64;
+            assert!((got - exact).abs() / exact < 0.035, "p{}: {}", p, got);
+        }
+        assert_eq!(s.buckets().map(|(_, _, n)| n).sum::<u64>(), 10_000);
+
+        let taken = h.take();
+        assert_eq!(taken.count(), 10_000);
+        assert_eq!(h.snapshot().count(), 0);
+        assert_eq!(h.snapshot().max(), None);
+    }
+
+    #[test]
+    fn test_large_values() {
+        let h = Histogram::new();
+        h.record(u64::MAX);
+        h.record_duration(Duration::from_secs(3));
+        let s = h.snapshot();
+        assert_eq!(s.count(), 2);
+        assert_eq!(s.min(), Some(3_000_000_000));
+        assert_eq!(s.max(), Some(u64::MAX));
+        assert_eq!(s.percentile(100.0), Some(u64::MAX));
+
+        // 只有一个 u64::MAX 时它既是最小值也是最大值
+        let h = Histogram::new();
+        h.record(u64::MAX);
+        let s = h.take();
+        assert_eq!(
+            (s.count(), s.min(), s.max()),
+            (1, Some(u64::MAX), Some(u64::MAX))
+        );
+        assert_eq!(s.percentile(0.0), Some(u64::MAX));
+        assert_eq!(h.snapshot().min(), None);
+    }
+
+    /// 多个线程各记一个接近 i64::MAX 的值：总和跨单元回绕，快照里按 u64 仍然正确
+    #[test]
+    fn test_sum_of_huge_values_from_threads() {
+        let h = Arc::new(Histogram::new());
+        spawn_workers(Arc::clone(&h), 2, |h, _| {
+            h.record(5_000_000_000_000_000_000)
+        });
+        assert_eq!(h.snapshot().sum(), 10_000_000_000_000_000_000);
+        assert_eq!(h.take().sum(), 10_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn stress_concurrent_record() {
+        let h = Arc::new(Histogram::new());
+        spawn_workers(Arc::clone(&h), 8, |h, i| {
+            for v in 0..10_000u64 {
+                h.record(v * (i as u64 + 1));
+            }
+        });
+        let s = h.snapshot();
+        assert_eq!(s.count(), 80_000);
+        let expected_sum: u64 = (1..=8u64).map(|k| k * 9_999 * 10_000 / 2).sum();
+        assert_eq!(s.sum(), expected_sum);
+        assert_eq!(s.max(), Some(9_999 * 8));
+    }
+}
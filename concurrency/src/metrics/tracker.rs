@@ -0,0 +1,179 @@
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// 瞬时值：例如当前连接数、队列长度
+#[derive(Default)]
+pub struct Gauge {
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(value: i64) -> Self {
+        Self {
+            value: AtomicI64::new(value),
+        }
+    }
+
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn add(&self, n: i64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn sub(&self, n: i64) {
+        self.value.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn dec(&self) {
+        self.sub(1);
+    }
+}
+
+impl fmt::Debug for Gauge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Gauge").field(&self.get()).finish()
+    }
+}
+
+/// 最大值记录器。内部用 `i64::MIN` 表示"尚未记录"，所以记录 `i64::MIN` 本身与未记录无法区分
+pub struct MaxTracker {
+    value: AtomicI64,
+}
+
+impl MaxTracker {
+    const EMPTY: i64 = i64::MIN;
+
+    pub const fn new() -> Self {
+        Self {
+            value: AtomicI64::new(Self::EMPTY),
+        }
+    }
+
+    /// fetch_max 是单条原子指令，多个线程同时记录也不会丢掉更大的值。
+    /// 先做一次普通读取：大多数记录不会刷新最大值，这样就不必独占缓存行
+    pub fn record(&self, value: i64) {
+        if value > self.value.load(Ordering::Relaxed) {
+            self.value.fetch_max(value, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get(&self) -> Option<i64> {
+        Some(self.value.load(Ordering::Relaxed)).filter(|&v| v != Self::EMPTY)
+    }
+
+    /// 取出当前值并重置，用于按时间窗口统计
+    pub fn take(&self) -> Option<i64> {
+        Some(self.value.swap(Self::EMPTY, Ordering::Relaxed)).filter(|&v| v != Self::EMPTY)
+    }
+}
+
+impl Default for MaxTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MaxTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MaxTracker").field(&self.get()).finish()
+    }
+}
+
+/// 最小值记录器。内部用 `i64::MAX` 表示"尚未记录"
+pub struct MinTracker {
+    value: AtomicI64,
+}
+
+impl MinTracker {
+    const EMPTY: i64 = i64::MAX;
+
+    pub const fn new() -> Self {
+        Self {
+            value: AtomicI64::new(Self::EMPTY),
+        }
+    }
+
+    pub fn record(&self, value: i64) {
+        if value < self.value.load(Ordering::Relaxed) {
+            self.value.fetch_min(value, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get(&self) -> Option<i64> {
+        Some(self.value.load(Ordering::Relaxed)).filter(|&v| v != Self::EMPTY)
+    }
+
+    pub fn take(&self) -> Option<i64> {
+        Some(self.value.swap(Self::EMPTY, Ordering::Relaxed)).filter(|&v| v != Self::EMPTY)
+    }
+}
+
+impl Default for MinTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MinTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MinTracker").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_gauge() {
+        let g = Gauge::new(5);
+        g.inc();
+        g.sub(3);
+        assert_eq!(g.get(), 3);
+        g.set(-1);
+        assert_eq!(g.get(), -1);
+    }
+
+    #[test]
+    fn test_trackers_empty_and_take() {
+        let max = MaxTracker::new();
+        let min = MinTracker::new();
+        assert_eq!((max.get(), min.get()), (None, None));
+        for v in [3, -7, 12, 0] {
+            max.record(v);
+            min.record(v);
+        }
+        assert_eq!((max.get(), min.get()), (Some(12), Some(-7)));
+        assert_eq!(max.take(), Some(12));
+        assert_eq!(max.get(), None);
+    }
+
+    #[test]
+    fn stress_concurrent_extremes() {
+        let trackers = Arc::new((MaxTracker::new(), MinTracker::new(), Gauge::default()));
+        spawn_workers(Arc::clone(&trackers), 8, |t, i| {
+            for n in 0..1_000i64 {
+                let v = n * 8 + i as i64;
+                t.0.record(v);
+                t.1.record(-v);
+                t.2.inc();
+                t.2.dec();
+            }
+        });
+        assert_eq!(trackers.0.get(), Some(999 * 8 + 7));
+        assert_eq!(trackers.1.get(), Some(-(999 * 8 + 7)));
+        assert_eq!(trackers.2.get(), 0);
+    }
+}
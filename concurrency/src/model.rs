@@ -0,0 +1,120 @@
+//! 确定性的并发模型检查（`model-check` 特性）
+//!
+//! 手写的同步原语（[`SpinLock`](crate::spinlock::SpinLock)、[`MyArc`](crate::arc::MyArc)、
+//! [`BoundedBuffer`](crate::bounded_buffer::BoundedBuffer)）里的 bug 往往只在极少见的交错下出现，
+//! 普通的多线程测试只能"跑很多遍碰运气"。
+//!
+//! 这些类型使用的原子变量、锁、条件变量和线程都从本模块导入。默认情况下它们就是 std 里的类型，
+//! 开启 `model-check` 特性后换成 [loom](https://docs.rs/loom) 的实现：
+//! [`check`] 里的闭包会在 loom 的确定性调度器下被反复执行，
+//! 穷举抢占次数不超过上限的所有线程交错以及弱内存序允许的所有取值，
+//! 任何一种交错下断言失败、死锁或对 [`cell::UnsafeCell`] 的无同步并发访问都会让测试失败。
+//!
+//! 模型测试都通过 [`check`] 运行，统一放在各文件的 `model_tests` 模块里（示例程序里的 `test_spinlock`、
+//! `test_arc_multithreaded` 也在其中）。其余测试在 loom 调度器之外使用这些类型会直接 panic，
+//! 所以放在 `#[cfg(all(test, not(feature = "model-check")))]` 的 `tests` 模块里，开启特性后不参与编译。
+//! 新测试放进哪个模块就决定了它在哪种模式下运行，可以直接跑整个包：
+//!
+//! ```text
+//! cargo test --release -p learning-concurrency --features model-check
+//! ```
+//!
+//! 抢占次数上限默认为 [`DEFAULT_PREEMPTION_BOUND`]，可以用环境变量 `LOOM_MAX_PREEMPTIONS` 调整；
+//! 失败时设置 `LOOM_LOCATION=1 LOOM_LOG=1` 可以看到出错的那条执行路径。
+//! 不开特性时，模型测试就是普通的多线程测试，`check` 会把闭包执行 [`NATIVE_ITERATIONS`] 次。
+
+/// 模型检查时每次执行最多允许的抢占次数。绝大多数并发 bug 只需要 2~3 次抢占就能触发
+pub const DEFAULT_PREEMPTION_BOUND: usize = 3;
+
+/// 不开 `model-check` 时 [`check`] 重复执行的次数
+pub const NATIVE_ITERATIONS: usize = 100;
+
+/// 在模型检查器下运行 `f`
+#[cfg(feature = "model-check")]
+pub fn check<F>(f: F)
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    let mut builder = loom::model::Builder::new();
+    if builder.preemption_bound.is_none() {
+        builder.preemption_bound = Some(DEFAULT_PREEMPTION_BOUND);
+    }
+    builder.check(f);
+}
+
+/// 在模型检查器下运行 `f`
+#[cfg(not(feature = "model-check"))]
+pub fn check<F>(f: F)
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    for _ in 0..NATIVE_ITERATIONS {
+        f();
+    }
+}
+
+#[cfg(feature = "model-check")]
+pub use loom::{hint, thread};
+#[cfg(not(feature = "model-check"))]
+pub use std::{hint, thread};
+
+pub mod sync {
+    #[cfg(feature = "model-check")]
+    pub use loom::sync::{Arc, Condvar, Mutex, MutexGuard};
+    #[cfg(not(feature = "model-check"))]
+    pub use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+    pub mod atomic {
+        #[cfg(feature = "model-check")]
+        pub use loom::sync::atomic::{fence, AtomicBool, AtomicUsize};
+        #[cfg(not(feature = "model-check"))]
+        pub use std::sync::atomic::{fence, AtomicBool, AtomicUsize};
+
+        pub use std::sync::atomic::Ordering;
+    }
+}
+
+pub mod cell {
+    #[cfg(feature = "model-check")]
+    pub use loom::cell::UnsafeCell;
+
+    /// 与 loom 的 `UnsafeCell` 接口相同的 std 版本
+    ///
+    /// 访问必须通过 `with` / `with_mut` 进行，loom 据此检查每次访问是否与其他线程的访问有 happens-before 关系。
+    /// 模型测试里用它包装被保护的数据，互斥或内存序写错时 loom 会直接报告数据竞争
+    #[cfg(not(feature = "model-check"))]
+    #[derive(Debug, Default)]
+    pub struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+    #[cfg(not(feature = "model-check"))]
+    impl<T> UnsafeCell<T> {
+        pub const fn new(data: T) -> Self {
+            Self(std::cell::UnsafeCell::new(data))
+        }
+
+        pub fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        pub fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner()
+        }
+    }
+}
+
+/// loom 的原子类型没有 `const fn new`，模型检查时把构造函数里的 `const` 去掉
+macro_rules! const_fn_unless_model {
+    ($(#[$attr:meta])* $vis:vis const fn $($rest:tt)*) => {
+        #[cfg(not(feature = "model-check"))]
+        $(#[$attr])* $vis const fn $($rest)*
+
+        #[cfg(feature = "model-check")]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+pub(crate) use const_fn_unless_model;
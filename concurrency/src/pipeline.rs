@@ -0,0 +1,550 @@
+//! 多级并行流水线
+//!
+//! ```
+//! use learning_concurrency::pipeline::Pipeline;
+//!
+//! let out: Result<Vec<String>, String> = Pipeline::source(1..=5)
+//!     .stage(4, |x: i32| Ok(x * x))
+//!     .stage(2, |x| Ok(format!("#{}", x)))
+//!     .collect();
+//! assert_eq!(out.unwrap(), ["#1", "#4", "#9", "#16", "#25"]);
+//! ```
+//!
+//! - 每一级由若干工作线程组成，级与级之间用 [`crate::channel::bounded`] 连接，
+//!   下游处理不过来时上游自然阻塞在 send 上（背压），内存占用与数据总量无关
+//! - 默认保序：每个元素带着来源序号，每一级在输出前按序号重排；
+//!   [`unordered`](Pipeline::unordered) 关闭重排，谁先处理完谁先往下走
+//! - 任一级返回 `Err` 或 panic 时整条流水线停止：
+//!   数据源不再产出，各级线程退出，`sink` 返回第一个错误（panic 则在调用线程上重新抛出）
+//! - [`cancel_on`](Pipeline::cancel_on) 把流水线挂到一个 [`CancellationToken`] 上，
+//!   令牌取消时按出错处理，`sink` 返回由 [`Cancelled`] 转换来的错误
+//!
+//! 线程在调用 [`sink`](Pipeline::sink) / [`collect`](Pipeline::collect) 时才启动，
+//! 所以 `capacity` 和 `unordered` 可以在构建过程中的任意位置设置，作用于整条流水线。
+
+use crate::channel::{self, Receiver, Sender};
+use crate::sync::{CancellationToken, Cancelled, Semaphore};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+const DEFAULT_CAPACITY: usize = 16;
+
+/// 取消时一次性放出的许可数，足够让所有卡在重排窗口上的工作线程醒来
+const OPEN_GATE: usize = usize::MAX >> 2;
+
+/// 元素与它在数据源中的序号
+type Item<T> = (u64, T);
+
+/// 外部取消令牌，以及把 [`Cancelled`] 转成流水线错误类型的函数
+type CancelOn<E> = (CancellationToken, fn(Cancelled) -> E);
+
+type Launch<T, E> = Box<dyn FnOnce(&mut Runtime<E>) -> Receiver<Item<T>>>;
+
+/// 整条流水线共享的停止信号与第一个错误
+struct Control<E> {
+    cancelled: AtomicBool,
+    error: Mutex<Option<E>>,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    /// 保序级的重排窗口，取消时全部打开，避免工作线程永远等不到许可
+    gates: Mutex<Vec<Arc<Semaphore>>>,
+}
+
+impl<E> Control<E> {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    fn cancel(&self) {
+        if !self.cancelled.swap(true, Ordering::AcqRel) {
+            for gate in self.gates.lock().unwrap().iter() {
+                gate.add_permits(OPEN_GATE);
+            }
+        }
+    }
+
+    /// 只保留第一个错误；已经有 panic 时错误不再重要
+    fn fail(&self, err: E) {
+        let mut slot = self.error.lock().unwrap();
+        if slot.is_none() && !self.is_cancelled() {
+            *slot = Some(err);
+        }
+        drop(slot);
+        self.cancel();
+    }
+
+    fn panicked(&self, payload: Box<dyn Any + Send>) {
+        self.panic.lock().unwrap().get_or_insert(payload);
+        self.cancel();
+    }
+
+    /// 在线程里运行 `f`，把 panic 转成取消信号而不是让线程悄悄死掉：
+    /// 否则保序级会永远等着那个丢失的序号
+    fn guard(&self, f: impl FnOnce()) {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+            self.panicked(payload);
+        }
+    }
+}
+
+struct Runtime<E> {
+    capacity: usize,
+    ordered: bool,
+    control: Arc<Control<E>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<E: Send + 'static> Runtime<E> {
+    fn spawn(&mut self, name: String, f: impl FnOnce(&Control<E>) + Send + 'static) {
+        let control = Arc::clone(&self.control);
+        let handle = thread::Builder::new()
+            .name(name)
+            .spawn(move || control.guard(|| f(&control)))
+            .expect("创建流水线线程失败");
+        self.threads.push(handle);
+    }
+}
+
+/// 流水线构建器，`T` 是当前最后一级的输出类型，`E` 是各级共用的错误类型
+pub struct Pipeline<T, E> {
+    capacity: usize,
+    ordered: bool,
+    stages: usize,
+    cancel_on: Option<CancelOn<E>>,
+    launch: Launch<T, E>,
+}
+
+impl<T, E> Pipeline<T, E>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    /// 以迭代器为数据源，迭代器在单独的线程里被消费
+    pub fn source<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+    {
+        let iter = iter.into_iter();
+        Pipeline {
+            capacity: DEFAULT_CAPACITY,
+            ordered: true,
+            stages: 0,
+            cancel_on: None,
+            launch: Box::new(move |rt: &mut Runtime<E>| {
+                let (tx, rx) = channel::bounded(rt.capacity);
+                rt.spawn("pipeline-source".into(), move |control| {
+                    for item in (0u64..).zip(iter) {
+                        if control.is_cancelled() || tx.send(item).is_err() {
+                            break;
+                        }
+                    }
+                });
+                rx
+            }),
+        }
+    }
+
+    /// 每两级之间通道的容量（默认 16）
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "容量必须大于 0");
+        self.capacity = capacity;
+        self
+    }
+
+    /// 不保证输出顺序，省掉每一级的重排开销
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    /// 令牌被取消时停止整条流水线，`sink` 返回 `E::from(Cancelled)`
+    pub fn cancel_on(mut self, token: &CancellationToken) -> Self
+    where
+        E: From<Cancelled>,
+    {
+        self.cancel_on = Some((token.clone(), E::from));
+        self
+    }
+
+    /// 追加一级：`threads` 个线程并行地对每个元素调用 `f`
+    pub fn stage<U, F>(self, threads: usize, f: F) -> Pipeline<U, E>
+    where
+        U: Send + 'static,
+        F: Fn(T) -> Result<U, E> + Send + Sync + 'static,
+    {
+        assert!(threads > 0, "每一级至少需要一个线程");
+        let index = self.stages;
+        let prev = self.launch;
+        Pipeline {
+            capacity: self.capacity,
+            ordered: self.ordered,
+            stages: self.stages + 1,
+            cancel_on: self.cancel_on,
+            launch: Box::new(move |rt: &mut Runtime<E>| {
+                let input = prev(rt);
+                let f = Arc::new(f);
+                if rt.ordered {
+                    launch_ordered(rt, index, threads, input, f)
+                } else {
+                    launch_unordered(rt, index, threads, input, f)
+                }
+            }),
+        }
+    }
+
+    /// 启动流水线，在调用线程上依次把输出交给 `f`，直到数据源耗尽或出错
+    ///
+    /// 返回前会等待所有线程退出。任一级（包括 `f` 自己）返回的第一个 `Err` 作为结果返回；
+    /// 任一级 panic 时在这里重新 panic
+    pub fn sink<F>(self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(T) -> Result<(), E>,
+    {
+        let control = Arc::new(Control {
+            cancelled: AtomicBool::new(false),
+            error: Mutex::new(None),
+            panic: Mutex::new(None),
+            gates: Mutex::new(Vec::new()),
+        });
+        // sink 返回时 registration 被 drop，回调随之注销
+        let _registration = self.cancel_on.map(|(token, into_error)| {
+            let control = Arc::clone(&control);
+            token.on_cancel(move || control.fail(into_error(Cancelled)))
+        });
+        let mut rt = Runtime {
+            capacity: self.capacity,
+            ordered: self.ordered,
+            control: Arc::clone(&control),
+            threads: Vec::new(),
+        };
+        let output = (self.launch)(&mut rt);
+
+        control.guard(|| {
+            for (_, item) in output.iter() {
+                if control.is_cancelled() {
+                    break;
+                }
+                if let Err(err) = f(item) {
+                    control.fail(err);
+                    break;
+                }
+            }
+        });
+        // 先丢掉接收端，让仍在 send 的上游立刻收到断开错误
+        drop(output);
+        for thread in rt.threads {
+            let _ = thread.join();
+        }
+
+        if let Some(payload) = control.panic.lock().unwrap().take() {
+            panic::resume_unwind(payload);
+        }
+        let error = control.error.lock().unwrap().take();
+        error.map_or(Ok(()), Err)
+    }
+
+    /// 把所有输出收集到 Vec 中
+    pub fn collect(self) -> Result<Vec<T>, E> {
+        let mut out = Vec::new();
+        self.sink(|item| {
+            out.push(item);
+            Ok(())
+        })?;
+        Ok(out)
+    }
+}
+
+impl<T, E> fmt::Debug for Pipeline<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("stages", &self.stages)
+            .field("capacity", &self.capacity)
+            .field("ordered", &self.ordered)
+            .field("cancellable", &self.cancel_on.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// 不保序：工作线程直接把结果发往下一级
+fn launch_unordered<T, U, E, F>(
+    rt: &mut Runtime<E>,
+    index: usize,
+    threads: usize,
+    input: Receiver<Item<T>>,
+    f: Arc<F>,
+) -> Receiver<Item<U>>
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    E: Send + 'static,
+    F: Fn(T) -> Result<U, E> + Send + Sync + 'static,
+{
+    let (tx, rx) = channel::bounded(rt.capacity);
+    for i in 0..threads {
+        let (input, tx, f) = (input.clone(), tx.clone(), Arc::clone(&f));
+        rt.spawn(format!("pipeline-{}-{}", index, i), move |control| {
+            worker(control, &input, &tx, &*f, || {});
+        });
+    }
+    rx
+}
+
+/// 保序：工作线程的结果先汇总到重排线程，按序号补齐后再发往下一级
+///
+/// 只有重排窗口（`capacity + threads` 个许可）以内的元素可以被取走处理，
+/// 一个特别慢的元素最多让窗口内的元素排队等它，重排缓冲不会无限增长
+fn launch_ordered<T, U, E, F>(
+    rt: &mut Runtime<E>,
+    index: usize,
+    threads: usize,
+    input: Receiver<Item<T>>,
+    f: Arc<F>,
+) -> Receiver<Item<U>>
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    E: Send + 'static,
+    F: Fn(T) -> Result<U, E> + Send + Sync + 'static,
+{
+    let window = rt.capacity + threads;
+    let gate = Arc::new(Semaphore::new(window));
+    rt.control.gates.lock().unwrap().push(Arc::clone(&gate));
+    if rt.control.is_cancelled() {
+        gate.add_permits(OPEN_GATE);
+    }
+
+    // 许可总数限制了在途元素，所以这条内部通道不会超过 window 条
+    let (done_tx, done_rx) = channel::unbounded::<Item<U>>();
+    for i in 0..threads {
+        let (input, done_tx, f, gate) = (
+            input.clone(),
+            done_tx.clone(),
+            Arc::clone(&f),
+            Arc::clone(&gate),
+        );
+        rt.spawn(format!("pipeline-{}-{}", index, i), move |control| {
+            // 许可由重排线程在元素真正发往下一级时归还
+            worker(control, &input, &done_tx, &*f, || gate.acquire().forget());
+        });
+    }
+    drop(done_tx);
+
+    let (tx, rx) = channel::bounded(rt.capacity);
+    rt.spawn(format!("pipeline-{}-reorder", index), move |control| {
+        let mut pending = BTreeMap::new();
+        let mut next = 0u64;
+        'outer: for (seq, item) in done_rx.iter() {
+            if control.is_cancelled() {
+                break;
+            }
+            pending.insert(seq, item);
+            while let Some(item) = pending.remove(&next) {
+                if tx.send((next, item)).is_err() {
+                    break 'outer;
+                }
+                next += 1;
+                gate.add_permits(1);
+            }
+        }
+        // 下游提前退出时，还有工作线程可能正等着许可
+        gate.add_permits(threads);
+    });
+    rx
+}
+
+fn worker<T, U, E, F>(
+    control: &Control<E>,
+    input: &Receiver<Item<T>>,
+    output: &Sender<Item<U>>,
+    f: &F,
+    before_recv: impl Fn(),
+) where
+    F: Fn(T) -> Result<U, E>,
+{
+    while !control.is_cancelled() {
+        before_recv();
+        let Ok((seq, item)) = input.recv() else {
+            break;
+        };
+        if control.is_cancelled() {
+            break;
+        }
+        match f(item) {
+            Ok(out) => {
+                if output.send((seq, out)).is_err() {
+                    break;
+                }
+            }
+            Err(err) => {
+                control.fail(err);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ordered_output_matches_input_order() {
+        let out: Result<Vec<u64>, ()> = Pipeline::source(0..200u64)
+            .capacity(4)
+            .stage(4, |x| {
+                // 让处理时间参差不齐，乱序完成
+                thread::sleep(Duration::from_micros((x % 7) * 100));
+                Ok(x * 2)
+            })
+            .stage(3, |x| Ok(x + 1))
+            .collect();
+        assert_eq!(
+            out.unwrap(),
+            (0..200).map(|x| x * 2 + 1).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unordered_keeps_every_item() {
+        let mut out: Vec<u64> = Pipeline::<_, ()>::source(0..500u64)
+            .unordered()
+            .stage(4, |x| Ok(x * 3))
+            .collect()
+            .unwrap();
+        out.sort_unstable();
+        assert_eq!(out, (0..500).map(|x| x * 3).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stage_error_stops_an_infinite_source() {
+        let produced = Arc::new(AtomicUsize::new(0));
+        let source = {
+            let produced = Arc::clone(&produced);
+            (0u64..).inspect(move |_| {
+                produced.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        let result = Pipeline::source(source)
+            .capacity(8)
+            .stage(2, Ok)
+            .stage(3, |x| {
+                if x == 100 {
+                    Err(format!("坏数据 {}", x))
+                } else {
+                    Ok(x)
+                }
+            })
+            .sink(|_| Ok(()));
+        assert_eq!(result, Err(String::from("坏数据 100")));
+        // 背压限制了出错时数据源最多跑到哪里
+        assert!(produced.load(Ordering::Relaxed) < 200);
+    }
+
+    #[test]
+    fn test_sink_error_and_backpressure() {
+        let produced = Arc::new(AtomicUsize::new(0));
+        let source = {
+            let produced = Arc::clone(&produced);
+            (0..10_000).inspect(move |_| {
+                produced.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        let mut seen = 0;
+        let result = Pipeline::source(source)
+            .capacity(2)
+            .stage(2, |x: i32| Ok(x))
+            .sink(|_| {
+                seen += 1;
+                thread::sleep(Duration::from_millis(1));
+                if seen == 20 {
+                    Err("sink 失败")
+                } else {
+                    Ok(())
+                }
+            });
+        assert_eq!(result, Err("sink 失败"));
+        // 两条通道各 2 个、重排窗口 4 个、两个工作线程手里各 1 个，数据源手里 1 个
+        assert!(produced.load(Ordering::Relaxed) <= 20 + 2 + 4 + 2 + 2 + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "第三级崩溃")]
+    fn test_stage_panic_is_resumed_on_caller() {
+        let _ = Pipeline::<_, ()>::source(0..)
+            .stage(2, Ok)
+            .stage(2, |x: u32| {
+                if x == 50 {
+                    panic!("第三级崩溃")
+                } else {
+                    Ok(x)
+                }
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_cancel_on_stops_an_infinite_source() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Cancelled,
+        }
+        impl From<Cancelled> for Error {
+            fn from(_: Cancelled) -> Self {
+                Error::Cancelled
+            }
+        }
+
+        let token = CancellationToken::new();
+        let mut seen = 0u64;
+        let result = Pipeline::source(0u64..)
+            .cancel_on(&token)
+            .stage(2, |x| Ok(x * 2))
+            .sink(|_| {
+                seen += 1;
+                if seen == 100 {
+                    // 从另一个线程取消，模拟外部的关闭信号
+                    let token = token.clone();
+                    thread::spawn(move || token.cancel()).join().unwrap();
+                }
+                Ok(())
+            });
+        assert_eq!(result, Err(Error::Cancelled));
+        assert!(seen >= 100);
+
+        // 令牌已经取消时流水线一启动就结束
+        let result: Result<Vec<u64>, Error> = Pipeline::source(0u64..)
+            .cancel_on(&token)
+            .stage(1, Ok)
+            .collect();
+        assert_eq!(result, Err(Error::Cancelled));
+    }
+
+    #[test]
+    fn stress_deep_ordered_pipeline() {
+        const N: u64 = 20_000;
+        let mut sum = 0;
+        let mut expected = 0;
+        Pipeline::<_, ()>::source(0..N)
+            .capacity(8)
+            .stage(3, |x| Ok(x + 1))
+            .stage(5, |x| Ok(x * 2))
+            .stage(2, |x| Ok(x.to_string()))
+            .stage(4, |s| Ok(s.parse::<u64>().unwrap()))
+            .sink(|x| {
+                assert_eq!(x, (expected + 1) * 2);
+                expected += 1;
+                sum += x;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(expected, N);
+        assert_eq!(sum, N * (N + 1));
+    }
+}
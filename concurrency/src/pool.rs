@@ -0,0 +1,196 @@
+//! 固定大小的线程池
+//!
+//! 所有工作线程从同一个 [`crate::channel`] 无界通道里抢任务，谁空闲谁执行。
+//! 任务 panic 只会让该任务失败，工作线程会捕获 panic 后继续处理后续任务。
+//!
+//! 每个线程池带一个 [`CancellationToken`]：取消后排队中的任务直接丢弃，
+//! 正在执行的长任务可以通过 [`ThreadPool::cancellation_token`] 拿到令牌，自行检查并提前结束。
+
+use crate::channel::{self, Receiver, Sender};
+use crate::sync::CancellationToken;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    panicked: Arc<AtomicUsize>,
+    token: CancellationToken,
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> Self {
+        Self::named("pool-worker", threads)
+    }
+
+    /// 工作线程命名为 `{name}-{编号}`，方便在调试器和 panic 信息里辨认
+    pub fn named(name: &str, threads: usize) -> Self {
+        assert!(threads > 0, "线程池至少需要一个线程");
+        let (sender, receiver) = channel::unbounded::<Job>();
+        let panicked = Arc::new(AtomicUsize::new(0));
+        let token = CancellationToken::new();
+        let workers = (0..threads)
+            .map(|i| {
+                let receiver = receiver.clone();
+                let panicked = Arc::clone(&panicked);
+                let token = token.clone();
+                thread::Builder::new()
+                    .name(format!("{}-{}", name, i))
+                    .spawn(move || worker_loop(receiver, panicked, token))
+                    .expect("创建工作线程失败")
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            workers,
+            panicked,
+            token,
+        }
+    }
+
+    /// 提交一个任务，立即返回
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // 只要 self 还活着，接收端就不会全部断开
+        self.sender
+            .as_ref()
+            .expect("线程池已关闭")
+            .send(Box::new(job))
+            .expect("工作线程全部退出");
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// 还在排队、尚未被工作线程取走的任务数
+    pub fn queued(&self) -> usize {
+        self.sender.as_ref().map_or(0, Sender::len)
+    }
+
+    /// 执行时 panic 的任务数
+    pub fn panicked_jobs(&self) -> usize {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    /// 线程池的取消令牌，可以交给任务用来检查是否该提前结束，也可以从外部取消
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// 不再接收新任务，等已提交的任务全部执行完后返回。drop 时也会这样做
+    pub fn join(mut self) {
+        self.shutdown();
+    }
+
+    /// 取消令牌后等待工作线程退出：排队中的任务不再执行，
+    /// 正在执行的任务需要自己响应令牌，否则仍会执行到结束
+    pub fn cancel(mut self) {
+        self.token.cancel();
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // 关闭发送端后，工作线程取完剩余任务就会收到 RecvError 并退出
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_loop(receiver: Receiver<Job>, panicked: Arc<AtomicUsize>, token: CancellationToken) {
+    while let Ok(job) = receiver.recv() {
+        // 取消后继续取走剩余任务，但只丢弃不执行，好让 join 尽快返回
+        if token.is_cancelled() {
+            continue;
+        }
+        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+            panicked.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPool")
+            .field("threads", &self.workers.len())
+            .field("queued", &self.queued())
+            .field("panicked_jobs", &self.panicked_jobs())
+            .field("cancelled", &self.token.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_join_runs_every_queued_job() {
+        let pool = ThreadPool::new(4);
+        let sum = Arc::new(AtomicUsize::new(0));
+        for i in 1..=1000 {
+            let sum = Arc::clone(&sum);
+            pool.execute(move || {
+                sum.fetch_add(i, Ordering::Relaxed);
+            });
+        }
+        pool.join();
+        assert_eq!(sum.load(Ordering::Relaxed), 500_500);
+    }
+
+    #[test]
+    fn test_panicking_job_does_not_kill_worker() {
+        let pool = ThreadPool::named("test", 1);
+        let names = Arc::new(Mutex::new(Vec::new()));
+        pool.execute(|| panic!("任务失败"));
+        for _ in 0..3 {
+            let names = Arc::clone(&names);
+            pool.execute(move || {
+                let name = thread::current().name().map(String::from);
+                names.lock().unwrap().push(name);
+            });
+        }
+        drop(pool);
+        let names = names.lock().unwrap();
+        assert_eq!(names.len(), 3);
+        assert!(names.iter().all(|n| n.as_deref() == Some("test-0")));
+    }
+
+    #[test]
+    fn test_cancel_drops_queued_jobs_and_stops_running_ones() {
+        let pool = ThreadPool::new(1);
+        let token = pool.cancellation_token();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            // 长任务：直到线程池被取消才结束
+            token.wait();
+        });
+        let ran = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let ran = Arc::clone(&ran);
+            pool.execute(move || {
+                ran.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        started_rx.recv().unwrap();
+        pool.cancel();
+        assert_eq!(ran.load(Ordering::Relaxed), 0);
+    }
+}
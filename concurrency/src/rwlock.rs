@@ -0,0 +1,667 @@
+//! 可选择公平策略的读写锁
+//!
+//! 标准库的 `RwLock` 直接使用操作系统的实现，读者优先还是写者优先取决于平台。
+//! 这里把锁状态放在一个 Mutex 里，用两个 Condvar 分别挂起读者和写者（相当于用户态的"停车场"），
+//! 这样就能显式地选择策略：
+//!
+//! - [`Policy::ReaderPreferring`]：只要没有活跃写者，读者就可以进入。吞吐最高，但写者可能饿死
+//! - [`Policy::WriterPreferring`]：有写者在排队时，新来的读者必须等待。读者可能饿死
+//! - [`Policy::PhaseFair`]：读阶段与写阶段交替。写者释放时，所有已在等待的读者一起进入；
+//!   之后到达的读者要排在下一个写者之后。两边都不会饿死
+//!
+//! 另外支持可升级读锁（同一时刻最多一个，与普通读者共存）以及写锁降级。
+
+use crate::lock_order::{Access, LockTracker};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    ReaderPreferring,
+    #[default]
+    WriterPreferring,
+    PhaseFair,
+}
+
+#[derive(Default)]
+struct State {
+    // 活跃读者数（包括可升级读者）
+    readers: usize,
+    writer: bool,
+    // 是否有人持有可升级读锁
+    upgradable: bool,
+    // 可升级读者正在等待升级：此时禁止新读者进入，保证升级能完成
+    upgrading: bool,
+    waiting_writers: usize,
+    waiting_readers: usize,
+    // 以下两个字段只用于 PhaseFair：
+    // phase 每开启一次读阶段就加 1；admitted 是本阶段被放行、但还没醒来的读者数
+    phase: u64,
+    admitted: usize,
+}
+
+pub struct RwLock<T> {
+    state: Mutex<State>,
+    readers_cv: Condvar,
+    writers_cv: Condvar,
+    policy: Policy,
+    tracker: LockTracker,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: 与 std::sync::RwLock 相同：多个读者会同时拿到 &T，所以要求 T: Sync
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+// 守卫与 SpinLockGuard 一样不能被移到其他线程：锁顺序跟踪在释放时弹出的是
+// 当前线程的持有栈，换了线程释放会在两边都留下错误的记录
+const _: () = {
+    use crate::assert::assert_sync;
+    use std::cell::Cell;
+
+    assert_sync::<RwLockReadGuard<'static, i32>>();
+    assert_sync::<RwLockUpgradableReadGuard<'static, i32>>();
+    assert_sync::<RwLockWriteGuard<'static, i32>>();
+    crate::assert_not_impl!(RwLockReadGuard<'static, i32>: Send);
+    crate::assert_not_impl!(RwLockUpgradableReadGuard<'static, i32>: Send);
+    crate::assert_not_impl!(RwLockWriteGuard<'static, i32>: Send);
+    crate::assert_not_impl!(RwLockWriteGuard<'static, Cell<i32>>: Sync);
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReadKind {
+    Shared,
+    Upgradable,
+}
+
+impl<T> RwLock<T> {
+    /// 默认使用写者优先策略
+    pub fn new(data: T) -> Self {
+        Self::with_policy(data, Policy::default())
+    }
+
+    pub fn with_policy(data: T, policy: Policy) -> Self {
+        let mut tracker = LockTracker::new();
+        // read_allowed 在有写者排队时拒绝新读者：持有读锁的线程再次 read 会被自己挡住
+        if policy != Policy::ReaderPreferring {
+            tracker.set_readers_wait_for_writers();
+        }
+        Self {
+            state: Mutex::new(State::default()),
+            readers_cv: Condvar::new(),
+            writers_cv: Condvar::new(),
+            policy,
+            tracker,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 命名后，`lock-order-debug` 的报告里会用这个名字指代这把锁
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.tracker.set_name(name);
+        self
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// 不考虑 PhaseFair 的"放行"时，策略是否允许一个新读者进入
+    fn read_allowed(&self, st: &State, kind: ReadKind) -> bool {
+        if st.writer || st.upgrading {
+            return false;
+        }
+        if kind == ReadKind::Upgradable && st.upgradable {
+            return false;
+        }
+        match self.policy {
+            Policy::ReaderPreferring => true,
+            Policy::WriterPreferring | Policy::PhaseFair => st.waiting_writers == 0,
+        }
+    }
+
+    fn acquire_read(&self, kind: ReadKind) {
+        let mut st = self.lock_state();
+        let arrival_phase = st.phase;
+        let mut waiting = false;
+        loop {
+            // PhaseFair：在等待期间开启了新的读阶段，即使有写者在排队也可以进入
+            let admitted = waiting && self.policy == Policy::PhaseFair && arrival_phase < st.phase;
+            let blocked_anyway =
+                st.writer || st.upgrading || (kind == ReadKind::Upgradable && st.upgradable);
+            if admitted && !blocked_anyway {
+                st.admitted -= 1;
+                break;
+            }
+            if !admitted && self.read_allowed(&st, kind) {
+                break;
+            }
+            if !waiting {
+                waiting = true;
+                st.waiting_readers += 1;
+            }
+            st = self.readers_cv.wait(st).unwrap();
+        }
+        if waiting {
+            st.waiting_readers -= 1;
+        }
+        st.readers += 1;
+        if kind == ReadKind::Upgradable {
+            st.upgradable = true;
+        }
+    }
+
+    fn try_acquire_read(&self, kind: ReadKind) -> bool {
+        let mut st = self.lock_state();
+        // PhaseFair 下，已放行的读者还没进来时也不插队
+        if !self.read_allowed(&st, kind) || st.admitted > 0 {
+            return false;
+        }
+        st.readers += 1;
+        if kind == ReadKind::Upgradable {
+            st.upgradable = true;
+        }
+        true
+    }
+
+    fn write_allowed(st: &State) -> bool {
+        !st.writer && st.readers == 0 && st.admitted == 0
+    }
+
+    /// 写者离开（释放或降级）时，PhaseFair 为所有正在等待的读者开启一个读阶段
+    fn open_read_phase(&self, st: &mut State) {
+        if self.policy == Policy::PhaseFair && st.waiting_readers > 0 {
+            st.phase += 1;
+            st.admitted = st.waiting_readers;
+        }
+        self.readers_cv.notify_all();
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let site = Location::caller();
+        self.tracker.before_wait(Access::Shared, site);
+        self.acquire_read(ReadKind::Shared);
+        self.tracker.acquired(Access::Shared, site);
+        RwLockReadGuard::new(self)
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let site = Location::caller();
+        self.try_acquire_read(ReadKind::Shared).then(|| {
+            self.tracker.acquired(Access::Shared, site);
+            RwLockReadGuard::new(self)
+        })
+    }
+
+    /// 可升级读锁：与普通读者共存，但同一时刻只能有一个，之后可以原子地升级为写锁
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        let site = Location::caller();
+        self.tracker.before_wait(Access::Upgradable, site);
+        self.acquire_read(ReadKind::Upgradable);
+        self.tracker.acquired(Access::Upgradable, site);
+        RwLockUpgradableReadGuard::new(self)
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        let site = Location::caller();
+        self.try_acquire_read(ReadKind::Upgradable).then(|| {
+            self.tracker.acquired(Access::Upgradable, site);
+            RwLockUpgradableReadGuard::new(self)
+        })
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let site = Location::caller();
+        self.tracker.before_wait(Access::Exclusive, site);
+        let mut st = self.lock_state();
+        st.waiting_writers += 1;
+        while !Self::write_allowed(&st) {
+            st = self.writers_cv.wait(st).unwrap();
+        }
+        st.waiting_writers -= 1;
+        st.writer = true;
+        self.tracker.acquired(Access::Exclusive, site);
+        RwLockWriteGuard::new(self)
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let site = Location::caller();
+        let mut st = self.lock_state();
+        if !Self::write_allowed(&st) {
+            return None;
+        }
+        st.writer = true;
+        self.tracker.acquired(Access::Exclusive, site);
+        Some(RwLockWriteGuard::new(self))
+    }
+
+    fn release_read(&self, kind: ReadKind) {
+        self.tracker.released();
+        let mut st = self.lock_state();
+        st.readers -= 1;
+        if kind == ReadKind::Upgradable {
+            st.upgradable = false;
+            // 可能有其他可升级读者在等
+            self.readers_cv.notify_all();
+        }
+        // readers 降到 1 时，可能有可升级读者在等待升级
+        if st.readers <= 1 {
+            self.writers_cv.notify_all();
+        }
+    }
+
+    fn release_write(&self) {
+        self.tracker.released();
+        let mut st = self.lock_state();
+        st.writer = false;
+        self.open_read_phase(&mut st);
+        self.writers_cv.notify_one();
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        d.field("policy", &self.policy);
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    // 裸指针让守卫不是 Send：必须在加锁的线程上解锁
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T> RwLockReadGuard<'a, T> {
+    fn new(lock: &'a RwLock<T>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+// SAFETY: 共享守卫只能拿到 &T，与 std 的读写锁守卫一样要求 T: Sync
+unsafe impl<T: Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: 持有读锁期间没有写者
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_read(ReadKind::Shared);
+    }
+}
+
+pub struct RwLockUpgradableReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    // 同 RwLockReadGuard
+    _not_send: PhantomData<*const ()>,
+}
+
+// SAFETY: 同 RwLockReadGuard
+unsafe impl<T: Sync> Sync for RwLockUpgradableReadGuard<'_, T> {}
+
+impl<'a, T> RwLockUpgradableReadGuard<'a, T> {
+    fn new(lock: &'a RwLock<T>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// 等待其他读者离开后升级为写锁。升级期间新读者不能进入，因此一定能完成
+    pub fn upgrade(self) -> RwLockWriteGuard<'a, T> {
+        let lock = ManuallyDrop::new(self).lock;
+        let mut st = lock.lock_state();
+        st.upgrading = true;
+        while st.readers > 1 {
+            st = lock.writers_cv.wait(st).unwrap();
+        }
+        st.upgrading = false;
+        st.readers -= 1;
+        st.upgradable = false;
+        st.writer = true;
+        lock.tracker.changed(Access::Exclusive);
+        RwLockWriteGuard::new(lock)
+    }
+
+    /// 只有自己一个读者时才升级，否则原样返回
+    pub fn try_upgrade(self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        let mut st = self.lock.lock_state();
+        if st.readers != 1 {
+            drop(st);
+            return Err(self);
+        }
+        st.readers = 0;
+        st.upgradable = false;
+        st.writer = true;
+        drop(st);
+        let lock = ManuallyDrop::new(self).lock;
+        lock.tracker.changed(Access::Exclusive);
+        Ok(RwLockWriteGuard::new(lock))
+    }
+
+    /// 放弃升级的权利，变成普通读锁
+    pub fn downgrade(self) -> RwLockReadGuard<'a, T> {
+        let lock = ManuallyDrop::new(self).lock;
+        lock.lock_state().upgradable = false;
+        lock.readers_cv.notify_all();
+        lock.tracker.changed(Access::Shared);
+        RwLockReadGuard::new(lock)
+    }
+}
+
+impl<T> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_read(ReadKind::Upgradable);
+    }
+}
+
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    // 同 RwLockReadGuard
+    _not_send: PhantomData<*const ()>,
+}
+
+// SAFETY: 同 RwLockReadGuard
+unsafe impl<T: Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<'a, T> RwLockWriteGuard<'a, T> {
+    fn new(lock: &'a RwLock<T>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// 原子地降级为读锁：中间不会有其他写者插进来
+    pub fn downgrade(self) -> RwLockReadGuard<'a, T> {
+        let lock = ManuallyDrop::new(self).lock;
+        let mut st = lock.lock_state();
+        st.writer = false;
+        st.readers += 1;
+        lock.open_read_phase(&mut st);
+        lock.tracker.changed(Access::Shared);
+        RwLockReadGuard::new(lock)
+    }
+
+    /// 降级为可升级读锁
+    pub fn downgrade_to_upgradable(self) -> RwLockUpgradableReadGuard<'a, T> {
+        let lock = ManuallyDrop::new(self).lock;
+        let mut st = lock.lock_state();
+        st.writer = false;
+        st.readers += 1;
+        st.upgradable = true;
+        lock.open_read_phase(&mut st);
+        lock.tracker.changed(Access::Upgradable);
+        RwLockUpgradableReadGuard::new(lock)
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: 持有写锁，独占访问
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_write();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    const POLICIES: [Policy; 3] = [
+        Policy::ReaderPreferring,
+        Policy::WriterPreferring,
+        Policy::PhaseFair,
+    ];
+
+    #[test]
+    fn test_shared_and_exclusive() {
+        for policy in POLICIES {
+            let lock = RwLock::with_policy(1, policy);
+            {
+                let r1 = lock.read();
+                // 同一线程阻塞式重入在读者优先以外的策略下会被 lock-order-debug 当成自锁
+                let r2 = lock.try_read().unwrap();
+                assert_eq!(*r1 + *r2, 2);
+                assert!(lock.try_write().is_none());
+            }
+            {
+                let mut w = lock.write();
+                *w = 5;
+                assert!(lock.try_read().is_none());
+                assert!(lock.try_write().is_none());
+            }
+            assert_eq!(lock.into_inner(), 5);
+        }
+    }
+
+    #[test]
+    fn test_upgradable() {
+        for policy in POLICIES {
+            let lock = RwLock::with_policy(vec![1], policy);
+            let up = lock.upgradable_read();
+            // 可升级读锁与普通读锁共存，但与另一个可升级读锁互斥
+            let r = lock.try_read().unwrap();
+            assert!(lock.try_upgradable_read().is_none());
+            let up = up.try_upgrade().unwrap_err();
+            drop(r);
+            let mut w = up.upgrade();
+            w.push(2);
+            let r = w.downgrade();
+            assert_eq!(*r, vec![1, 2]);
+            assert!(lock.try_read().is_some());
+            drop(r);
+
+            let w = lock.write();
+            let up = w.downgrade_to_upgradable();
+            assert!(lock.try_write().is_none());
+            let r = up.downgrade();
+            assert!(lock.try_upgradable_read().is_some());
+            drop(r);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_waits_for_readers() {
+        let lock = Arc::new(RwLock::new(0));
+        let (tx, rx) = mpsc::channel();
+        let reader = lock.read();
+
+        let l2 = Arc::clone(&lock);
+        let upgrader = thread::spawn(move || {
+            let up = l2.upgradable_read();
+            tx.send(()).unwrap();
+            let mut w = up.upgrade();
+            *w += 1;
+        });
+        rx.recv().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        // 升级进行中：新读者必须等待
+        assert!(lock.try_read().is_none());
+        assert_eq!(*reader, 0);
+        drop(reader);
+        upgrader.join().unwrap();
+        assert_eq!(*lock.read(), 1);
+    }
+
+    /// 让一个写者在读者持锁时排队，然后观察新读者能否插队
+    fn new_reader_can_overtake_waiting_writer(policy: Policy) -> bool {
+        let lock = Arc::new(RwLock::with_policy(0, policy));
+        let reader = lock.read();
+
+        let l2 = Arc::clone(&lock);
+        let writer = thread::spawn(move || {
+            *l2.write() += 1;
+        });
+        // 等待写者进入排队
+        while lock.lock_state().waiting_writers == 0 {
+            thread::yield_now();
+        }
+        let overtook = lock.try_read().is_some();
+        drop(reader);
+        writer.join().unwrap();
+        overtook
+    }
+
+    #[test]
+    fn test_policy_reader_vs_writer_preference() {
+        assert!(new_reader_can_overtake_waiting_writer(
+            Policy::ReaderPreferring
+        ));
+        assert!(!new_reader_can_overtake_waiting_writer(
+            Policy::WriterPreferring
+        ));
+        assert!(!new_reader_can_overtake_waiting_writer(Policy::PhaseFair));
+    }
+
+    #[test]
+    fn test_phase_fair_readers_go_before_next_writer() {
+        let lock = Arc::new(RwLock::with_policy(Vec::new(), Policy::PhaseFair));
+        let w1 = lock.write();
+
+        // 写者持锁期间，先到一个读者，再到一个写者
+        let l = Arc::clone(&lock);
+        let reader = thread::spawn(move || l.read().len());
+        while lock.lock_state().waiting_readers == 0 {
+            thread::yield_now();
+        }
+        let l = Arc::clone(&lock);
+        let writer = thread::spawn(move || l.write().push("w2"));
+        while lock.lock_state().waiting_writers == 0 {
+            thread::yield_now();
+        }
+
+        drop(w1);
+        // 阶段公平：读者在 w1 之后、w2 之前进入，所以看不到 w2 写入的数据
+        assert_eq!(reader.join().unwrap(), 0);
+        writer.join().unwrap();
+        assert_eq!(lock.read().len(), 1);
+    }
+
+    #[test]
+    fn stress_mixed_workload_all_policies() {
+        for policy in POLICIES {
+            let lock = Arc::new(RwLock::with_policy((0usize, 0usize), policy));
+            let in_write = Arc::new(AtomicBool::new(false));
+
+            spawn_workers(
+                (Arc::clone(&lock), Arc::clone(&in_write)),
+                8,
+                |(lock, in_write), i| {
+                    for n in 0..500 {
+                        match (i + n) % 4 {
+                            0 => {
+                                let mut w = lock.write();
+                                assert!(!in_write.swap(true, Ordering::SeqCst));
+                                // 两个字段始终保持相等：读者绝不能看到中间状态
+                                w.0 += 1;
+                                w.1 += 1;
+                                in_write.store(false, Ordering::SeqCst);
+                            }
+                            1 => {
+                                let up = lock.upgradable_read();
+                                let mut w = up.upgrade();
+                                w.0 += 1;
+                                w.1 += 1;
+                                let r = w.downgrade();
+                                assert_eq!(r.0, r.1);
+                            }
+                            _ => {
+                                let r = lock.read();
+                                assert!(!in_write.load(Ordering::SeqCst));
+                                assert_eq!(r.0, r.1);
+                            }
+                        }
+                    }
+                },
+            );
+
+            let (a, b) = *lock.read();
+            assert_eq!((a, b), (8 * 500 / 2, 8 * 500 / 2));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! 顺序锁 (SeqLock)：读者不加锁、不写共享内存的乐观读
+//!
+//! 写者在修改前后各把序号加一：序号为奇数表示正在写。
+//! 读者先记下序号，复制数据，再检查序号有没有变化——没变说明复制到的是完整的一份，
+//! 变了（或一开始就是奇数）就重试。读者之间完全没有缓存行争用，
+//! 适合"读极多、写极少、数据很小"的场景，例如配置、坐标、统计快照。
+//!
+//! 代价是读者可能读到写了一半的数据再丢弃，所以只支持 `T: Copy`：
+//! 复制不会调用任何用户代码，丢弃也不需要析构。
+//!
+//! 注意：与 C/C++ 里常见的 seqlock 一样，读者与写者对数据的并发访问在 Rust 内存模型下
+//! 严格来说属于数据竞争。这里用 volatile 读写阻止编译器基于"无竞争"假设做优化，
+//! 并且先把字节读进 `MaybeUninit`，序号校验通过后才当作 `T` 使用，
+//! 避免撕裂的值（比如非法的 bool）被当成合法值。
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+
+/// 读者连续失败多少次之后让出 CPU：写者可能正被抢占
+const SPINS_BEFORE_YIELD: u32 = 64;
+
+pub struct SeqLock<T> {
+    seq: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: 写者之间通过序号上的 CAS 互斥，读者只复制数据、不产生引用。
+// T 的值会被复制到其他线程，因此需要 T: Send
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+const _: () = {
+    use crate::assert::assert_sync;
+
+    assert_sync::<SeqLock<(u64, u64)>>();
+    crate::assert_not_impl!(SeqLock<*const u8>: Sync);
+};
+
+impl<T: Copy> SeqLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// 读取一份完整的数据，与写者冲突时自动重试
+    pub fn read(&self) -> T {
+        let mut spins = 0;
+        loop {
+            if let Some(value) = self.try_read() {
+                return value;
+            }
+            if spins < SPINS_BEFORE_YIELD {
+                spins += 1;
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    /// 只尝试一次乐观读；正好碰上写者时返回 None
+    pub fn try_read(&self) -> Option<T> {
+        // Acquire：看到偶数序号 s1，就能看到产生 s1 的那次写入的全部数据
+        let s1 = self.seq.load(Ordering::Acquire);
+        if s1 & 1 == 1 {
+            return None;
+        }
+        // SAFETY: 指针有效且对齐；读到的字节在校验前不会被当作 T 使用
+        let value = unsafe { ptr::read_volatile(self.data.get() as *const MaybeUninit<T>) };
+        // 这道屏障保证上面的读取不会被重排到第二次读序号之后
+        fence(Ordering::Acquire);
+        let s2 = self.seq.load(Ordering::Relaxed);
+        // SAFETY: 序号前后一致且为偶数，期间没有写者，value 是某次写入后的完整数据
+        (s1 == s2).then(|| unsafe { value.assume_init() })
+    }
+
+    pub fn write(&self, value: T) {
+        self.update(|data| {
+            // SAFETY: update 保证当前只有本线程在写
+            unsafe { ptr::write_volatile(data, value) }
+        });
+    }
+
+    /// 在写者互斥下原地修改，返回闭包的返回值
+    ///
+    /// 闭包运行期间读者会不断重试，应当尽量短小
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let guard = self.lock_writer();
+        // SAFETY: 序号为奇数期间只有持有 guard 的线程能写
+        f(unsafe { &mut *guard.lock.data.get() })
+    }
+
+    fn lock_writer(&self) -> WriteGuard<'_, T> {
+        let mut spins = 0;
+        loop {
+            let seq = self.seq.load(Ordering::Relaxed);
+            if seq & 1 == 0
+                && self
+                    .seq
+                    .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                // 序号先变成奇数，再写数据：与读者那边的 Acquire 屏障配对
+                fence(Ordering::Release);
+                return WriteGuard { lock: self, seq };
+            }
+            if spins < SPINS_BEFORE_YIELD {
+                spins += 1;
+                std::hint::spin_loop();
+            } else {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+/// 写完（或闭包 panic）时把序号恢复成偶数，否则读者会永远重试下去
+struct WriteGuard<'a, T> {
+    lock: &'a SeqLock<T>,
+    seq: usize,
+}
+
+impl<T> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock
+            .seq
+            .store(self.seq.wrapping_add(2), Ordering::Release);
+    }
+}
+
+impl<T: Copy + Default> Default for SeqLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SeqLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqLock")
+            .field("data", &self.read())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_read_write_update() {
+        let lock = SeqLock::new((1u32, 2u32));
+        assert_eq!(lock.read(), (1, 2));
+        lock.write((3, 4));
+        let sum = lock.update(|(a, b)| {
+            *a += 1;
+            *a + *b
+        });
+        assert_eq!(sum, 8);
+        assert_eq!(lock.try_read(), Some((4, 4)));
+        assert_eq!(lock.into_inner(), (4, 4));
+    }
+
+    #[test]
+    fn test_panicking_writer_releases_readers() {
+        let lock = SeqLock::new(0u64);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            lock.update(|v| {
+                *v = 1;
+                panic!("写到一半");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(lock.read(), 1);
+    }
+
+    #[test]
+    fn stress_readers_never_see_torn_values() {
+        // 四个字段总是一起更新，读者看到不一致就说明读到了写了一半的数据
+        let shared = Arc::new((SeqLock::new([0u64; 4]), AtomicBool::new(false)));
+        let writers: Vec<_> = (0..2u64)
+            .map(|w| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    for i in 0..20_000u64 {
+                        let v = i * 2 + w;
+                        shared.0.write([v, v, v, v]);
+                    }
+                })
+            })
+            .collect();
+
+        let readers = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                spawn_workers(shared, 4, |shared, _| {
+                    let (lock, done) = &*shared;
+                    while !done.load(Ordering::Relaxed) {
+                        let [a, b, c, d] = lock.read();
+                        assert!(a == b && b == c && c == d, "撕裂读: {:?}", [a, b, c, d]);
+                    }
+                })
+            })
+        };
+        for w in writers {
+            w.join().unwrap();
+        }
+        shared.1.store(true, Ordering::Relaxed);
+        readers.join().unwrap();
+
+        let last = shared.0.read();
+        assert!(last[0] >= 39_998);
+    }
+}
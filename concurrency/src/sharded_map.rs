@@ -0,0 +1,467 @@
+//! 分片并发 HashMap
+//!
+//! 用一把全局 `RwLock<HashMap>` 保护整个表时，任何一次写入都会挡住所有读者。
+//! 这里把键按哈希值分散到 N 个分片，每个分片各有一把 `RwLock`：
+//! 访问不同分片的线程互不干扰，只有落在同一分片上的写操作才会互相等待。
+//!
+//! 分片序号取哈希值的**高位**。分片内部的 HashMap 用同一个哈希器、按低位选桶，
+//! 如果分片也用低位，同一分片里的键低位全都相同，桶的分布会变差。
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, HashMap, RandomState};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+
+type Shard<K, V, S> = RwLock<HashMap<K, V, S>>;
+
+pub struct ShardedMap<K, V, S = RandomState> {
+    shards: Box<[Shard<K, V, S>]>,
+    // log2(分片数)
+    shift: u32,
+    hasher: S,
+}
+
+/// 默认分片数：CPU 核数的 4 倍，向上取整到 2 的幂
+fn default_shard_count() -> usize {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    (cpus * 4).next_power_of_two()
+}
+
+impl<K: Eq + Hash, V> ShardedMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_shards(default_shard_count())
+    }
+
+    /// 分片数会向上取整到 2 的幂
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V> Default for ShardedMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> ShardedMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        assert!(shards > 0, "分片数必须大于 0");
+        let shards = shards.next_power_of_two();
+        Self {
+            shards: (0..shards)
+                .map(|_| RwLock::new(HashMap::with_hasher(hasher.clone())))
+                .collect(),
+            shift: shards.trailing_zeros(),
+            hasher,
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard_for<Q>(&self, key: &Q) -> &Shard<K, V, S>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let idx = match self.shift {
+            0 => 0,
+            shift => (self.hasher.hash_one(key) >> (u64::BITS - shift)) as usize,
+        };
+        &self.shards[idx]
+    }
+
+    fn read_shard<Q>(&self, key: &Q) -> RwLockReadGuard<'_, HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        self.shard_for(key).read().unwrap()
+    }
+
+    fn write_shard<Q>(&self, key: &Q) -> RwLockWriteGuard<'_, HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        self.shard_for(key).write().unwrap()
+    }
+
+    /// 返回值的克隆。不能返回引用：引用离开函数时分片锁已经释放
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.read_shard(key).get(key).cloned()
+    }
+
+    /// 在持有分片读锁期间访问值，适合 V 不便克隆或只需要其中一部分的情况
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read_shard(key).get(key).map(f)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read_shard(key).contains_key(key)
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write_shard(&key).insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.write_shard(key).remove(key)
+    }
+
+    /// 获取键所在分片的写锁，在锁内完成"查找-插入/修改"，中间不会被其他线程插入同一个键
+    pub fn entry(&self, key: K) -> Entry<'_, K, V, S> {
+        let guard = self.write_shard(&key);
+        if guard.contains_key(&key) {
+            Entry::Occupied(OccupiedEntry { guard, key })
+        } else {
+            Entry::Vacant(VacantEntry { guard, key })
+        }
+    }
+
+    /// 元素个数的提示值：逐个分片读取长度，并发修改时结果可能已经过时
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.read().unwrap().is_empty())
+    }
+
+    /// 一致性快照：按固定顺序同时持有所有分片的读锁再复制。
+    /// 单个操作只会锁一个分片，所以按顺序加锁不会死锁
+    pub fn snapshot(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let guards: Vec<_> = self.shards.iter().map(|s| s.read().unwrap()).collect();
+        let mut items = Vec::with_capacity(guards.iter().map(|g| g.len()).sum());
+        for guard in &guards {
+            items.extend(guard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        items
+    }
+
+    /// 遍历 [`snapshot`](Self::snapshot)，遍历期间不持有任何锁
+    pub fn iter(&self) -> std::vec::IntoIter<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.snapshot().into_iter()
+    }
+
+    /// 逐个分片执行 retain，每次只锁一个分片
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in self.shards.iter() {
+            shard.write().unwrap().retain(&mut f);
+        }
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().unwrap().clear();
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for ShardedMap<K, V, S>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guards: Vec<_> = self.shards.iter().map(|s| s.read().unwrap()).collect();
+        f.debug_map()
+            .entries(guards.iter().flat_map(|g| g.iter()))
+            .finish()
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for ShardedMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let map = Self::with_shards_and_hasher(default_shard_count(), S::default());
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<K, V, S> IntoIterator for ShardedMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<hash_map::IntoIter<K, V>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let shards: Vec<_> = self
+            .shards
+            .into_vec()
+            .into_iter()
+            .map(|s| s.into_inner().unwrap().into_iter())
+            .collect();
+        shards.into_iter().flatten()
+    }
+}
+
+pub enum Entry<'a, K, V, S> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> Entry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> RefMut<'a, K, V, S> {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> RefMut<'a, K, V, S> {
+        match self {
+            Entry::Occupied(e) => e.into_ref(),
+            Entry::Vacant(e) => e.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> RefMut<'a, K, V, S>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+pub struct OccupiedEntry<'a, K, V, S> {
+    guard: RwLockWriteGuard<'a, HashMap<K, V, S>>,
+    key: K,
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &V {
+        // 构造 OccupiedEntry 时已确认键存在，且一直持有写锁
+        self.guard.get(&self.key).unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.guard.get_mut(&self.key).unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(mut self) -> V {
+        self.guard.remove(&self.key).unwrap()
+    }
+
+    pub fn into_ref(mut self) -> RefMut<'a, K, V, S> {
+        let value: *mut V = self.get_mut();
+        RefMut {
+            _guard: self.guard,
+            value,
+        }
+    }
+}
+
+pub struct VacantEntry<'a, K, V, S> {
+    guard: RwLockWriteGuard<'a, HashMap<K, V, S>>,
+    key: K,
+}
+
+impl<'a, K: Eq + Hash, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(mut self, value: V) -> RefMut<'a, K, V, S> {
+        let value: *mut V = match self.guard.entry(self.key) {
+            hash_map::Entry::Vacant(e) => e.insert(value),
+            hash_map::Entry::Occupied(_) => unreachable!("持有写锁期间键不会被插入"),
+        };
+        RefMut {
+            _guard: self.guard,
+            value,
+        }
+    }
+}
+
+/// 持有分片写锁的可变引用
+pub struct RefMut<'a, K, V, S> {
+    _guard: RwLockWriteGuard<'a, HashMap<K, V, S>>,
+    value: *mut V,
+}
+
+impl<K, V, S> Deref for RefMut<'_, K, V, S> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        // SAFETY: value 指向 guard 所保护的 HashMap 中的元素。
+        // RefMut 独占这把写锁，期间 HashMap 不会被修改，元素地址保持不变
+        unsafe { &*self.value }
+    }
+}
+
+impl<K, V, S> DerefMut for RefMut<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { &mut *self.value }
+    }
+}
+
+impl<K, V: fmt::Debug, S> fmt::Debug for RefMut<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_basic_operations() {
+        let map = ShardedMap::with_shards(3);
+        assert_eq!(map.shard_count(), 4);
+        assert!(map.is_empty());
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("a".to_string(), 2), Some(1));
+        map.insert("b".to_string(), 3);
+        // 可以用 &str 查询 String 键
+        assert_eq!(map.get("a"), Some(2));
+        assert_eq!(map.get_with("b", |v| v * 10), Some(30));
+        assert!(map.contains_key("b"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove("a"), Some(2));
+        assert_eq!(map.get("a"), None);
+
+        map.retain(|_, v| *v > 5);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_entry_api() {
+        let map: ShardedMap<&str, Vec<i32>> = ShardedMap::new();
+        map.entry("x").or_default().push(1);
+        map.entry("x").or_default().push(2);
+        *map.entry("y")
+            .or_insert_with(|| vec![0])
+            .first_mut()
+            .unwrap() += 7;
+        map.entry("y").and_modify(|v| v.push(8)).or_default();
+        assert_eq!(map.get("x"), Some(vec![1, 2]));
+        assert_eq!(map.get("y"), Some(vec![7, 8]));
+
+        match map.entry("x") {
+            Entry::Occupied(mut e) => {
+                assert_eq!(e.insert(vec![]), vec![1, 2]);
+                assert!(e.remove().is_empty());
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        match map.entry("z") {
+            Entry::Vacant(e) => assert_eq!(e.into_key(), "z"),
+            Entry::Occupied(_) => unreachable!(),
+        }
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_and_into_iter() {
+        let map: ShardedMap<i32, i32> = (0..100).map(|i| (i, i * i)).collect();
+        let mut snap = map.snapshot();
+        snap.sort();
+        assert_eq!(snap.len(), 100);
+        assert_eq!(snap[9], (9, 81));
+        // 快照不受之后修改的影响
+        map.clear();
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(snap.len(), 100);
+
+        map.insert(1, 1);
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_keys_spread_across_shards() {
+        let map = ShardedMap::with_shards(8);
+        for i in 0..1000 {
+            map.insert(i, ());
+        }
+        for shard in map.shards.iter() {
+            let len = shard.read().unwrap().len();
+            assert!(len > 50, "分片分布过于不均: {}", len);
+        }
+    }
+
+    #[test]
+    fn stress_concurrent_entry_counts() {
+        // 多个线程对同一组键做 entry 累加：若 entry 不在锁内完成，计数会丢失
+        let map = Arc::new(ShardedMap::with_shards(4));
+        spawn_workers(Arc::clone(&map), 8, |map, i| {
+            for n in 0..2_000 {
+                *map.entry(n % 64).or_insert(0usize) += 1;
+                if n % 7 == 0 {
+                    map.insert(1000 + i * 10_000 + n, n);
+                    map.remove(&(1000 + i * 10_000 + n));
+                }
+                if n % 500 == 0 {
+                    let snap = map.snapshot();
+                    assert!(snap.len() <= 64 + 8);
+                }
+            }
+        });
+        assert_eq!(map.len(), 64);
+        let total: usize = map.iter().map(|(_, v)| v).sum();
+        assert_eq!(total, 8 * 2_000);
+    }
+}
@@ -0,0 +1,233 @@
+//! RCU 风格的快照单元（类似 arc-swap 的 `ArcSwap`）
+//!
+//! 单元里保存一个 `Arc<T>`。读者 [`load`](SnapshotCell::load) 拿到当前版本的 `Arc` 后
+//! 就与单元再无关系，写者随时可以发布新版本，旧版本等最后一个持有者放手后自然释放。
+//! 读路径不加锁：只需要 [`crate::epoch::pin`] 一下，读到指针后把引用计数加一。
+//!
+//! 写者不在原地修改，而是"复制-修改-发布"（Read-Copy-Update）：
+//! [`rcu`](SnapshotCell::rcu) 基于当前版本算出新版本，再用 CAS 发布；
+//! 期间如果被别的写者抢先，就基于最新版本重算。
+//!
+//! 实现上单元里存的是指向 `Arc<T>` 的指针（`Box<Arc<T>>`），
+//! 被替换下来的盒子交给纪元回收延迟释放，保证仍在读它的线程不会访问到已释放的内存。
+//! 释放可能发生在任意时刻、任意线程上，届时 `T` 借用的数据可能早已失效，
+//! 所以替换版本的方法（[`store`](SnapshotCell::store)、[`swap`](SnapshotCell::swap)、
+//! [`rcu`](SnapshotCell::rcu)）都要求 `T: 'static`。
+
+use crate::epoch;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Arc;
+
+pub struct SnapshotCell<T> {
+    ptr: AtomicPtr<Arc<T>>,
+    // AtomicPtr 对任何 T 都是 Send + Sync 的；这里让自动推导与 Arc<T> 保持一致：
+    // 只有 T: Send + Sync 时，单元才能被多个线程共享
+    _marker: PhantomData<Arc<T>>,
+}
+
+const _: () = {
+    use crate::assert::{assert_send, assert_sync};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    assert_send::<SnapshotCell<Vec<u8>>>();
+    assert_sync::<SnapshotCell<Vec<u8>>>();
+    crate::assert_not_impl!(SnapshotCell<Cell<i32>>: Sync);
+    crate::assert_not_impl!(SnapshotCell<Rc<i32>>: Send);
+};
+
+impl<T> SnapshotCell<T> {
+    pub fn new(value: T) -> Self {
+        Self::from_arc(Arc::new(value))
+    }
+
+    pub fn from_arc(value: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 取得当前版本的快照
+    pub fn load(&self) -> Arc<T> {
+        let _guard = epoch::pin();
+        // SAFETY: pin 期间读到的盒子不会被释放（替换者会 defer_destroy）
+        unsafe { Arc::clone(&*self.ptr.load(Ordering::Acquire)) }
+    }
+
+    /// 不增加引用计数，直接在当前版本上执行 `f`
+    ///
+    /// 比 `load` 少一次对共享计数器的原子写，读者很多时争用更小；
+    /// 代价是 `f` 执行期间本线程一直处于 pin 状态，`f` 应当尽量短
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let _guard = epoch::pin();
+        // SAFETY: 同 load
+        f(unsafe { &**self.ptr.load(Ordering::Acquire) })
+    }
+
+    pub fn store(&self, value: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.swap(Arc::new(value));
+    }
+
+    /// 发布新版本，返回被替换下来的旧版本
+    pub fn swap(&self, value: Arc<T>) -> Arc<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let new = Box::into_raw(Box::new(value));
+        let guard = epoch::pin();
+        let old = self.ptr.swap(new, Ordering::AcqRel);
+        // SAFETY: old 已从单元中摘下，之后 pin 的线程读不到它；先克隆出返回值再延迟释放盒子
+        unsafe {
+            let previous = Arc::clone(&*old);
+            guard.defer_destroy(old);
+            previous
+        }
+    }
+
+    /// 基于当前版本计算新版本并发布，返回被替换的旧版本
+    ///
+    /// 与其他写者冲突时 `f` 会以最新版本为参数被再次调用，所以 `f` 不应有副作用
+    pub fn rcu<F>(&self, mut f: F) -> Arc<T>
+    where
+        F: FnMut(&T) -> T,
+        T: Send + Sync + 'static,
+    {
+        let guard = epoch::pin();
+        let mut current = self.ptr.load(Ordering::Acquire);
+        loop {
+            // SAFETY: 整个循环都处于 pin 状态，current 指向的盒子不会被释放，
+            // 因此也不会出现地址被复用导致的 ABA
+            let next = Arc::new(f(unsafe { &**current }));
+            let new = Box::into_raw(Box::new(next));
+            match self
+                .ptr
+                .compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(old) => unsafe {
+                    let previous = Arc::clone(&*old);
+                    guard.defer_destroy(old);
+                    return previous;
+                },
+                Err(actual) => {
+                    // SAFETY: new 从未发布过，直接释放
+                    drop(unsafe { Box::from_raw(new) });
+                    current = actual;
+                }
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> Arc<T> {
+        let ptr = self.ptr.load(Ordering::Relaxed);
+        std::mem::forget(self);
+        // SAFETY: 独占 self，不存在其他读者
+        *unsafe { Box::from_raw(ptr) }
+    }
+}
+
+impl<T> Drop for SnapshotCell<T> {
+    fn drop(&mut self) {
+        // SAFETY: &mut self 保证没有并发读者；之前替换下来的盒子由纪元回收负责
+        drop(unsafe { Box::from_raw(*self.ptr.get_mut()) });
+    }
+}
+
+impl<T: Default> Default for SnapshotCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<Arc<T>> for SnapshotCell<T> {
+    fn from(value: Arc<T>) -> Self {
+        Self::from_arc(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SnapshotCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.read(|value| f.debug_tuple("SnapshotCell").field(value).finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    #[test]
+    fn test_snapshots_outlive_updates() {
+        let cell = SnapshotCell::new(vec![1, 2, 3]);
+        let before = cell.load();
+        let old = cell.swap(Arc::new(vec![4]));
+        assert!(Arc::ptr_eq(&before, &old));
+        // 旧快照不受新版本影响
+        assert_eq!(*before, [1, 2, 3]);
+        assert_eq!(*cell.load(), [4]);
+
+        let previous = cell.rcu(|v| v.iter().map(|x| x * 10).collect());
+        assert_eq!(*previous, [4]);
+        assert_eq!(cell.read(|v| v.clone()), [40]);
+        cell.store(vec![]);
+        assert!(cell.into_inner().is_empty());
+    }
+
+    #[test]
+    fn stress_rcu_loses_no_updates() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 500;
+        let cell = Arc::new(SnapshotCell::new(HashMap::<usize, usize>::new()));
+        spawn_workers(Arc::clone(&cell), THREADS, |cell, t| {
+            for _ in 0..PER_THREAD {
+                cell.rcu(|map| {
+                    let mut map = map.clone();
+                    *map.entry(t).or_default() += 1;
+                    map
+                });
+            }
+        });
+        let map = cell.load();
+        assert_eq!(map.len(), THREADS);
+        assert!(map.values().all(|&n| n == PER_THREAD));
+    }
+
+    #[test]
+    fn stress_readers_see_consistent_versions() {
+        // 每个版本内部两个字段相等，且版本号只增不减
+        let shared = Arc::new((SnapshotCell::new((0u64, 0u64)), AtomicBool::new(false)));
+        let writer = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for _ in 0..5_000 {
+                    shared.0.rcu(|&(a, _)| (a + 1, a + 1));
+                }
+                shared.1.store(true, Ordering::Relaxed);
+            })
+        };
+        spawn_workers(Arc::clone(&shared), 4, |shared, i| {
+            let (cell, done) = &*shared;
+            let mut last = 0;
+            while !done.load(Ordering::Relaxed) {
+                let (a, b) = if i % 2 == 0 {
+                    *cell.load()
+                } else {
+                    cell.read(|&v| v)
+                };
+                assert_eq!(a, b);
+                assert!(a >= last);
+                last = a;
+            }
+        });
+        writer.join().unwrap();
+        assert_eq!(*shared.0.load(), (5_000, 5_000));
+    }
+}
@@ -0,0 +1,248 @@
+//! 基于 AtomicBool 的自旋锁（从 atomic_spinlock 演示中提取，供基准测试等复用）
+//!
+//! 原子变量和线程调度都经过 [`crate::model`]，开启 `model-check` 后可以做模型检查。
+
+use crate::lock_order::{Access, LockTracker};
+use crate::model::sync::atomic::{AtomicBool, Ordering};
+use crate::model::{hint, thread};
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+/// 自旋多少次之后让出 CPU。持锁线程被抢占时，一直自旋只会白白烧掉时间片
+const SPINS_BEFORE_YIELD: u32 = 64;
+
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    tracker: LockTracker,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: 只要 T 是 Send 的，SpinLock<T> 就可以在线程间安全传递（Sync）。
+// 因为 lock() 机制保证了同一时间只有一个线程能访问内部数据。
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+// SpinLock 只需要 T: Send（与 std::sync::Mutex 相同），所以 SpinLock<Cell<_>> 是 Sync 的；
+// 但 Rc 这类非 Send 的类型绝不能被它"洗"成可共享的
+const _: () = {
+    use crate::assert::{assert_send, assert_sync};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    assert_send::<SpinLock<i32>>();
+    assert_sync::<SpinLock<Cell<i32>>>();
+
+    crate::assert_not_impl!(SpinLock<Rc<i32>>: Send);
+    crate::assert_not_impl!(SpinLock<Rc<i32>>: Sync);
+    assert_sync::<SpinLockGuard<'static, i32>>();
+    crate::assert_not_impl!(SpinLockGuard<'static, Cell<i32>>: Sync);
+    crate::assert_not_impl!(SpinLockGuard<'static, i32>: Send);
+};
+
+impl<T> SpinLock<T> {
+    crate::model::const_fn_unless_model! {
+        pub const fn new(data: T) -> SpinLock<T> {
+            SpinLock {
+                locked: AtomicBool::new(false),
+                tracker: LockTracker::new(),
+                data: UnsafeCell::new(data),
+            }
+        }
+    }
+
+    /// 命名后，`lock-order-debug` 的报告里会用这个名字指代这把锁
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.tracker.set_name(name);
+        self
+    }
+
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        let site = Location::caller();
+        self.tracker.before_wait(Access::Exclusive, site);
+        let mut spins = 0;
+        loop {
+            // 先进行简单的 load 检查，减少对缓存行的独占争用
+            while self.locked.load(Ordering::Relaxed) {
+                if spins < SPINS_BEFORE_YIELD {
+                    spins += 1;
+                    // 通知 CPU 我在自旋
+                    hint::spin_loop();
+                } else {
+                    thread::yield_now();
+                }
+            }
+            // 尝试获取锁：Acquire 确保我们在拿到锁之后，才能看到受保护数据的变化
+            if self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.tracker.acquired(Access::Exclusive, site);
+                return SpinLockGuard::new(self);
+            }
+        }
+    }
+
+    /// 不会阻塞，所以不参与锁顺序检查，但成功后同样记为"已持有"
+    #[cfg_attr(feature = "lock-order-debug", track_caller)]
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        let site = Location::caller();
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+            .then(|| {
+                self.tracker.acquired(Access::Exclusive, site);
+                SpinLockGuard::new(self)
+            })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for SpinLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SpinLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_struct("SpinLock").field("data", &&*guard).finish(),
+            None => f.write_str("SpinLock { <locked> }"),
+        }
+    }
+}
+
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+    // 裸指针让守卫既不 Send 也不 Sync：
+    // 自动推导会让 SpinLockGuard<Cell<_>> 变成 Sync，从而在两个线程里同时拿到 &Cell；
+    // 解锁也应当发生在加锁的线程上（锁顺序跟踪按线程记录持有的锁）
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T> SpinLockGuard<'a, T> {
+    fn new(lock: &'a SpinLock<T>) -> Self {
+        SpinLockGuard {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+// SAFETY: 共享守卫只能拿到 &T，与 std::sync::MutexGuard 一样要求 T: Sync
+unsafe impl<T: Sync> Sync for SpinLockGuard<'_, T> {}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.tracker.released();
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: 我们持有锁，且锁提供了互斥保证，
+        // 所以可以安全地分发可变引用。
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+#[cfg(all(test, not(feature = "model-check")))]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_try_lock() {
+        let lock = SpinLock::new(1);
+        let guard = lock.lock();
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+        *lock.try_lock().unwrap() += 1;
+        assert_eq!(lock.into_inner(), 2);
+    }
+
+    #[test]
+    fn stress_mutual_exclusion() {
+        let lock = Arc::new(SpinLock::new((0usize, 0usize)));
+        spawn_workers(Arc::clone(&lock), 8, |lock, _| {
+            for _ in 0..2_000 {
+                let mut g = lock.lock();
+                // 非原子的两步修改：若互斥失效，两个字段会不一致
+                g.0 += 1;
+                g.1 = g.0;
+            }
+        });
+        assert_eq!(*lock.lock(), (16_000, 16_000));
+    }
+}
+
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+
+    /// 比 atomic_spinlock 里的 test_spinlock 更严格：计数器放在 model::cell::UnsafeCell 里，
+    /// 互斥失效时即使计数碰巧正确，loom 也会报告并发访问
+    #[test]
+    fn model_spinlock_counter() {
+        use crate::model::{self, cell::UnsafeCell, sync::Arc, thread};
+
+        model::check(|| {
+            let lock = Arc::new(SpinLock::new(UnsafeCell::new(0usize)));
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let lock = Arc::clone(&lock);
+                    thread::spawn(move || {
+                        let guard = lock.lock();
+                        guard.with_mut(|n| unsafe { *n += 1 });
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            let total = lock.lock().with(|n| unsafe { *n });
+            assert_eq!(total, 2);
+        });
+    }
+
+    #[test]
+    fn model_try_lock_never_overlaps_lock() {
+        use crate::model::{self, cell::UnsafeCell, sync::Arc, thread};
+
+        model::check(|| {
+            let lock = Arc::new(SpinLock::new(UnsafeCell::new(0usize)));
+            let other = {
+                let lock = Arc::clone(&lock);
+                thread::spawn(move || {
+                    if let Some(guard) = lock.try_lock() {
+                        guard.with_mut(|n| unsafe { *n += 1 });
+                    }
+                })
+            };
+            lock.lock().with_mut(|n| unsafe { *n += 10 });
+            other.join().unwrap();
+            let total = lock.lock().with(|n| unsafe { *n });
+            assert!(total == 10 || total == 11);
+        });
+    }
+}
@@ -0,0 +1,21 @@
+//! 阻塞线程用的同步原语（networking 里 async_barrier / async_semaphore / async_once_cell 的阻塞版本）
+//!
+//! - [`Barrier`]：可重复使用的屏障，每一轮选出一个 leader
+//! - [`CountDownLatch`]：一次性的倒计时门闩，计数归零后放行所有等待者
+//! - [`Semaphore`]：计数信号量，许可以 RAII 方式归还，支持超时，按先来后到分配
+//! - [`OnceLock`]：只初始化一次的单元，并发调用 `get_or_init` 时只有一个线程执行初始化
+//! - [`CancellationToken`]：可以层层派生的取消令牌，用来通知阻塞的工作线程协作退出
+//!
+//! 前三者和取消令牌都基于 `Mutex + Condvar`；`OnceLock` 初始化完成后的读取只需要一次原子 load。
+
+mod barrier;
+mod cancel;
+mod latch;
+mod once_lock;
+mod semaphore;
+
+pub use barrier::{Barrier, BarrierWaitResult};
+pub use cancel::{CancelRegistration, CancellationToken, Cancelled};
+pub use latch::CountDownLatch;
+pub use once_lock::OnceLock;
+pub use semaphore::{Semaphore, SemaphorePermit};
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+struct State {
+    /// 本轮已到达的线程数
+    arrived: usize,
+    /// 轮次编号。等待者记下自己所在的轮次，编号变化就说明本轮已放行，
+    /// 这样即使下一轮已经开始（arrived 又从 0 涨起来），上一轮的等待者也不会被误判为还要继续等
+    generation: u64,
+}
+
+/// 可重复使用的屏障
+///
+/// 每凑齐 `n` 个线程放行一轮，随后自动进入下一轮。最后一个到达的线程是本轮的 leader，
+/// 适合在每轮之间做一次汇总（例如交换双缓冲）。
+pub struct Barrier {
+    state: Mutex<State>,
+    cvar: Condvar,
+    parties: usize,
+}
+
+/// [`Barrier::wait`] 的返回值，每一轮恰好有一个线程的 `is_leader()` 为 true
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+    generation: u64,
+}
+
+impl BarrierWaitResult {
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+
+    /// 放行的是第几轮（从 0 开始）
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Barrier {
+    /// `parties` 为 0 时与 1 相同：每次 wait 都立即返回并成为 leader
+    pub fn new(parties: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                arrived: 0,
+                generation: 0,
+            }),
+            cvar: Condvar::new(),
+            parties,
+        }
+    }
+
+    pub fn parties(&self) -> usize {
+        self.parties
+    }
+
+    /// 阻塞直到本轮所有线程都到达
+    pub fn wait(&self) -> BarrierWaitResult {
+        self.wait_deadline(None)
+            .expect("没有 deadline 的等待不会超时")
+    }
+
+    /// 最多等待 `timeout`；超时返回 None，并把自己从本轮的到达计数中撤回，
+    /// 因此本轮仍然需要凑齐 `parties` 个线程才会放行。
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`wait`](Self::wait) 一样一直等
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<BarrierWaitResult> {
+        self.wait_deadline(Instant::now().checked_add(timeout))
+    }
+
+    fn wait_deadline(&self, deadline: Option<Instant>) -> Option<BarrierWaitResult> {
+        let mut state = self.state.lock().unwrap();
+        let generation = state.generation;
+        state.arrived += 1;
+        if state.arrived >= self.parties {
+            state.arrived = 0;
+            state.generation = state.generation.wrapping_add(1);
+            self.cvar.notify_all();
+            return Some(BarrierWaitResult {
+                is_leader: true,
+                generation,
+            });
+        }
+
+        while state.generation == generation {
+            state = match deadline {
+                None => self.cvar.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        state.arrived -= 1;
+                        return None;
+                    }
+                    self.cvar.wait_timeout(state, deadline - now).unwrap().0
+                }
+            };
+        }
+        Some(BarrierWaitResult {
+            is_leader: false,
+            generation,
+        })
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Barrier")
+            .field("parties", &self.parties)
+            .field("arrived", &state.arrived)
+            .field("generation", &state.generation)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_single_party_is_always_leader() {
+        let barrier = Barrier::new(1);
+        for round in 0..3 {
+            let result = barrier.wait();
+            assert!(result.is_leader());
+            assert_eq!(result.generation(), round);
+        }
+    }
+
+    #[test]
+    fn test_timeout_withdraws_arrival() {
+        let barrier = Arc::new(Barrier::new(2));
+        assert!(barrier.wait_timeout(Duration::from_millis(10)).is_none());
+
+        // 超时的那次不算数：本轮仍需要两个线程
+        let other = Arc::clone(&barrier);
+        let handle = thread::spawn(move || other.wait());
+        let mine = barrier.wait();
+        let theirs = handle.join().unwrap();
+        assert_eq!(mine.generation(), 0);
+        assert_ne!(mine.is_leader(), theirs.is_leader());
+    }
+
+    #[test]
+    fn test_huge_timeout_waits_for_the_round() {
+        let barrier = Arc::new(Barrier::new(2));
+        let other = Arc::clone(&barrier);
+        let handle = thread::spawn(move || other.wait_timeout(Duration::MAX).is_some());
+        thread::sleep(Duration::from_millis(20));
+        barrier.wait();
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn stress_rounds_stay_in_lockstep() {
+        const THREADS: usize = 8;
+        const ROUNDS: usize = 200;
+        // 每个线程每轮 +1；leader 检查本轮所有线程都已加完，并记录 leader 次数
+        let shared = Arc::new((
+            Barrier::new(THREADS),
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+        ));
+        spawn_workers(Arc::clone(&shared), THREADS, |shared, _| {
+            let (barrier, progress, leaders) = &*shared;
+            for round in 0..ROUNDS {
+                progress.fetch_add(1, Ordering::SeqCst);
+                let result = barrier.wait();
+                assert_eq!(result.generation(), 2 * round as u64);
+                if result.is_leader() {
+                    assert_eq!(progress.load(Ordering::SeqCst), (round + 1) * THREADS);
+                    leaders.fetch_add(1, Ordering::SeqCst);
+                }
+                // 第二道屏障：保证 leader 检查时没有线程已经进入下一轮
+                barrier.wait();
+            }
+        });
+        assert_eq!(shared.2.load(Ordering::SeqCst), ROUNDS);
+    }
+}
@@ -0,0 +1,338 @@
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+type Callback = Box<dyn FnOnce() + Send + 'static>;
+
+struct State {
+    callbacks: Vec<(u64, Callback)>,
+    next_id: u64,
+    children: Vec<Weak<Inner>>,
+}
+
+struct Inner {
+    /// 只在持有 state 锁时置位，等待者因此不会错过唤醒
+    cancelled: AtomicBool,
+    state: Mutex<State>,
+    cvar: Condvar,
+}
+
+/// 协作式取消令牌
+///
+/// 克隆出来的令牌共享同一个取消状态；[`child_token`](Self::child_token) 创建的子令牌
+/// 会随父令牌一起被取消，但取消子令牌不影响父令牌。
+///
+/// 取消只是一个信号：长时间运行的线程需要自己定期检查 [`is_cancelled`](Self::is_cancelled)，
+/// 或者在阻塞等待时改用 [`wait_timeout`](Self::wait_timeout)，然后自行清理退出。
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                state: Mutex::new(State {
+                    callbacks: Vec::new(),
+                    next_id: 0,
+                    children: Vec::new(),
+                }),
+                cvar: Condvar::new(),
+            }),
+        }
+    }
+
+    /// 创建子令牌。父令牌已经取消时，子令牌一创建就是已取消状态
+    pub fn child_token(&self) -> CancellationToken {
+        let child = CancellationToken::new();
+        let mut state = self.inner.state.lock().unwrap();
+        if self.is_cancelled() {
+            drop(state);
+            child.cancel();
+        } else {
+            // 顺手清理已经被丢弃的子令牌，避免长寿的父令牌上越积越多
+            state.children.retain(|c| c.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.inner));
+        }
+        child
+    }
+
+    /// 取消本令牌及其所有后代，并在当前线程上依次执行注册的回调。重复调用没有效果
+    ///
+    /// 某个回调 panic 时，其余回调和子令牌照常处理，最后再把第一个 panic 重新抛出
+    pub fn cancel(&self) {
+        let (callbacks, children) = {
+            let mut state = self.inner.state.lock().unwrap();
+            if self.inner.cancelled.swap(true, Ordering::AcqRel) {
+                return;
+            }
+            (
+                std::mem::take(&mut state.callbacks),
+                std::mem::take(&mut state.children),
+            )
+        };
+        self.inner.cvar.notify_all();
+
+        let mut first_panic = None;
+        for (_, callback) in callbacks {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(callback)) {
+                first_panic.get_or_insert(payload);
+            }
+        }
+        for child in children.iter().filter_map(Weak::upgrade) {
+            let child = CancellationToken { inner: child };
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| child.cancel())) {
+                first_panic.get_or_insert(payload);
+            }
+        }
+        if let Some(payload) = first_panic {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// 已取消时返回 `Err(Cancelled)`，方便在循环里用 `?` 提前退出
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 阻塞直到令牌被取消
+    pub fn wait(&self) {
+        let state = self.inner.state.lock().unwrap();
+        let _state = self
+            .inner
+            .cvar
+            .wait_while(state, |_| !self.is_cancelled())
+            .unwrap();
+    }
+
+    /// 最多等待 `timeout`，返回令牌是否已被取消
+    ///
+    /// 可以代替工作循环里的 `thread::sleep`：取消时立刻醒来，而不是睡满整段时间。
+    /// 传入 `Duration::MAX` 这类 `Instant` 表示不了的超时时等同于 [`wait`](Self::wait)
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            self.wait();
+            return true;
+        };
+        let mut state = self.inner.state.lock().unwrap();
+        while !self.is_cancelled() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .inner
+                .cvar
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+        true
+    }
+
+    /// 注册取消时执行的回调，回调在调用 `cancel` 的线程上运行
+    ///
+    /// 令牌已经取消时，回调立即在当前线程上执行。
+    /// 返回的 [`CancelRegistration`] 被 drop 时注销回调，不想注销就调用它的 `forget`
+    pub fn on_cancel<F>(&self, f: F) -> CancelRegistration
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut state = self.inner.state.lock().unwrap();
+        if self.is_cancelled() {
+            drop(state);
+            f();
+            return CancelRegistration {
+                token: Weak::new(),
+                id: 0,
+            };
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.callbacks.push((id, Box::new(f)));
+        CancelRegistration {
+            token: Arc::downgrade(&self.inner),
+            id,
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// [`CancellationToken::on_cancel`] 注册的回调，drop 时注销
+#[must_use = "drop 会立即注销回调；不需要注销时请调用 forget"]
+pub struct CancelRegistration {
+    token: Weak<Inner>,
+    id: u64,
+}
+
+impl CancelRegistration {
+    /// 保留回调，直到令牌被取消（或令牌本身被丢弃）
+    pub fn forget(mut self) {
+        self.token = Weak::new();
+    }
+}
+
+impl Drop for CancelRegistration {
+    fn drop(&mut self) {
+        if let Some(inner) = self.token.upgrade() {
+            let mut state = inner.state.lock().unwrap();
+            state.callbacks.retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+impl fmt::Debug for CancelRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelRegistration")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// 操作因令牌被取消而提前结束
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("操作已取消")
+    }
+}
+
+impl Error for Cancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    #[test]
+    fn test_cancel_propagates_to_descendants_only() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let sibling = root.child_token();
+
+        child.cancel();
+        assert!(child.is_cancelled() && grandchild.is_cancelled());
+        assert!(!root.is_cancelled() && !sibling.is_cancelled());
+        assert_eq!(child.check(), Err(Cancelled));
+
+        root.cancel();
+        assert!(sibling.is_cancelled());
+        // 父令牌取消之后再创建的子令牌直接处于取消状态
+        assert!(root.child_token().is_cancelled());
+        assert!(root.clone().wait_timeout(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_callbacks_run_once_and_can_be_unregistered() {
+        let token = CancellationToken::new();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = |n| {
+            let hits = Arc::clone(&hits);
+            move || {
+                hits.fetch_add(n, Ordering::Relaxed);
+            }
+        };
+        token.on_cancel(counter(1)).forget();
+        drop(token.on_cancel(counter(100)));
+        let kept = token.on_cancel(counter(10));
+
+        token.cancel();
+        token.cancel();
+        assert_eq!(hits.load(Ordering::Relaxed), 11);
+        drop(kept);
+
+        // 已取消时立即执行
+        token.on_cancel(counter(1000)).forget();
+        assert_eq!(hits.load(Ordering::Relaxed), 1011);
+    }
+
+    #[test]
+    fn test_panicking_callback_does_not_skip_the_rest() {
+        let token = CancellationToken::new();
+        let child = token.child_token();
+        token.on_cancel(|| panic!("回调出错")).forget();
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ran);
+        token
+            .on_cancel(move || flag.store(true, Ordering::Relaxed))
+            .forget();
+
+        let result = panic::catch_unwind(|| token.cancel());
+        assert!(result.is_err());
+        assert!(ran.load(Ordering::Relaxed));
+        assert!(child.is_cancelled());
+    }
+
+    #[test]
+    fn test_huge_timeout_sleeps_until_cancelled() {
+        let token = CancellationToken::new();
+        let worker = {
+            let token = token.clone();
+            thread::spawn(move || token.wait_timeout(Duration::MAX))
+        };
+        thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        assert!(worker.join().unwrap());
+    }
+
+    #[test]
+    fn stress_waiters_wake_on_cancel() {
+        let token = CancellationToken::new();
+        assert!(!token.wait_timeout(Duration::from_millis(5)));
+
+        let woke = Arc::new(AtomicUsize::new(0));
+        let waiters = {
+            let (token, woke) = (token.clone(), Arc::clone(&woke));
+            thread::spawn(move || {
+                spawn_workers((token, woke), 8, |(token, woke), i| {
+                    let token = if i % 2 == 0 {
+                        token.child_token()
+                    } else {
+                        token
+                    };
+                    if i % 3 == 0 {
+                        token.wait();
+                    } else {
+                        assert!(token.wait_timeout(Duration::from_secs(60)));
+                    }
+                    woke.fetch_add(1, Ordering::Relaxed);
+                })
+            })
+        };
+        thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        waiters.join().unwrap();
+        assert_eq!(woke.load(Ordering::Relaxed), 8);
+    }
+}
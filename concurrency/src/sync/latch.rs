@@ -0,0 +1,133 @@
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// 一次性的倒计时门闩（类似 Java 的 CountDownLatch）
+///
+/// 与 [`Barrier`](super::Barrier) 不同，调用 `count_down` 的线程不会阻塞，
+/// 等待者与计数者可以是完全不同的线程；计数归零后门闩永久打开，不能重置。
+pub struct CountDownLatch {
+    count: Mutex<usize>,
+    cvar: Condvar,
+}
+
+impl CountDownLatch {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: Mutex::new(count),
+            cvar: Condvar::new(),
+        }
+    }
+
+    /// 计数减一，减到 0 时唤醒所有等待者。已经为 0 时什么也不做
+    pub fn count_down(&self) {
+        let mut count = self.count.lock().unwrap();
+        if *count == 0 {
+            return;
+        }
+        *count -= 1;
+        if *count == 0 {
+            self.cvar.notify_all();
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        *self.count.lock().unwrap()
+    }
+
+    /// 阻塞直到计数归零
+    pub fn wait(&self) {
+        let count = self.count.lock().unwrap();
+        let _count = self.cvar.wait_while(count, |count| *count > 0).unwrap();
+    }
+
+    /// 最多等待 `timeout`，返回门闩是否已经打开。
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`wait`](Self::wait) 一样一直等
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            self.wait();
+            return true;
+        };
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            count = self.cvar.wait_timeout(count, deadline - now).unwrap().0;
+        }
+        true
+    }
+}
+
+impl fmt::Debug for CountDownLatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountDownLatch")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_wait_timeout_and_saturation() {
+        let latch = CountDownLatch::new(2);
+        latch.count_down();
+        assert!(!latch.wait_timeout(Duration::from_millis(10)));
+        latch.count_down();
+        latch.count_down(); // 多余的 count_down 不会下溢
+        assert_eq!(latch.count(), 0);
+        assert!(latch.wait_timeout(Duration::ZERO));
+        latch.wait();
+    }
+
+    #[test]
+    fn test_huge_timeout_waits_until_open() {
+        let latch = Arc::new(CountDownLatch::new(1));
+        let waiter = {
+            let latch = Arc::clone(&latch);
+            thread::spawn(move || latch.wait_timeout(Duration::MAX))
+        };
+        thread::sleep(Duration::from_millis(20));
+        latch.count_down();
+        assert!(waiter.join().unwrap());
+    }
+
+    #[test]
+    fn stress_waiters_see_all_work() {
+        const WORKERS: usize = 16;
+        const WAITERS: usize = 4;
+        let latch = Arc::new(CountDownLatch::new(WORKERS));
+        let done = Arc::new(AtomicUsize::new(0));
+
+        let waiters: Vec<_> = (0..WAITERS)
+            .map(|_| {
+                let latch = Arc::clone(&latch);
+                let done = Arc::clone(&done);
+                thread::spawn(move || {
+                    latch.wait();
+                    // 门闩打开前的所有写入对等待者可见
+                    assert_eq!(done.load(Ordering::Relaxed), WORKERS);
+                })
+            })
+            .collect();
+
+        spawn_workers(Arc::clone(&done), WORKERS, {
+            let latch = Arc::clone(&latch);
+            move |done, _| {
+                done.fetch_add(1, Ordering::Relaxed);
+                latch.count_down();
+            }
+        });
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    }
+}
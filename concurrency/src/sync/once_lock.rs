@@ -0,0 +1,269 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Condvar, Mutex};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// 只写入一次的单元
+///
+/// 多个线程同时调用 [`get_or_init`](Self::get_or_init) 时只有一个线程执行初始化闭包，
+/// 其余线程阻塞到初始化完成后直接拿到结果。初始化完成后的读取只是一次 Acquire load。
+///
+/// 初始化闭包 panic 时单元回到未初始化状态，等待者中的一个会接着尝试初始化（与 std 相同）。
+pub struct OnceLock<T> {
+    state: AtomicU8,
+    // 只有初始化期间才会用到：等待者睡在这里，而不是忙等
+    lock: Mutex<()>,
+    cvar: Condvar,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: 共享的 OnceLock 可以让一个线程写入 T、其他线程读取 &T，
+// 所以需要 T: Send（值由写入线程移交出去）和 T: Sync（多个线程同时持有 &T）
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+unsafe impl<T: Send> Send for OnceLock<T> {}
+
+const _: () = {
+    use crate::assert::assert_sync;
+    use std::cell::Cell;
+    use std::sync::MutexGuard;
+
+    assert_sync::<OnceLock<String>>();
+    crate::assert_not_impl!(OnceLock<Cell<i32>>: Sync);
+    crate::assert_not_impl!(OnceLock<MutexGuard<'static, i32>>: Sync);
+};
+
+impl<T> OnceLock<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+            lock: Mutex::new(()),
+            cvar: Condvar::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// 已初始化时返回值，否则返回 None（不会阻塞，即使另一个线程正在初始化）
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == COMPLETE {
+            // SAFETY: COMPLETE 之后 value 已写入且不会再被修改；Acquire 与写入方的 Release 配对
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == COMPLETE {
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// 尝试写入；已经有值（或写入期间别人先完成了）时把 `value` 原样返回
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// 返回已有的值，没有时用 `f` 初始化；其他线程正在初始化时阻塞等待
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        self.initialize(f);
+        self.get().expect("initialize 返回后一定已经初始化")
+    }
+
+    fn initialize<F: FnOnce() -> T>(&self, f: F) {
+        let mut guard = self.lock.lock().unwrap();
+        loop {
+            match self.state.compare_exchange(
+                INCOMPLETE,
+                RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(COMPLETE) => return,
+                Err(_) => guard = self.cvar.wait(guard).unwrap(),
+            }
+        }
+        // 执行用户闭包时不持有内部锁：闭包里可能再去访问别的 OnceLock，甚至耗时很久
+        drop(guard);
+
+        // 闭包 panic 时由 Reset 把状态改回 INCOMPLETE 并唤醒等待者
+        let reset = Reset(self);
+        let value = f();
+        std::mem::forget(reset);
+
+        // SAFETY: 只有把状态从 INCOMPLETE 改成 RUNNING 的线程会走到这里，此时没有任何读者
+        unsafe { (*self.value.get()).write(value) };
+        self.finish(COMPLETE);
+    }
+
+    fn finish(&self, state: u8) {
+        // 先拿锁再改状态：保证不会有等待者在"检查状态"和"开始睡眠"之间错过通知
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.state.store(state, Ordering::Release);
+        self.cvar.notify_all();
+    }
+
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+
+    /// 取出值并把单元恢复为未初始化状态
+    pub fn take(&mut self) -> Option<T> {
+        if *self.state.get_mut() == COMPLETE {
+            *self.state.get_mut() = INCOMPLETE;
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+}
+
+struct Reset<'a, T>(&'a OnceLock<T>);
+
+impl<T> Drop for Reset<'_, T> {
+    fn drop(&mut self) {
+        self.0.finish(INCOMPLETE);
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceLock<T> {
+    fn from(value: T) -> Self {
+        let cell = Self::new();
+        let _ = cell.set(value);
+        cell
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == COMPLETE {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceLock").field(value).finish(),
+            None => f.write_str("OnceLock(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::panic;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_set_get_take() {
+        let mut cell = OnceLock::new();
+        assert_eq!(cell.get(), None);
+        assert_eq!(cell.set(String::from("a")), Ok(()));
+        assert_eq!(cell.set(String::from("b")), Err(String::from("b")));
+        assert_eq!(cell.get().map(String::as_str), Some("a"));
+        assert_eq!(cell.take().as_deref(), Some("a"));
+        assert_eq!(cell.get_or_init(|| String::from("c")), "c");
+        assert_eq!(cell.into_inner().as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_panicking_init_lets_another_thread_retry() {
+        let cell = Arc::new(OnceLock::new());
+        let result = {
+            let cell = Arc::clone(&cell);
+            thread::spawn(move || {
+                cell.get_or_init(|| -> i32 { panic!("初始化失败") });
+            })
+            .join()
+        };
+        assert!(result.is_err());
+        assert_eq!(cell.get(), None);
+        assert_eq!(*cell.get_or_init(|| 7), 7);
+    }
+
+    #[test]
+    fn test_value_is_dropped_once() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Noisy;
+        impl Drop for Noisy {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        drop(OnceLock::from(Noisy));
+        drop(OnceLock::<Noisy>::new());
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn stress_single_initializer() {
+        const THREADS: usize = 16;
+        let shared = Arc::new((OnceLock::new(), AtomicUsize::new(0), Barrier::new(THREADS)));
+        spawn_workers(Arc::clone(&shared), THREADS, |shared, i| {
+            let (cell, calls, barrier) = &*shared;
+            barrier.wait();
+            let value = cell.get_or_init(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                // 初始化拖得久一些，让其他线程确实进入等待
+                thread::sleep(Duration::from_millis(20));
+                vec![i; 100]
+            });
+            // 所有线程看到的都是同一个、完整写入的值
+            assert_eq!(value.len(), 100);
+            assert!(value.iter().all(|&v| v == value[0]));
+        });
+        assert_eq!(shared.1.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn stress_retry_after_panics() {
+        // 前几次初始化都 panic，最终必须恰好成功一次，且所有线程都拿到值
+        let shared = Arc::new((OnceLock::new(), AtomicUsize::new(0)));
+        spawn_workers(Arc::clone(&shared), 8, |shared, _| {
+            let (cell, attempts) = &*shared;
+            loop {
+                let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    *cell.get_or_init(|| {
+                        if attempts.fetch_add(1, Ordering::SeqCst) < 3 {
+                            panic!("还没准备好");
+                        }
+                        42
+                    })
+                }));
+                if let Ok(value) = result {
+                    assert_eq!(value, 42);
+                    break;
+                }
+            }
+        });
+        assert_eq!(shared.1.load(Ordering::SeqCst), 4);
+    }
+}
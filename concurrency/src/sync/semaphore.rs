@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+struct State {
+    permits: usize,
+    /// 排队中的请求编号，按到达顺序排列
+    queue: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+/// 计数信号量
+///
+/// 许可按先来后到分配：队首的请求拿到许可之前，后来的请求即使需要的更少也不能插队。
+/// 否则一个 `acquire_many(10)` 可能被源源不断的 `acquire()` 饿死。
+///
+/// 许可以 [`SemaphorePermit`] 的形式返回，drop 时自动归还。
+pub struct Semaphore {
+    state: Mutex<State>,
+    cvar: Condvar,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                permits,
+                queue: VecDeque::new(),
+                next_ticket: 0,
+            }),
+            cvar: Condvar::new(),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// 凭空增加 `n` 个许可（例如扩容连接池）
+    pub fn add_permits(&self, n: usize) {
+        self.release(n);
+    }
+
+    /// 获取一个许可，没有空闲许可时阻塞
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        self.acquire_many(1)
+    }
+
+    /// 一次性获取 `n` 个许可。若许可总数永远达不到 `n`，会一直阻塞
+    pub fn acquire_many(&self, n: usize) -> SemaphorePermit<'_> {
+        self.acquire_deadline(n, None)
+            .expect("没有 deadline 的等待不会超时")
+    }
+
+    /// 立即尝试获取一个许可；有线程在排队时也会失败，以免插队
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        let mut state = self.state.lock().unwrap();
+        if state.queue.is_empty() && state.permits >= n {
+            state.permits -= n;
+            Some(SemaphorePermit::new(self, n))
+        } else {
+            None
+        }
+    }
+
+    /// 最多等待 `timeout`，超时返回 None
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<SemaphorePermit<'_>> {
+        self.acquire_many_timeout(1, timeout)
+    }
+
+    /// 超时大到 `Instant` 表示不了（比如 `Duration::MAX`）时和 [`acquire_many`](Self::acquire_many) 一样一直等
+    pub fn acquire_many_timeout(&self, n: usize, timeout: Duration) -> Option<SemaphorePermit<'_>> {
+        self.acquire_deadline(n, Instant::now().checked_add(timeout))
+    }
+
+    fn acquire_deadline(&self, n: usize, deadline: Option<Instant>) -> Option<SemaphorePermit<'_>> {
+        let mut state = self.state.lock().unwrap();
+        if state.queue.is_empty() && state.permits >= n {
+            state.permits -= n;
+            return Some(SemaphorePermit::new(self, n));
+        }
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.queue.push_back(ticket);
+        loop {
+            if state.queue.front() == Some(&ticket) && state.permits >= n {
+                state.queue.pop_front();
+                state.permits -= n;
+                // 剩下的许可也许还够下一个排队者用
+                if state.permits > 0 && !state.queue.is_empty() {
+                    self.cvar.notify_all();
+                }
+                return Some(SemaphorePermit::new(self, n));
+            }
+            state = match deadline {
+                None => self.cvar.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        let pos = state.queue.iter().position(|&t| t == ticket).unwrap();
+                        state.queue.remove(pos);
+                        // 如果放弃的是队首，后面的请求可能已经可以满足了
+                        self.cvar.notify_all();
+                        return None;
+                    }
+                    self.cvar.wait_timeout(state, deadline - now).unwrap().0
+                }
+            };
+        }
+    }
+
+    fn release(&self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.state.lock().unwrap().permits += n;
+        // 条件变量无法只唤醒队首，只能全部唤醒后由它们自己判断
+        self.cvar.notify_all();
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Semaphore")
+            .field("permits", &state.permits)
+            .field("waiting", &state.queue.len())
+            .finish()
+    }
+}
+
+/// 持有的许可，drop 时归还给信号量
+#[must_use = "许可被立即 drop 后会马上归还"]
+pub struct SemaphorePermit<'a> {
+    sem: &'a Semaphore,
+    permits: usize,
+}
+
+impl<'a> SemaphorePermit<'a> {
+    fn new(sem: &'a Semaphore, permits: usize) -> Self {
+        Self { sem, permits }
+    }
+
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// 不归还许可，信号量的总许可数因此永久减少
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.sem.release(self.permits);
+    }
+}
+
+impl fmt::Debug for SemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MaxTracker;
+    use crate::spawn_workers;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_permits_are_returned_on_drop() {
+        let sem = Semaphore::new(3);
+        let two = sem.acquire_many(2);
+        assert_eq!(two.num_permits(), 2);
+        assert_eq!(sem.available_permits(), 1);
+        assert!(sem.try_acquire_many(2).is_none());
+        drop(two);
+        assert_eq!(sem.available_permits(), 3);
+
+        sem.acquire().forget();
+        assert_eq!(sem.available_permits(), 2);
+        sem.add_permits(1);
+        assert_eq!(sem.available_permits(), 3);
+    }
+
+    #[test]
+    fn test_timeout_and_no_barging() {
+        let sem = Arc::new(Semaphore::new(1));
+        let held = sem.acquire();
+        assert!(sem.acquire_timeout(Duration::from_millis(10)).is_none());
+
+        // 一个需要 2 个许可的请求在排队，此时即使有空闲许可，单个请求也不能插队
+        let waiter = {
+            let sem = Arc::clone(&sem);
+            thread::spawn(move || sem.acquire_many(2).num_permits())
+        };
+        while sem.state.lock().unwrap().queue.is_empty() {
+            thread::yield_now();
+        }
+        drop(held);
+        assert!(sem.try_acquire().is_none());
+        sem.add_permits(1);
+        assert_eq!(waiter.join().unwrap(), 2);
+        assert_eq!(sem.available_permits(), 2);
+    }
+
+    #[test]
+    fn test_timed_out_head_unblocks_followers() {
+        let sem = Arc::new(Semaphore::new(1));
+        // 队首要 2 个许可（永远凑不齐），后面只要 1 个：队首超时后后者必须能拿到
+        let head = {
+            let sem = Arc::clone(&sem);
+            thread::spawn(move || {
+                sem.acquire_many_timeout(2, Duration::from_millis(50))
+                    .is_none()
+            })
+        };
+        while sem.state.lock().unwrap().queue.is_empty() {
+            thread::yield_now();
+        }
+        let follower = sem.acquire_timeout(Duration::from_secs(5));
+        assert!(follower.is_some());
+        assert!(head.join().unwrap());
+    }
+
+    #[test]
+    fn test_huge_timeout_waits_for_release() {
+        let sem = Arc::new(Semaphore::new(1));
+        let held = sem.acquire();
+        let waiter = {
+            let sem = Arc::clone(&sem);
+            thread::spawn(move || sem.acquire_timeout(Duration::MAX).is_some())
+        };
+        thread::sleep(Duration::from_millis(20));
+        drop(held);
+        assert!(waiter.join().unwrap());
+    }
+
+    #[test]
+    fn stress_never_exceeds_permits() {
+        const PERMITS: usize = 3;
+        let shared = Arc::new((
+            Semaphore::new(PERMITS),
+            AtomicUsize::new(0),
+            MaxTracker::new(),
+        ));
+        spawn_workers(Arc::clone(&shared), 16, |shared, i| {
+            let (sem, inside, max) = &*shared;
+            for round in 0..300 {
+                let permit = if (i + round) % 4 == 0 {
+                    sem.acquire_many(2)
+                } else {
+                    sem.acquire()
+                };
+                let n =
+                    inside.fetch_add(permit.num_permits(), Ordering::SeqCst) + permit.num_permits();
+                max.record(n as i64);
+                thread::yield_now();
+                inside.fetch_sub(permit.num_permits(), Ordering::SeqCst);
+            }
+        });
+        let (sem, inside, max) = &*shared;
+        assert_eq!(inside.load(Ordering::SeqCst), 0);
+        assert!(max.get().unwrap() <= PERMITS as i64);
+        assert_eq!(sem.available_permits(), PERMITS);
+    }
+}
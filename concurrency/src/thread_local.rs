@@ -0,0 +1,380 @@
+//! 对象级的线程局部存储
+//!
+//! `thread_local!` 声明的是全局静态变量：每个线程一份，但线程结束后值就丢了，别的线程也看不到。
+//! [`ThreadLocal<T>`] 则是一个普通对象：每个访问它的线程懒加载一份自己的 `T`，
+//! 持有者在工作线程结束后可以遍历或合并所有线程的值，典型用法是"每线程累加器"——
+//! 热路径上各写各的，完全没有争用，最后再汇总一次。
+//!
+//! 实现：每个线程分配一个小整数编号，按编号把值放进分段数组。
+//! 第 b 段长度为 2^b，懒分配、只增不减，因此已经发出的 `&T` 永远不会因为扩容而失效。
+
+use std::cell::{RefCell, UnsafeCell};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+
+const BUCKETS: usize = usize::BITS as usize;
+
+/// 线程编号分配器：线程退出时归还编号，新线程优先复用最小的空闲编号，
+/// 这样编号总是紧凑的，分段数组不会无限增长
+struct IdAllocator {
+    next: usize,
+    free: BinaryHeap<Reverse<usize>>,
+}
+
+static IDS: Mutex<IdAllocator> = Mutex::new(IdAllocator {
+    next: 0,
+    free: BinaryHeap::new(),
+});
+
+struct ThreadId(usize);
+
+impl ThreadId {
+    fn new() -> Self {
+        let mut ids = IDS.lock().unwrap();
+        let id = match ids.free.pop() {
+            Some(Reverse(id)) => id,
+            None => {
+                ids.next += 1;
+                ids.next - 1
+            }
+        };
+        ThreadId(id)
+    }
+}
+
+impl Drop for ThreadId {
+    fn drop(&mut self) {
+        IDS.lock().unwrap().free.push(Reverse(self.0));
+    }
+}
+
+thread_local! {
+    static THREAD_ID: ThreadId = ThreadId::new();
+}
+
+fn current_thread_id() -> usize {
+    THREAD_ID.with(|id| id.0)
+}
+
+/// 编号 -> (段号, 段内下标)
+fn location(id: usize) -> (usize, usize) {
+    let n = id + 1;
+    let bucket = (usize::BITS - 1 - n.leading_zeros()) as usize;
+    (bucket, n - (1 << bucket))
+}
+
+struct Entry<T> {
+    present: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// 每线程一份的值容器
+///
+/// 线程退出后它的值不会被销毁，而是留在容器里等持有者汇总。
+/// 线程编号会被新线程复用，所以新线程可能拿到某个已退出线程留下的值——
+/// 对累加器来说这没有影响，只是继续往上累加。
+pub struct ThreadLocal<T: Send> {
+    buckets: [AtomicPtr<Entry<T>>; BUCKETS],
+    // 让 drop 检查知道我们拥有 T
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: 每个槽位只会被它所属的线程写入和通过 get 读取；
+// 跨线程读取只发生在 iter（要求 T: Sync）或拥有 &mut self / self 的时候
+unsafe impl<T: Send> Sync for ThreadLocal<T> {}
+
+impl<T: Send> ThreadLocal<T> {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicPtr::new(ptr::null_mut()) }; BUCKETS],
+            _marker: PhantomData,
+        }
+    }
+
+    fn entry(&self, id: usize) -> Option<&Entry<T>> {
+        let (bucket, index) = location(id);
+        let ptr = self.buckets[bucket].load(Ordering::Acquire);
+        // SAFETY: 段一旦发布就不会被释放（直到 ThreadLocal 本身被 drop），长度为 2^bucket
+        (!ptr.is_null()).then(|| unsafe { &*ptr.add(index) })
+    }
+
+    fn entry_or_alloc(&self, id: usize) -> &Entry<T> {
+        let (bucket, index) = location(id);
+        let slot = &self.buckets[bucket];
+        let mut ptr = slot.load(Ordering::Acquire);
+        if ptr.is_null() {
+            let new = allocate_bucket::<T>(1 << bucket);
+            match slot.compare_exchange(ptr, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => ptr = new,
+                Err(existing) => {
+                    // 其他线程抢先分配了同一段：释放自己的那份
+                    unsafe { free_bucket(new, 1 << bucket) };
+                    ptr = existing;
+                }
+            }
+        }
+        unsafe { &*ptr.add(index) }
+    }
+
+    /// 当前线程的值（如果已经创建）
+    pub fn get(&self) -> Option<&T> {
+        let entry = self.entry(current_thread_id())?;
+        entry
+            .present
+            .load(Ordering::Acquire)
+            // SAFETY: present 为 true 说明值已初始化，且只有本线程会写这个槽位
+            .then(|| unsafe { (*entry.value.get()).assume_init_ref() })
+    }
+
+    /// 当前线程的值，第一次访问时用 `create` 创建
+    pub fn get_or(&self, create: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        let value = create();
+        let entry = self.entry_or_alloc(current_thread_id());
+        // create 内部可能重入并已经为本线程创建了值：保留先创建的那个
+        if !entry.present.load(Ordering::Acquire) {
+            unsafe { (*entry.value.get()).write(value) };
+            // Release：遍历的线程看到 present 后一定能看到完整的值
+            entry.present.store(true, Ordering::Release);
+        }
+        unsafe { (*entry.value.get()).assume_init_ref() }
+    }
+
+    pub fn get_or_default(&self) -> &T
+    where
+        T: Default,
+    {
+        self.get_or(T::default)
+    }
+
+    /// 遍历所有线程的值。其他线程可能正在使用自己的值，所以要求 T: Sync
+    pub fn iter(&self) -> impl Iterator<Item = &T>
+    where
+        T: Sync,
+    {
+        self.entries()
+            .map(|e| unsafe { (*e.value.get()).assume_init_ref() })
+    }
+
+    /// 独占访问时可以拿到可变引用，不需要 T: Sync
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.entries()
+            .map(|e| unsafe { (*e.value.get()).assume_init_mut() })
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &Entry<T>> {
+        self.buckets.iter().enumerate().flat_map(|(b, slot)| {
+            let ptr = slot.load(Ordering::Acquire);
+            let len = if ptr.is_null() { 0 } else { 1 << b };
+            (0..len)
+                .map(move |i| unsafe { &*ptr.add(i) })
+                .filter(|e| e.present.load(Ordering::Acquire))
+        })
+    }
+
+    /// 丢弃所有线程的值，之后每个线程再次访问时会重新创建
+    pub fn clear(&mut self) {
+        for entry in self.entries() {
+            entry.present.store(false, Ordering::Relaxed);
+            unsafe { (*entry.value.get()).assume_init_drop() };
+        }
+    }
+}
+
+fn allocate_bucket<T>(len: usize) -> *mut Entry<T> {
+    let bucket: Box<[Entry<T>]> = (0..len)
+        .map(|_| Entry {
+            present: AtomicBool::new(false),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        })
+        .collect();
+    Box::into_raw(bucket) as *mut Entry<T>
+}
+
+/// 只释放内存，不 drop 其中的值
+unsafe fn free_bucket<T>(ptr: *mut Entry<T>, len: usize) {
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+}
+
+impl<T: Send> Drop for ThreadLocal<T> {
+    fn drop(&mut self) {
+        self.clear();
+        for (b, slot) in self.buckets.iter().enumerate() {
+            let ptr = slot.load(Ordering::Relaxed);
+            if !ptr.is_null() {
+                unsafe { free_bucket(ptr, 1 << b) };
+            }
+        }
+    }
+}
+
+impl<T: Send> Default for ThreadLocal<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> IntoIterator for ThreadLocal<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let values: Vec<T> = self
+            .entries()
+            .map(|e| {
+                e.present.store(false, Ordering::Relaxed);
+                unsafe { (*e.value.get()).assume_init_read() }
+            })
+            .collect();
+        values.into_iter()
+    }
+}
+
+impl<T: Send + fmt::Debug> fmt::Debug for ThreadLocal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ThreadLocal {{ local: {:?} }}", self.get())
+    }
+}
+
+/// [`crate::spawn_workers`] 的归约版本：每个工作线程拿到一个自己独占的累加器 `&mut A`，
+/// 全部结束后返回各线程的累加器，由调用方合并。整个过程不需要任何锁。
+///
+/// 线程编号会复用，先结束的线程留下的累加器可能被后来的线程接着使用，
+/// 所以返回的个数不超过 `count`，但合并结果不受影响
+///
+/// ```
+/// use learning_concurrency::thread_local::spawn_workers_reduce;
+///
+/// let partial = spawn_workers_reduce((), 4, |_, i, sum: &mut usize| {
+///     for n in 0..1000 {
+///         *sum += n * i;
+///     }
+/// });
+/// let total: usize = partial.into_iter().sum();
+/// assert_eq!(total, 499_500 * (0 + 1 + 2 + 3));
+/// ```
+pub fn spawn_workers_reduce<T, A, F>(shared_data: T, count: usize, task: F) -> Vec<A>
+where
+    T: Send + Clone + 'static,
+    A: Default + Send + 'static,
+    F: Fn(T, usize, &mut A) + Send + Sync + 'static + Clone,
+{
+    let locals = Arc::new(ThreadLocal::<RefCell<A>>::new());
+    crate::spawn_workers(
+        (shared_data, Arc::clone(&locals)),
+        count,
+        move |(data, locals), i| {
+            let acc = locals.get_or_default();
+            task(data, i, &mut acc.borrow_mut());
+        },
+    );
+    // spawn_workers 已 join 了所有线程，它们持有的 Arc 都已释放
+    let locals = Arc::try_unwrap(locals).ok().expect("所有工作线程都已结束");
+    locals.into_iter().map(RefCell::into_inner).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawn_workers;
+    use std::cell::Cell;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn test_location_layout() {
+        assert_eq!(location(0), (0, 0));
+        assert_eq!(location(1), (1, 0));
+        assert_eq!(location(2), (1, 1));
+        assert_eq!(location(3), (2, 0));
+        assert_eq!(location(6), (2, 3));
+        assert_eq!(location(7), (3, 0));
+    }
+
+    #[test]
+    fn test_per_thread_values() {
+        let tls = ThreadLocal::new();
+        assert!(tls.get().is_none());
+        assert_eq!(*tls.get_or(|| 5), 5);
+        // 已经有值时不再调用 create
+        assert_eq!(*tls.get_or(|| unreachable!()), 5);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert!(tls.get().is_none());
+                tls.get_or(|| 7);
+            });
+        });
+        let mut values: Vec<i32> = tls.iter().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![5, 7]);
+    }
+
+    #[test]
+    fn test_accumulate_with_cell_and_reduce() {
+        // Cell 不是 Sync，但可以放进 ThreadLocal：每个 Cell 只被一个线程访问
+        let tls = Arc::new(ThreadLocal::<Cell<u64>>::new());
+        spawn_workers(Arc::clone(&tls), 8, |tls, i| {
+            for _ in 0..1_000 {
+                let c = tls.get_or_default();
+                c.set(c.get() + i as u64);
+            }
+        });
+        let tls = Arc::try_unwrap(tls).unwrap();
+        let total: u64 = tls.into_iter().map(Cell::into_inner).sum();
+        assert_eq!(total, 1_000 * (0..8).sum::<u64>());
+    }
+
+    #[test]
+    fn test_drop_and_clear_release_values() {
+        struct Tracked(Arc<AtomicUsize>);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut tls = ThreadLocal::new();
+        tls.get_or(|| Tracked(Arc::clone(&drops)));
+        // 线程编号会复用：让 4 个线程同时存活，保证它们各占一个槽位
+        let barrier = Barrier::new(4);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    tls.get_or(|| Tracked(Arc::clone(&drops)));
+                    barrier.wait();
+                });
+            }
+        });
+        assert_eq!(tls.iter_mut().count(), 5);
+        tls.clear();
+        assert_eq!(drops.load(Ordering::Relaxed), 5);
+        assert!(tls.get().is_none());
+
+        tls.get_or(|| Tracked(Arc::clone(&drops)));
+        drop(tls);
+        assert_eq!(drops.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn stress_spawn_workers_reduce() {
+        let result = spawn_workers_reduce(Arc::new(7u64), 16, |k, i, acc: &mut Vec<u64>| {
+            for n in 0..100 {
+                acc.push(*k * (i as u64 * 100 + n));
+            }
+        });
+        let mut all: Vec<u64> = result.into_iter().flatten().collect();
+        all.sort();
+        assert_eq!(all, (0..1_600).map(|n| n * 7).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,467 @@
+//! 基于分层时间轮的定时任务调度器
+//!
+//! 一个定时线程负责推进时间轮，到期的任务交给 [`ThreadPool`] 执行，
+//! 因此任务执行得再久也不会耽误其他任务的触发时间。
+//!
+//! - [`Timer::schedule_after`] / [`Timer::schedule_at`]：一次性任务
+//! - [`Timer::schedule_periodic`]：固定频率的周期任务
+//! - 所有调度方法都返回 [`TimerHandle`]，可以随时取消
+//!
+//! 时间精度为一个 tick（默认 1ms），任务不会早于指定时间执行，但可能晚一个 tick 左右。
+//!
+//! ```
+//! use learning_concurrency::timer::Timer;
+//! use std::sync::mpsc;
+//! use std::time::Duration;
+//!
+//! let timer = Timer::new(2);
+//! let (tx, rx) = mpsc::channel();
+//! timer.schedule_after(Duration::from_millis(20), move || tx.send("到点了").unwrap());
+//! let cancelled = timer.schedule_after(Duration::from_millis(10), || unreachable!());
+//! assert!(cancelled.cancel());
+//! assert_eq!(rx.recv().unwrap(), "到点了");
+//! ```
+
+mod wheel;
+
+use crate::pool::ThreadPool;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use wheel::Wheel;
+
+type TaskId = u64;
+
+enum Task {
+    Once(Box<dyn FnOnce() + Send + 'static>),
+    Periodic {
+        f: Arc<dyn Fn() + Send + Sync + 'static>,
+        period: u64,
+        deadline: u64,
+    },
+}
+
+struct State {
+    wheel: Wheel<TaskId>,
+    /// 取消只从这里删除，时间轮里残留的编号到期时查不到任务，直接忽略
+    tasks: HashMap<TaskId, Task>,
+    next_id: TaskId,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    cvar: Condvar,
+    start: Instant,
+    tick: Duration,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// 当前时刻对应的 tick（向下取整）
+    fn now_tick(&self) -> u64 {
+        (self.start.elapsed().as_nanos() / self.tick.as_nanos()) as u64
+    }
+
+    fn instant_for(&self, tick: u64) -> Instant {
+        let nanos = u128::from(tick) * self.tick.as_nanos();
+        self.start + Duration::from_nanos(nanos.min(u128::from(u64::MAX)) as u64)
+    }
+
+    /// `at` 对应的 tick（向上取整，保证不会提前触发）
+    fn tick_for(&self, at: Instant) -> u64 {
+        let nanos = at.saturating_duration_since(self.start).as_nanos();
+        nanos
+            .div_ceil(self.tick.as_nanos())
+            .min(u128::from(u64::MAX)) as u64
+    }
+
+    /// 从现在起 `delay` 之后对应的 tick。`Duration::MAX` 这类超出 `Instant` 表示范围的延迟
+    /// 直接取最大 tick，相当于永不触发
+    fn tick_after(&self, delay: Duration) -> u64 {
+        Instant::now()
+            .checked_add(delay)
+            .map_or(u64::MAX, |at| self.tick_for(at))
+    }
+}
+
+pub struct Timer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Timer {
+    /// 以 1ms 为 tick，到期任务在 `workers` 个线程的线程池中执行
+    pub fn new(workers: usize) -> Self {
+        Self::with_tick(Duration::from_millis(1), workers)
+    }
+
+    pub fn with_tick(tick: Duration, workers: usize) -> Self {
+        assert!(!tick.is_zero(), "tick 必须大于 0");
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                wheel: Wheel::new(),
+                tasks: HashMap::new(),
+                next_id: 0,
+                shutdown: false,
+            }),
+            cvar: Condvar::new(),
+            start: Instant::now(),
+            tick,
+        });
+        let pool = ThreadPool::named("timer-worker", workers);
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("timer".into())
+                .spawn(move || run(&shared, pool))
+                .expect("创建定时线程失败")
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// `delay` 之后执行一次 `f`
+    pub fn schedule_after<F>(&self, delay: Duration, f: F) -> TimerHandle
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let deadline = self.shared.tick_after(delay);
+        self.insert(deadline, Task::Once(Box::new(f)))
+    }
+
+    /// 在 `at` 时刻执行一次 `f`；`at` 已经过去时尽快执行
+    pub fn schedule_at<F>(&self, at: Instant, f: F) -> TimerHandle
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let deadline = self.shared.tick_for(at);
+        self.insert(deadline, Task::Once(Box::new(f)))
+    }
+
+    /// `initial_delay` 之后第一次执行，此后每隔 `period` 执行一次，直到被取消
+    ///
+    /// 按固定频率而不是固定间隔调度：下一次的时间从上一次的计划时间算起，不受执行耗时影响。
+    /// 定时线程落后超过一个周期时，错过的那几次直接跳过，不会补发。
+    pub fn schedule_periodic<F>(
+        &self,
+        initial_delay: Duration,
+        period: Duration,
+        f: F,
+    ) -> TimerHandle
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let period = period
+            .as_nanos()
+            .div_ceil(self.shared.tick.as_nanos())
+            .clamp(1, u128::from(u64::MAX)) as u64;
+        let deadline = self.shared.tick_after(initial_delay);
+        self.insert(
+            deadline,
+            Task::Periodic {
+                f: Arc::new(f),
+                period,
+                deadline,
+            },
+        )
+    }
+
+    fn insert(&self, deadline: u64, task: Task) -> TimerHandle {
+        let mut state = self.shared.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        // 已经到期的任务放在下一个 tick，统一由定时线程派发
+        let deadline = deadline.max(state.wheel.elapsed() + 1);
+        let earliest = state.wheel.next_expiration();
+        state.wheel.insert(deadline, id).ok();
+        state.tasks.insert(id, task);
+        // 只有新任务比原来最早的还早时，才需要叫醒定时线程重新计算睡眠时间
+        if earliest.is_none_or(|when| deadline < when) {
+            self.shared.cvar.notify_one();
+        }
+        TimerHandle {
+            id,
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    /// 尚未触发（以及周期任务尚未取消）的任务数
+    pub fn pending(&self) -> usize {
+        self.shared.lock().tasks.len()
+    }
+
+    /// 停止定时线程。尚未触发的任务被丢弃，已经交给线程池的任务会执行完。drop 时也会这样做
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.cvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("tick", &self.shared.tick)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+fn run(shared: &Shared, pool: ThreadPool) {
+    let mut expired = Vec::new();
+    let mut state = shared.lock();
+    while !state.shutdown {
+        let now = shared.now_tick();
+        state.wheel.advance(now, &mut expired);
+        for id in expired.drain(..) {
+            // 查不到说明已被取消
+            let Some(task) = state.tasks.remove(&id) else {
+                continue;
+            };
+            match task {
+                Task::Once(f) => pool.execute(f),
+                Task::Periodic {
+                    f,
+                    period,
+                    deadline,
+                } => {
+                    let job = Arc::clone(&f);
+                    pool.execute(move || job());
+                    // 跳过已经错过的周期
+                    let missed = (now - deadline) / period;
+                    let deadline = deadline.saturating_add((missed + 1).saturating_mul(period));
+                    state.wheel.insert(deadline, id).ok();
+                    state.tasks.insert(
+                        id,
+                        Task::Periodic {
+                            f,
+                            period,
+                            deadline,
+                        },
+                    );
+                }
+            }
+        }
+
+        state = match state.wheel.next_expiration() {
+            None => shared.cvar.wait(state).unwrap(),
+            Some(when) => {
+                let timeout = shared
+                    .instant_for(when)
+                    .saturating_duration_since(Instant::now());
+                shared.cvar.wait_timeout(state, timeout).unwrap().0
+            }
+        };
+    }
+    // 先放开状态锁再等线程池收尾：池里的任务可能还会调度或取消定时任务
+    drop(state);
+    pool.join();
+}
+
+/// 调度后返回的句柄，用于取消任务。丢弃句柄不会取消任务
+#[derive(Clone)]
+pub struct TimerHandle {
+    id: TaskId,
+    shared: Weak<Shared>,
+}
+
+impl TimerHandle {
+    /// 取消任务，返回是否真的阻止了一次执行：
+    /// 一次性任务已经触发、已被取消或定时器已关闭时返回 false。
+    /// 正在线程池中执行的那一次不受影响
+    pub fn cancel(&self) -> bool {
+        let Some(shared) = self.shared.upgrade() else {
+            return false;
+        };
+        let removed = shared.lock().tasks.remove(&self.id).is_some();
+        removed
+    }
+
+    /// 任务是否还会在将来执行
+    pub fn is_pending(&self) -> bool {
+        self.shared
+            .upgrade()
+            .is_some_and(|shared| shared.lock().tasks.contains_key(&self.id))
+    }
+}
+
+impl fmt::Debug for TimerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimerHandle")
+            .field("id", &self.id)
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_tasks_fire_in_deadline_order_and_not_early() {
+        let timer = Timer::new(1);
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        for ms in [30u64, 10, 20] {
+            let tx = tx.clone();
+            timer.schedule_after(Duration::from_millis(ms), move || {
+                tx.send((ms, start.elapsed())).unwrap();
+            });
+        }
+        let fired: Vec<_> = (0..3).map(|_| rx.recv().unwrap()).collect();
+        assert_eq!(
+            fired.iter().map(|&(ms, _)| ms).collect::<Vec<_>>(),
+            [10, 20, 30]
+        );
+        for (ms, elapsed) in fired {
+            assert!(elapsed >= Duration::from_millis(ms));
+        }
+        assert_eq!(timer.pending(), 0);
+    }
+
+    #[test]
+    fn test_schedule_at_in_the_past_runs_soon() {
+        let timer = Timer::new(1);
+        let (tx, rx) = mpsc::channel();
+        let past = Instant::now() - Duration::from_secs(1);
+        timer.schedule_at(past, move || tx.send(()).unwrap());
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let timer = Timer::new(1);
+        let fired = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let fired = Arc::clone(&fired);
+            timer.schedule_after(Duration::from_millis(20), move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        assert!(handle.is_pending());
+        assert!(handle.cancel());
+        assert!(!handle.cancel());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+
+        // 一次性任务触发之后再取消返回 false
+        let (tx, rx) = mpsc::channel();
+        let handle = timer.schedule_after(Duration::ZERO, move || tx.send(()).unwrap());
+        rx.recv().unwrap();
+        assert!(!handle.cancel());
+    }
+
+    #[test]
+    fn test_huge_durations_do_not_panic() {
+        let timer = Timer::new(1);
+        let once = timer.schedule_after(Duration::MAX, || unreachable!());
+        let periodic = timer.schedule_periodic(Duration::MAX, Duration::MAX, || unreachable!());
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let soon = timer.schedule_periodic(Duration::ZERO, Duration::MAX, move || {
+            tx.lock().unwrap().send(()).unwrap();
+        });
+        // 第一次照常执行，下一次远在天边
+        rx.recv().unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(20)).is_err());
+        assert!(once.is_pending() && periodic.is_pending() && soon.is_pending());
+        assert_eq!(timer.pending(), 3);
+    }
+
+    #[test]
+    fn test_periodic_until_cancelled() {
+        let timer = Timer::new(2);
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let start = Instant::now();
+        let handle = timer.schedule_periodic(Duration::ZERO, Duration::from_millis(5), move || {
+            let _ = tx.lock().unwrap().send(Instant::now());
+        });
+        let ticks: Vec<Instant> = (0..5).map(|_| rx.recv().unwrap()).collect();
+        // 第 k 次最早在 start + k * period 执行（错过的周期会被跳过，只会更晚）
+        for (k, tick) in ticks.iter().enumerate() {
+            assert!(tick.duration_since(start) >= Duration::from_millis(5 * k as u64));
+        }
+        assert!(handle.cancel());
+        assert!(!handle.is_pending());
+        // 取消之后最多还有取消前已经派发的那一次
+        thread::sleep(Duration::from_millis(30));
+        let extra = rx.try_iter().count();
+        assert!(extra <= 1, "取消后又执行了 {} 次", extra);
+    }
+
+    #[test]
+    fn test_shutdown_drops_pending_tasks() {
+        let timer = Timer::new(1);
+        let fired = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let fired = Arc::clone(&fired);
+            timer.schedule_after(Duration::from_secs(60), move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        timer.shutdown();
+        assert!(!handle.cancel());
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn stress_many_timers_from_many_threads() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 200;
+        let timer = Arc::new(Timer::new(4));
+        let fired = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let timer = Arc::clone(&timer);
+                let fired = Arc::clone(&fired);
+                thread::spawn(move || {
+                    let mut cancelled = 0;
+                    for i in 0..PER_THREAD {
+                        let fired = Arc::clone(&fired);
+                        let delay = Duration::from_millis(((t * 31 + i * 17) % 50) as u64);
+                        let handle = timer.schedule_after(delay, move || {
+                            fired.fetch_add(1, Ordering::SeqCst);
+                        });
+                        if i % 5 == 0 && handle.cancel() {
+                            cancelled += 1;
+                        }
+                    }
+                    cancelled
+                })
+            })
+            .collect();
+        let cancelled: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        let expected = THREADS * PER_THREAD - cancelled;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while fired.load(Ordering::SeqCst) < expected && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(fired.load(Ordering::SeqCst), expected);
+        assert_eq!(timer.pending(), 0);
+    }
+}
@@ -0,0 +1,189 @@
+//! 分层时间轮（与时钟无关，只认 tick 编号，方便单独测试）
+//!
+//! 共 [`LEVELS`] 层，每层 64 个槽：第 0 层每槽 1 tick，第 1 层每槽 64 tick，依此类推。
+//! 定时项按"到期 tick 与当前 tick 最高的不同位"放进对应层：离得越远放得越高、粒度越粗。
+//! 时间推进到高层某个槽的起点时，把槽里的项重新插入，它们会自然落到更低的层，
+//! 最终在第 0 层精确到期。插入、取消（由调用方惰性处理）都是 O(1)，
+//! 推进时直接跳到下一个非空槽，空闲时不需要逐 tick 空转。
+
+use std::mem;
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+pub(crate) const LEVELS: usize = 6;
+
+/// 能表示的最大延迟。顶层离当前槽最多 63 个槽，保证顶层的槽不会绕一整圈回到当前位置
+pub(crate) const MAX_DELAY_TICKS: u64 = ((SLOTS as u64) - 1) << (SLOT_BITS * (LEVELS as u32 - 1));
+
+struct Entry<T> {
+    /// 真实的到期 tick，可能比所在槽能表示的范围更远
+    deadline: u64,
+    item: T,
+}
+
+struct Level<T> {
+    slots: [Vec<Entry<T>>; SLOTS],
+    /// 第 i 位表示第 i 个槽非空，用来快速找下一个要处理的槽
+    occupied: u64,
+}
+
+impl<T> Level<T> {
+    fn new() -> Self {
+        Self {
+            slots: std::array::from_fn(|_| Vec::new()),
+            occupied: 0,
+        }
+    }
+}
+
+pub(crate) struct Wheel<T> {
+    elapsed: u64,
+    levels: Vec<Level<T>>,
+}
+
+impl<T> Wheel<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            elapsed: 0,
+            levels: (0..LEVELS).map(|_| Level::new()).collect(),
+        }
+    }
+
+    /// 当前 tick
+    pub(crate) fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+
+    /// 插入定时项。已经到期（`deadline <= elapsed`）时原样返回，由调用方立即处理；
+    /// 超过 [`MAX_DELAY_TICKS`] 的延迟先放在最远的槽里，到那时还没到期就再插入一次，
+    /// 所以不会提前触发
+    pub(crate) fn insert(&mut self, deadline: u64, item: T) -> Result<(), T> {
+        if deadline <= self.elapsed {
+            return Err(item);
+        }
+        let slot_deadline = deadline.min(self.elapsed.saturating_add(MAX_DELAY_TICKS));
+        let (level, slot) = self.position(slot_deadline);
+        let level = &mut self.levels[level];
+        level.slots[slot].push(Entry { deadline, item });
+        level.occupied |= 1 << slot;
+        Ok(())
+    }
+
+    fn position(&self, deadline: u64) -> (usize, usize) {
+        // 低 SLOT_BITS 位强制置 1：即使只差几个 tick 也算第 0 层
+        let masked = (self.elapsed ^ deadline) | (SLOTS as u64 - 1);
+        let significant = 63 - masked.leading_zeros();
+        let level = ((significant / SLOT_BITS) as usize).min(LEVELS - 1);
+        let slot = (deadline >> (level as u32 * SLOT_BITS)) as usize & (SLOTS - 1);
+        (level, slot)
+    }
+
+    /// 下一个需要处理的槽的起始 tick（可能早于其中定时项的实际到期时间）
+    pub(crate) fn next_expiration(&self) -> Option<u64> {
+        self.next_slot().map(|(_, _, when)| when)
+    }
+
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        let mut best: Option<(usize, usize, u64)> = None;
+        for (index, level) in self.levels.iter().enumerate() {
+            if level.occupied == 0 {
+                continue;
+            }
+            let shift = index as u32 * SLOT_BITS;
+            let current = self.elapsed >> shift;
+            let now_slot = current as usize & (SLOTS - 1);
+            // 从当前槽开始向后数第几个槽非空（自然处理了环绕）
+            let distance = level
+                .occupied
+                .rotate_right(now_slot as u32)
+                .trailing_zeros() as u64;
+            let slot = (now_slot + distance as usize) & (SLOTS - 1);
+            let when = ((current + distance) << shift).max(self.elapsed);
+            if best.is_none_or(|(_, _, w)| when < w) {
+                best = Some((index, slot, when));
+            }
+        }
+        best
+    }
+
+    /// 把时间推进到 `now`，所有 `deadline <= now` 的项按到期顺序放入 `expired`
+    pub(crate) fn advance(&mut self, now: u64, expired: &mut Vec<T>) {
+        while let Some((level, slot, when)) = self.next_slot() {
+            if when > now {
+                break;
+            }
+            self.elapsed = when;
+            let entries = mem::take(&mut self.levels[level].slots[slot]);
+            self.levels[level].occupied &= !(1 << slot);
+            for entry in entries {
+                if entry.deadline <= self.elapsed {
+                    expired.push(entry.item);
+                } else {
+                    // 槽的起点已到、项还没到期：重新插入后会落到更低的层
+                    let _ = self.insert(entry.deadline, entry.item);
+                }
+            }
+        }
+        self.elapsed = self.elapsed.max(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(wheel: &mut Wheel<u64>, now: u64) -> Vec<u64> {
+        let mut out = Vec::new();
+        wheel.advance(now, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_fires_exactly_at_deadline_across_levels() {
+        let mut wheel = Wheel::new();
+        let deadlines = [1, 63, 64, 65, 4095, 4096, 300_000, 20_000_000];
+        for &d in &deadlines {
+            wheel.insert(d, d).unwrap();
+        }
+        for &d in &deadlines {
+            // 到期前一个 tick 不会触发，到期那个 tick 恰好触发
+            assert_eq!(drain(&mut wheel, d - 1), Vec::<u64>::new());
+            assert_eq!(drain(&mut wheel, d), vec![d]);
+        }
+        assert_eq!(wheel.next_expiration(), None);
+    }
+
+    #[test]
+    fn test_expired_in_order_after_long_jump() {
+        let mut wheel = Wheel::new();
+        wheel.advance(1_000, &mut Vec::new());
+        let mut deadlines: Vec<u64> = (0..500).map(|i| 1_001 + (i * 7_919) % 100_000).collect();
+        for &d in &deadlines {
+            wheel.insert(d, d).unwrap();
+        }
+        assert_eq!(wheel.insert(1_000, 0), Err(0));
+
+        let fired = drain(&mut wheel, 200_000);
+        deadlines.sort_unstable();
+        assert_eq!(fired, deadlines);
+        assert_eq!(wheel.elapsed(), 200_000);
+    }
+
+    #[test]
+    fn test_beyond_max_delay_never_fires_early() {
+        let mut wheel = Wheel::new();
+        wheel.advance((1 << 36) - 3, &mut Vec::new());
+        let start = wheel.elapsed();
+        let far = start + 3 * MAX_DELAY_TICKS + 5;
+        wheel.insert(u64::MAX, u64::MAX).unwrap();
+        wheel.insert(far, far).unwrap();
+        wheel.insert(start + MAX_DELAY_TICKS, 2).unwrap();
+        assert!(drain(&mut wheel, start + MAX_DELAY_TICKS - 1).is_empty());
+        assert_eq!(drain(&mut wheel, start + MAX_DELAY_TICKS), vec![2]);
+        // 超出范围的项每到最远的槽就重新插入一次，直到真正到期
+        assert!(drain(&mut wheel, far - 1).is_empty());
+        assert_eq!(drain(&mut wheel, far), vec![far]);
+        assert!(drain(&mut wheel, far + 10 * MAX_DELAY_TICKS).is_empty());
+        assert!(wheel.next_expiration().is_some());
+    }
+}
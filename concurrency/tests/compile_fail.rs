@@ -0,0 +1,12 @@
+//! 编译失败用例：证明不满足 Send / Sync 的类型会在编译期被拒绝
+//!
+//! 每个 `tests/compile_fail/*.rs` 都必须编译失败，且错误信息与同名的 `.stderr` 一致。
+//! 升级 rustc 导致提示文字变化时，用 `TRYBUILD=overwrite cargo test --test compile_fail` 重新生成。
+
+#[test]
+// 开启 model-check 后错误信息里出现的是 loom 的类型，与记录的 .stderr 对不上
+#[cfg_attr(feature = "model-check", ignore = "错误信息按 std 类型记录")]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/compile_fail/*.rs");
+}
@@ -0,0 +1,11 @@
+// Cell 是 Send 但不是 Sync：多个 MyArc 克隆会让两个线程同时修改同一个 Cell
+use learning_concurrency::arc::MyArc;
+use std::cell::Cell;
+use std::thread;
+
+fn main() {
+    let shared = MyArc::new(Cell::new(0));
+    let other = shared.clone();
+    thread::spawn(move || other.set(1));
+    shared.set(2);
+}
@@ -0,0 +1,11 @@
+// MyArc 只是让指针本身可以跨线程，Rc 的非原子计数仍然不安全
+use learning_concurrency::arc::MyArc;
+use std::rc::Rc;
+use std::thread;
+
+fn main() {
+    let shared = MyArc::new(Rc::new(5));
+    thread::spawn(move || {
+        let _ = **shared;
+    });
+}
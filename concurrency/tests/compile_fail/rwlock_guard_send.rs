@@ -0,0 +1,12 @@
+// 读写锁的守卫不能被移到其他线程：解锁必须发生在加锁的线程上，
+// 否则锁顺序跟踪会弹出另一个线程的持有记录
+use learning_concurrency::rwlock::RwLock;
+use std::thread;
+
+fn main() {
+    let lock = RwLock::new(0);
+    let guard = lock.read();
+    thread::scope(|s| {
+        s.spawn(move || drop(guard));
+    });
+}
@@ -0,0 +1,8 @@
+// 被替换下来的版本由纪元回收在之后任意时刻释放，不能借用可能先失效的数据
+use learning_concurrency::snapshot_cell::SnapshotCell;
+
+fn main() {
+    let name = String::from("v1");
+    let cell = SnapshotCell::new(name.as_str());
+    cell.store("v2");
+}
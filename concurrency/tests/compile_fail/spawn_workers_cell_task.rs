@@ -0,0 +1,12 @@
+// 任务闭包会被所有工作线程共享，捕获了 Cell（非 Sync）的闭包必须被拒绝
+use learning_concurrency::spawn_workers;
+use std::cell::Cell;
+use std::sync::Arc;
+
+fn main() {
+    let hits = Cell::new(0);
+    let hits = Arc::new(hits);
+    spawn_workers((), 2, move |_, _| {
+        hits.set(hits.get() + 1);
+    });
+}
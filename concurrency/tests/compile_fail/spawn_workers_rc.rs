@@ -0,0 +1,9 @@
+// Rc 的引用计数不是原子的，不能交给 spawn_workers 分发到多个线程
+use learning_concurrency::spawn_workers;
+use std::rc::Rc;
+
+fn main() {
+    spawn_workers(Rc::new(5), 2, |data, _| {
+        let _ = *data;
+    });
+}
@@ -0,0 +1,13 @@
+// 守卫不能被多个线程共享：否则两个线程可以同时通过 &Cell 修改受保护的数据
+use learning_concurrency::spinlock::SpinLock;
+use std::cell::Cell;
+use std::thread;
+
+fn main() {
+    let lock = SpinLock::new(Cell::new(0));
+    let guard = lock.lock();
+    thread::scope(|s| {
+        s.spawn(|| guard.set(1));
+        guard.set(2);
+    });
+}
@@ -0,0 +1,13 @@
+// SpinLock 要求 T: Send 才是 Sync，锁住 Rc 并不能让它跨线程共享
+use learning_concurrency::spinlock::SpinLock;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+
+fn main() {
+    let lock = Arc::new(SpinLock::new(Rc::new(5)));
+    let other = Arc::clone(&lock);
+    thread::spawn(move || {
+        let _ = **other.lock();
+    });
+}
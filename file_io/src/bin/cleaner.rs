@@ -1,127 +1,437 @@
-use std::fs;
-use std::path::Path;
-use walkdir::WalkDir;
-
-fn main() {
-    // 实际运行时，你可以传入命令行参数，这里为了演示直接写当前目录
-    let path = Path::new(".");
-    if let Err(e) = clean_empty_directories(path) {
-        eprintln!("Error: {}", e);
+//! 递归清理空目录的命令行工具
+//!
+//! ```text
+//! cleaner [选项] <目录>...
+//! cleaner restore <撤销清单>...
+//! ```
+//!
+//! 每次真正动手的清理都会写一份撤销清单，`restore` 按清单把删掉的目录和移走的文件恢复回来。
+//!
+//! 退出码：0 表示成功；1 表示过程中遇到过错误（其余目录照常处理），或有条目没能恢复；2 表示参数错误。
+
+use learning_file_io::cleaner::{
+    clean_empty_directories_with, CleanOptions, ConsoleObserver, Verbosity,
+};
+use learning_file_io::cli::{Arg, ArgParser};
+use learning_file_io::glob::Pattern;
+use learning_file_io::junk::JunkRules;
+use learning_file_io::report::{CleanReport, CleanSummary};
+use learning_file_io::trash::Disposal;
+use learning_file_io::undo::{restore, UndoManifest};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+用法: cleaner [选项] <目录>...
+      cleaner restore <撤销清单>...
+
+递归删除给定目录下的空目录（先子后父，子目录删光后父目录也会被删除）。
+
+选项:
+  -n, --dry-run          只列出将会删除的目录，不真正删除
+      --max-depth <N>    最多向下遍历 N 层（根目录为第 0 层）
+      --exclude <GLOB>   跳过匹配的目录及其子树，可重复使用；
+                         不含 / 的模式匹配名字，含 / 的匹配相对根目录的路径
+      --keep-root        保留根目录本身（默认）
+      --include-root     根目录为空时也删除
+  -L, --follow-symlinks  跟随符号链接（链接本身不会被删除）
+
+删除方式（默认直接删除）:
+      --quarantine <DIR>   移进 DIR 下以本次运行命名的子目录，保留原来的完整路径
+      --trash              移进当前用户的回收站（freedesktop.org 规范）
+      --manifest <FILE>    撤销清单写到 FILE；默认写到 $XDG_STATE_HOME/cleaner/ 下
+                           （没有设置时为 ~/.local/state/cleaner/）。dry-run 不写清单
+
+垃圾文件（只含垃圾文件的目录视同空目录，垃圾文件随目录一起删除）:
+      --junk <GLOB>        文件名匹配的文件算作垃圾，可重复使用
+      --junk-common        常见的系统垃圾文件: .DS_Store ._* Thumbs.db desktop.ini
+      --junk-empty-files   大小为 0 的文件算作垃圾
+      --junk-min-age <AGE> 只有修改时间早于 AGE 之前的文件才算垃圾，如 90s、30m、12h、7d
+
+输出:
+  -q, --quiet            只输出错误
+  -v, --verbose          同时输出保留和跳过的目录
+      --json             结束后把每个目录的完整报告以 JSON 数组输出到 stdout（不再输出逐条结果）
+  -h, --help             显示本帮助";
+
+#[derive(Debug)]
+enum Command {
+    Help,
+    Clean {
+        roots: Vec<PathBuf>,
+        options: CleanOptions,
+        verbosity: Verbosity,
+        json: bool,
+        manifest: Option<PathBuf>,
+    },
+    Restore {
+        manifests: Vec<PathBuf>,
+    },
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = CleanOptions::default();
+    let mut roots = Vec::new();
+    let mut verbosity = Verbosity::Normal;
+    let mut json = false;
+    let mut manifest = None;
+    let mut args = args.into_iter().peekable();
+
+    if args.peek().map(String::as_str) == Some("restore") {
+        args.next();
+        let mut manifests = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                _ if arg.starts_with('-') => return Err(format!("restore 不接受选项: {}", arg)),
+                _ => manifests.push(PathBuf::from(arg)),
+            }
+        }
+        if manifests.is_empty() {
+            return Err("restore 至少需要指定一份撤销清单".to_string());
+        }
+        return Ok(Command::Restore { manifests });
     }
+
+    let mut args = ArgParser::new(args);
+    while let Some(arg) = args.next_arg()? {
+        let name = match arg {
+            Arg::Positional(path) => {
+                roots.push(PathBuf::from(path));
+                continue;
+            }
+            Arg::Option(name) => name,
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-n" | "--dry-run" => options.dry_run = true,
+            "--max-depth" => options.max_depth = Some(args.parse()?),
+            "--exclude" => {
+                let v = args.value()?;
+                let pattern =
+                    Pattern::new(&v).map_err(|e| format!("--exclude 模式 {:?} 无效: {}", v, e))?;
+                options.exclude.push(pattern);
+            }
+            "--junk" => {
+                let v = args.value()?;
+                let pattern =
+                    Pattern::new(&v).map_err(|e| format!("--junk 模式 {:?} 无效: {}", v, e))?;
+                options.junk.names.push(pattern);
+            }
+            "--junk-common" => options.junk.names.extend(JunkRules::common().names),
+            "--junk-empty-files" => options.junk.zero_size = true,
+            "--junk-min-age" => {
+                let v = args.value()?;
+                let age = parse_age(&v)
+                    .map_err(|e| format!("--junk-min-age 的取值 {:?} 无效: {}", v, e))?;
+                options.junk.min_age = Some(age);
+            }
+            "--quarantine" => options.disposal = Disposal::Quarantine(PathBuf::from(args.value()?)),
+            "--trash" => options.disposal = Disposal::Trash,
+            "--manifest" => manifest = Some(PathBuf::from(args.value()?)),
+            "--keep-root" => options.include_root = false,
+            "--include-root" => options.include_root = true,
+            "-L" | "--follow-symlinks" => options.follow_symlinks = true,
+            "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+            "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+            "--json" => json = true,
+            _ => return Err(format!("未知选项: {}", name)),
+        }
+    }
+
+    if roots.is_empty() {
+        return Err("至少需要指定一个目录".to_string());
+    }
+    Ok(Command::Clean {
+        roots,
+        options,
+        verbosity,
+        json,
+        manifest,
+    })
+}
+
+/// 解析 `90`、`90s`、`30m`、`12h`、`7d` 这样的时长，不带单位时按秒计
+fn parse_age(text: &str) -> Result<Duration, String> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let number: u64 = number.parse().map_err(|e| format!("{}", e))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("未知的时间单位 {:?}", unit)),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| "时长过大".to_string())
 }
 
-/// 核心业务逻辑：递归清理空目录
-/// 参数：target_root - 要清理的目标根目录
-pub fn clean_empty_directories(target_root: &Path) -> std::io::Result<()> {
-    // contents_first(true) 是关键：后序遍历（先子后父）
-    let walker = WalkDir::new(target_root).contents_first(true);
-
-    for entry in walker.into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-
-        // 1. 必须是目录
-        // 2. 不能是根目录自己（通常我们不想删掉用户指定的那个顶层文件夹，防止误删挂载点）
-        if path.is_dir() && path != target_root {
-            match fs::remove_dir(path) {
-                Ok(_) => println!("[Deleted] {:?}", path),
-                Err(e) => {
-                    // 忽略“非空”错误，这是预期行为
-                    if !is_dir_not_empty_error(&e) {
-                        eprintln!("[Error] Cannot delete {:?}: {}", path, e);
-                    }
-                }
+/// `$XDG_STATE_HOME/cleaner/undo-<时间戳>-<pid>.json`，两个变量都没有时放在当前目录
+fn default_manifest_path() -> PathBuf {
+    let dir = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+        .map_or_else(PathBuf::new, |state| state.join("cleaner"));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    dir.join(format!("undo-{}-{}.json", now, process::id()))
+}
+
+fn print_summary(summary: &CleanSummary, options: &CleanOptions) {
+    let deleted = if options.dry_run {
+        "将删除"
+    } else {
+        "已删除"
+    };
+    println!(
+        "\n扫描目录 {}，{} {} 个目录和 {} 个垃圾文件，保留 {}，排除 {}，错误 {}{}",
+        summary.scanned,
+        deleted,
+        summary.deleted,
+        summary.junk_files,
+        summary.kept,
+        summary.excluded,
+        summary.errors,
+        if options.dry_run {
+            "（dry-run，未做任何修改）"
+        } else {
+            ""
+        }
+    );
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Clean {
+            roots,
+            options,
+            verbosity,
+            json,
+            manifest,
+        }) => run_clean(&roots, &options, verbosity, json, manifest),
+        Ok(Command::Restore { manifests }) => run_restore(&manifests),
+        Err(e) => {
+            eprintln!("错误: {}\n\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_clean(
+    roots: &[PathBuf],
+    options: &CleanOptions,
+    verbosity: Verbosity,
+    json: bool,
+    manifest: Option<PathBuf>,
+) -> ExitCode {
+    // JSON 模式下 stdout 只留给报告，逐条结果不再打印，错误照常写到 stderr
+    let mut observer = ConsoleObserver::new(if json { Verbosity::Quiet } else { verbosity });
+    let mut reports: Vec<CleanReport> = Vec::new();
+    let mut total = CleanSummary::default();
+    for root in roots {
+        match clean_empty_directories_with(root, options, &mut observer) {
+            Ok(report) => {
+                total += report.summary();
+                reports.push(report);
+            }
+            Err(e) => {
+                total.errors += 1;
+                eprintln!("[Error] 无法清理 {:?}: {}", root, e);
+            }
+        }
+    }
+
+    // 即使什么都没删也写一份，每次运行都有据可查
+    if !options.dry_run {
+        let path = manifest.unwrap_or_else(default_manifest_path);
+        let entries = reports
+            .iter()
+            .flat_map(|r| r.undo.iter().cloned())
+            .collect();
+        match UndoManifest::new(entries).save(&path) {
+            Ok(()) if !json && verbosity != Verbosity::Quiet => {
+                println!("撤销清单: {}（用 cleaner restore 恢复）", path.display())
+            }
+            Ok(()) => {}
+            Err(e) => {
+                total.errors += 1;
+                eprintln!("[Error] 无法写入撤销清单 {:?}: {}", path, e);
             }
         }
     }
-    Ok(())
+
+    if json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("[Error] 无法序列化报告: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else if verbosity != Verbosity::Quiet {
+        print_summary(&total, options);
+    }
+    if total.errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
-/// 辅助函数：判断错误是否为“文件夹非空”
-fn is_dir_not_empty_error(e: &std::io::Error) -> bool {
-    // Windows: 145, Unix-like: DirectoryNotEmpty
-    e.kind() == std::io::ErrorKind::DirectoryNotEmpty
-        || (cfg!(windows) && e.raw_os_error() == Some(145))
+fn run_restore(manifests: &[PathBuf]) -> ExitCode {
+    let mut complete = true;
+    for path in manifests {
+        let manifest = match UndoManifest::load(path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                complete = false;
+                eprintln!("[Error] 无法读取撤销清单 {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let report = restore(&manifest);
+        for path in &report.restored {
+            println!("[Restored] {:?}", path);
+        }
+        for path in &report.unrecoverable {
+            println!("[Unrecoverable] {:?} (已被直接删除)", path);
+        }
+        for path in &report.conflicts {
+            println!("[Conflict] {:?} (原位置已存在，未覆盖)", path);
+        }
+        for error in &report.errors {
+            eprintln!("[Error] {:?}: {}", error.path, error.message);
+        }
+        complete &= report.is_complete();
+    }
+    if complete {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::{self, File};
-    use std::io::Write;
-
-    // 一个辅助函数，用来创建测试环境
-    fn setup_test_environment(root: &str) {
-        // 结构设计：
-        // root/
-        // ├── empty_chain_1/     (空)
-        // │   └── empty_chain_2/ (空) -> 应该被连坐删除
-        // ├── keep_me/
-        // │   └── data.txt       (文件) -> 应该保留 keep_me
-        // └── mixed/
-        //     ├── trash/         (空)   -> 应该被删除
-        //     └── treasure.txt   (文件) -> 应该导致 mixed 被保留
-
-        let root_path = Path::new(root);
-        if root_path.exists() {
-            fs::remove_dir_all(root_path).unwrap(); // 清理旧环境
-        }
-        fs::create_dir_all(root_path.join("empty_chain_1/empty_chain_2")).unwrap();
-        fs::create_dir_all(root_path.join("keep_me")).unwrap();
-        fs::create_dir_all(root_path.join("mixed/trash")).unwrap();
-
-        // 创建文件
-        let mut f1 = File::create(root_path.join("keep_me/data.txt")).unwrap();
-        f1.write_all(b"content").unwrap();
+    use learning_file_io::junk::COMMON_JUNK_NAMES;
 
-        let mut f2 = File::create(root_path.join("mixed/treasure.txt")).unwrap();
-        f2.write_all(b"gold").unwrap();
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
     }
 
     #[test]
-    fn test_clean_empty_dirs_logic() {
-        let test_root = "test_env_temp";
-        setup_test_environment(test_root);
-        let root_path = Path::new(test_root);
-
-        // --- 执行前断言 ---
-        assert!(
-            root_path.join("empty_chain_1/empty_chain_2").exists(),
-            "Setup 失败"
-        );
-        assert!(root_path.join("mixed/trash").exists(), "Setup 失败");
-
-        // --- 执行逻辑 ---
-        clean_empty_directories(root_path).expect("执行失败");
+    fn test_parse_options() {
+        let Command::Clean {
+            roots,
+            options,
+            verbosity,
+            json,
+            ..
+        } = parse(&[
+            "-n",
+            "a",
+            "--max-depth=2",
+            "--exclude",
+            "*.git",
+            "--include-root",
+            "-L",
+            "-v",
+            "--json",
+            "--",
+            "-b",
+        ])
+        .unwrap()
+        else {
+            panic!("应当解析为 Clean");
+        };
+        assert_eq!(roots, [PathBuf::from("a"), PathBuf::from("-b")]);
+        assert!(options.dry_run && options.include_root && options.follow_symlinks);
+        assert_eq!(options.max_depth, Some(2));
+        assert_eq!(options.exclude.len(), 1);
+        assert_eq!(verbosity, Verbosity::Verbose);
+        assert!(json);
+    }
 
-        // --- 执行后验证 (Assert) ---
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(parse(&["-h"]), Ok(Command::Help)));
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--max-depth"]).is_err());
+        assert!(parse(&["--max-depth", "x", "."]).is_err());
+        assert!(parse(&["--exclude", "[a", "."]).is_err());
+        assert!(parse(&["--dry-run=yes", "."]).is_err());
+        assert!(parse(&["--bogus", "."]).is_err());
+        assert!(parse(&["--junk-min-age", "3w", "."]).is_err());
+        assert!(parse(&["restore"]).is_err());
+        assert!(parse(&["restore", "-n", "undo.json"]).is_err());
+    }
 
-        // 1. 验证连锁删除：最底层的空文件夹没了
-        assert!(
-            !root_path.join("empty_chain_1/empty_chain_2").exists(),
-            "底层空文件夹未删除"
-        );
-        // 2. 验证连锁删除：父级变空后也该没了 (后序遍历的威力)
-        assert!(
-            !root_path.join("empty_chain_1").exists(),
-            "父级空文件夹未被连锁删除"
+    #[test]
+    fn test_parse_disposal_and_restore() {
+        let Command::Clean {
+            options, manifest, ..
+        } = parse(&["--quarantine=/tmp/q", "--manifest", "undo.json", "."]).unwrap()
+        else {
+            panic!("应当解析为 Clean");
+        };
+        assert_eq!(
+            options.disposal,
+            Disposal::Quarantine(PathBuf::from("/tmp/q"))
         );
+        assert_eq!(manifest, Some(PathBuf::from("undo.json")));
 
-        // 3. 验证非空保护：有文件的文件夹还在
-        assert!(root_path.join("keep_me").exists(), "包含文件的文件夹误删");
-        assert!(root_path.join("keep_me/data.txt").exists(), "文件误删");
+        let Command::Clean { options, .. } = parse(&["--trash", "."]).unwrap() else {
+            panic!("应当解析为 Clean");
+        };
+        assert_eq!(options.disposal, Disposal::Trash);
 
-        // 4. 验证混合情况：删了空的子目录，但保留了有内容的父目录
-        assert!(
-            !root_path.join("mixed/trash").exists(),
-            "混合目录下的空文件夹未删除"
-        );
-        assert!(root_path.join("mixed").exists(), "混合目录被误删");
-        assert!(
-            root_path.join("mixed/treasure.txt").exists(),
-            "混合目录下的文件被误删"
+        // 目录名恰好叫 restore 时用 `--` 隔开
+        assert!(matches!(
+            parse(&["--", "restore"]),
+            Ok(Command::Clean { .. })
+        ));
+        let Command::Restore { manifests } = parse(&["restore", "a.json", "b.json"]).unwrap()
+        else {
+            panic!("应当解析为 Restore");
+        };
+        assert_eq!(
+            manifests,
+            [PathBuf::from("a.json"), PathBuf::from("b.json")]
         );
+    }
 
-        // 清理测试现场
-        fs::remove_dir_all(root_path).unwrap();
+    #[test]
+    fn test_parse_junk_rules() {
+        let Command::Clean { options, .. } = parse(&[
+            "--junk-common",
+            "--junk=*.tmp",
+            "--junk-empty-files",
+            "--junk-min-age",
+            "2h",
+            ".",
+        ])
+        .unwrap() else {
+            panic!("应当解析为 Clean");
+        };
+        assert_eq!(options.junk.names.len(), COMMON_JUNK_NAMES.len() + 1);
+        assert!(options.junk.zero_size);
+        assert_eq!(options.junk.min_age, Some(Duration::from_secs(7200)));
+        assert_eq!(parse_age("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert!(parse_age("h").is_err());
     }
 }
@@ -0,0 +1,361 @@
+//! 统计磁盘占用的命令行工具（类似 `du`）
+//!
+//! ```text
+//! disk_usage [选项] <目录>...
+//! ```
+//!
+//! 退出码：0 表示成功；1 表示过程中遇到过错误（其余条目照常统计）；2 表示参数错误。
+
+use learning_file_io::cli::{Arg, ArgParser};
+use learning_file_io::error::FsError;
+use learning_file_io::report::human_size;
+use learning_file_io::usage::{
+    disk_usage, DirUsage, FileUsage, SizeKind, UsageOptions, UsageReport,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+用法: disk_usage [选项] <目录>...
+
+统计目录树的磁盘占用。硬链接只计一次，默认不进入其他文件系统。
+
+选项:
+      --apparent             按文件长度统计和排序，而不是实际占用的磁盘空间
+      --cross-file-systems   进入挂载在目录树里的其他文件系统
+  -L, --follow-symlinks      跟随符号链接
+  -j, --threads <N>          遍历线程数（默认为 CPU 核数）
+
+输出（默认为树形视图）:
+  -d, --depth <N>            树形视图最多显示 N 层（默认 2，根目录为第 0 层）
+      --top <N>              列出最大的 N 个目录和 N 个文件（默认 10，0 表示不列）
+      --json                 以 JSON 输出完整报告
+      --csv                  以 CSV 输出每个目录和最大的 N 个文件各一行，方便导入表格
+  -h, --help                 显示本帮助";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tree { depth: usize },
+    Json,
+    Csv,
+}
+
+#[derive(Debug)]
+enum Command {
+    Help,
+    Usage {
+        roots: Vec<PathBuf>,
+        options: UsageOptions,
+        format: Format,
+        top: usize,
+    },
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = UsageOptions::default();
+    let mut roots = Vec::new();
+    let mut depth = 2;
+    let mut top = 10;
+    let mut format = None;
+    let mut args = ArgParser::new(args);
+
+    while let Some(arg) = args.next_arg()? {
+        let name = match arg {
+            Arg::Positional(path) => {
+                roots.push(PathBuf::from(path));
+                continue;
+            }
+            Arg::Option(name) => name,
+        };
+        let mut set_format = |new| match format {
+            Some(old) if old != new => Err("--json 和 --csv 只能选一个".to_string()),
+            _ => {
+                format = Some(new);
+                Ok(())
+            }
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--apparent" => options.size_kind = SizeKind::Apparent,
+            "--cross-file-systems" => options.cross_file_systems = true,
+            "-L" | "--follow-symlinks" => options.follow_symlinks = true,
+            "-j" | "--threads" => options.threads = args.parse()?,
+            "-d" | "--depth" => depth = args.parse()?,
+            "--top" => top = args.parse()?,
+            "--json" => set_format(Format::Json)?,
+            "--csv" => set_format(Format::Csv)?,
+            _ => return Err(format!("未知选项: {}", name)),
+        }
+    }
+
+    if roots.is_empty() {
+        return Err("至少需要指定一个目录".to_string());
+    }
+    options.largest_files = top;
+    Ok(Command::Usage {
+        roots,
+        options,
+        format: format.unwrap_or(Format::Tree { depth }),
+        top,
+    })
+}
+
+fn print_errors(errors: &[FsError]) {
+    for error in errors {
+        match &error.path {
+            Some(path) => eprintln!("[Error] {:?}: {}", path, error.message),
+            None => eprintln!("[Error] {}", error.message),
+        }
+    }
+}
+
+fn print_tree(report: &UsageReport, kind: SizeKind, depth: usize, top: usize) {
+    for dir in report.directories.iter().filter(|d| d.depth <= depth) {
+        let name = match dir.depth {
+            0 => dir.path.to_string_lossy(),
+            _ => dir.path.file_name().unwrap_or_default().to_string_lossy(),
+        };
+        println!(
+            "{:>10}  {}{}",
+            human_size(dir.usage.get(kind)),
+            "  ".repeat(dir.depth),
+            name
+        );
+    }
+
+    if top > 0 {
+        println!("\n最大的目录:");
+        for dir in report.largest_dirs(top, kind) {
+            println!("{:>10}  {:?}", human_size(dir.usage.get(kind)), dir.path);
+        }
+        println!("\n最大的文件:");
+        for file in &report.largest_files {
+            println!("{:>10}  {:?}", human_size(file.usage.get(kind)), file.path);
+        }
+    }
+
+    let total = report.total();
+    let root = report.directories.first();
+    println!(
+        "\n共 {} 个文件、{} 个目录，表观大小 {}，占用 {}{}",
+        root.map_or(0, |r| r.files),
+        root.map_or(0, |r| r.dirs),
+        human_size(total.apparent),
+        human_size(total.allocated),
+        if report.hard_links > 0 {
+            format!("（另有 {} 个重复的硬链接未计入）", report.hard_links)
+        } else {
+            String::new()
+        }
+    );
+}
+
+/// 含有逗号、引号或换行的字段用引号括起来，引号写两遍
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// 第一列区分这一行是目录（`dir`）还是最大的文件之一（`file`）；文件行没有 files、dirs 两列
+const CSV_HEADER: &str = "kind,path,depth,apparent,allocated,files,dirs";
+
+fn csv_dir_row(dir: &DirUsage) -> String {
+    format!(
+        "dir,{},{},{},{},{},{}",
+        csv_field(&dir.path.to_string_lossy()),
+        dir.depth,
+        dir.usage.apparent,
+        dir.usage.allocated,
+        dir.files,
+        dir.dirs
+    )
+}
+
+fn csv_file_row(root: &Path, file: &FileUsage) -> String {
+    let depth = file
+        .path
+        .strip_prefix(root)
+        .map_or(0, |p| p.components().count());
+    format!(
+        "file,{},{},{},{},,",
+        csv_field(&file.path.to_string_lossy()),
+        depth,
+        file.usage.apparent,
+        file.usage.allocated
+    )
+}
+
+/// 每个目录一行，再是最大的文件各一行
+fn csv_rows(report: &UsageReport) -> impl Iterator<Item = String> + '_ {
+    let dirs = report.directories.iter().map(csv_dir_row);
+    let files = report
+        .largest_files
+        .iter()
+        .map(|file| csv_file_row(&report.root, file));
+    dirs.chain(files)
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    #[serde(flatten)]
+    report: &'a UsageReport,
+    largest_dirs: Vec<&'a DirUsage>,
+}
+
+fn main() -> ExitCode {
+    let (roots, options, format, top) = match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Usage {
+            roots,
+            options,
+            format,
+            top,
+        }) => (roots, options, format, top),
+        Err(e) => {
+            eprintln!("错误: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut reports = Vec::new();
+    let mut failed = false;
+    if format == Format::Csv {
+        println!("{}", CSV_HEADER);
+    }
+    for (i, root) in roots.iter().enumerate() {
+        let report = match disk_usage(root, &options) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("[Error] {:?}: {}", root, e);
+                failed = true;
+                continue;
+            }
+        };
+        print_errors(&report.errors);
+        failed |= !report.errors.is_empty();
+        match format {
+            Format::Tree { depth } => {
+                if i > 0 {
+                    println!();
+                }
+                print_tree(&report, options.size_kind, depth, top);
+            }
+            Format::Csv => csv_rows(&report).for_each(|row| println!("{}", row)),
+            Format::Json => reports.push(report),
+        }
+    }
+
+    if format == Format::Json {
+        let output: Vec<_> = reports
+            .iter()
+            .map(|report| JsonOutput {
+                report,
+                largest_dirs: report.largest_dirs(top, options.size_kind),
+            })
+            .collect();
+        match serde_json::to_string_pretty(&output) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("[Error] 无法序列化报告: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use learning_file_io::usage::Usage;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let Ok(Command::Usage {
+            roots,
+            options,
+            format,
+            top,
+        }) = parse(&["--apparent", "-d", "3", "--top=5", "-j", "2", "a"])
+        else {
+            panic!("应当解析为 Usage");
+        };
+        assert_eq!(roots, [PathBuf::from("a")]);
+        assert_eq!(options.size_kind, SizeKind::Apparent);
+        assert!(!options.cross_file_systems);
+        assert_eq!((options.threads, options.largest_files, top), (2, 5, 5));
+        assert_eq!(format, Format::Tree { depth: 3 });
+
+        let Ok(Command::Usage { format, .. }) = parse(&["--csv", "--cross-file-systems", "."])
+        else {
+            panic!("应当解析为 Usage");
+        };
+        assert_eq!(format, Format::Csv);
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--json", "--csv", "."]).is_err());
+        assert!(parse(&["--json=yes", "."]).is_err());
+        assert!(parse(&["--depth", "-1", "."]).is_err());
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let usage = |apparent, allocated| Usage {
+            apparent,
+            allocated,
+        };
+        let report = UsageReport {
+            root: PathBuf::from("r"),
+            directories: vec![
+                DirUsage {
+                    path: PathBuf::from("r"),
+                    depth: 0,
+                    usage: usage(30, 8192),
+                    files: 3,
+                    dirs: 1,
+                },
+                DirUsage {
+                    path: PathBuf::from("r/a,\"b\""),
+                    depth: 1,
+                    usage: usage(10, 4096),
+                    files: 2,
+                    dirs: 0,
+                },
+            ],
+            largest_files: vec![FileUsage {
+                path: PathBuf::from("r/a,\"b\"/big"),
+                usage: usage(8, 4096),
+            }],
+            hard_links: 0,
+            errors: Vec::new(),
+        };
+        // 每行的列数与表头一致，文件行的 files、dirs 留空
+        let rows: Vec<_> = csv_rows(&report).collect();
+        assert_eq!(
+            rows,
+            [
+                "dir,r,0,30,8192,3,1",
+                "dir,\"r/a,\"\"b\"\"\",1,10,4096,2,0",
+                "file,\"r/a,\"\"b\"\"/big\",2,8,4096,,",
+            ]
+        );
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
@@ -0,0 +1,262 @@
+//! 查找重复文件的命令行工具
+//!
+//! ```text
+//! dupes [选项] <目录>...
+//! ```
+//!
+//! 退出码：0 表示成功；1 表示过程中遇到过错误（其余文件照常处理）；2 表示参数错误。
+
+use learning_file_io::cli::{Arg, ArgParser};
+use learning_file_io::dupes::{
+    apply, find_duplicates, ActionReport, DupeAction, DupeOptions, DupeReport, KeepPolicy,
+};
+use learning_file_io::error::FsError;
+use learning_file_io::report::human_size;
+use serde::Serialize;
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+用法: dupes [选项] <目录>...
+
+查找内容相同的文件：先按大小分组，再比较首尾两块的哈希，最后比较完整内容的哈希。
+
+选项:
+      --min-size <N>     忽略小于 N 字节的文件（默认 1，即忽略空文件）
+  -L, --follow-symlinks  跟随符号链接
+  -j, --threads <N>      哈希线程数（默认为 CPU 核数）
+
+处理方式（默认只报告）:
+      --delete           每组只保留一个文件，删除其余副本
+      --hardlink         每组只保留一个文件，其余副本替换成指向它的硬链接
+      --keep <POLICY>    保留哪一个: oldest（默认，修改时间最早）、newest、shortest（路径最短）
+  -n, --dry-run          只列出将会执行的操作
+
+输出:
+      --json             以 JSON 输出扫描结果和执行结果
+  -h, --help             显示本帮助";
+
+#[derive(Debug)]
+enum Command {
+    Help,
+    Find {
+        roots: Vec<PathBuf>,
+        options: DupeOptions,
+        action: Option<DupeAction>,
+        keep: KeepPolicy,
+        dry_run: bool,
+        json: bool,
+    },
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = DupeOptions::default();
+    let mut roots = Vec::new();
+    let mut action = None;
+    let mut keep = KeepPolicy::default();
+    let mut dry_run = false;
+    let mut json = false;
+    let mut args = ArgParser::new(args);
+
+    while let Some(arg) = args.next_arg()? {
+        let name = match arg {
+            Arg::Positional(path) => {
+                roots.push(PathBuf::from(path));
+                continue;
+            }
+            Arg::Option(name) => name,
+        };
+        let mut set_action = |new| match action {
+            Some(old) if old != new => Err("--delete 和 --hardlink 只能选一个".to_string()),
+            _ => {
+                action = Some(new);
+                Ok(())
+            }
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--min-size" => options.min_size = args.parse()?,
+            "-L" | "--follow-symlinks" => options.follow_symlinks = true,
+            "-j" | "--threads" => options.threads = args.parse()?,
+            "--delete" => set_action(DupeAction::Delete)?,
+            "--hardlink" => set_action(DupeAction::Hardlink)?,
+            "--keep" => {
+                keep = match args.value()?.as_str() {
+                    "oldest" => KeepPolicy::Oldest,
+                    "newest" => KeepPolicy::Newest,
+                    "shortest" => KeepPolicy::ShortestPath,
+                    other => return Err(format!("--keep 的取值 {:?} 无效", other)),
+                }
+            }
+            "-n" | "--dry-run" => dry_run = true,
+            "--json" => json = true,
+            _ => return Err(format!("未知选项: {}", name)),
+        }
+    }
+
+    if roots.is_empty() {
+        return Err("至少需要指定一个目录".to_string());
+    }
+    Ok(Command::Find {
+        roots,
+        options,
+        action,
+        keep,
+        dry_run,
+        json,
+    })
+}
+
+fn print_errors(errors: &[FsError]) {
+    for error in errors {
+        match &error.path {
+            Some(path) => eprintln!("[Error] {:?}: {}", path, error.message),
+            None => eprintln!("[Error] {}", error.message),
+        }
+    }
+}
+
+fn print_report(report: &DupeReport) {
+    for group in &report.groups {
+        println!(
+            "{} × {} 个文件（可省 {}）",
+            human_size(group.size),
+            group.files.len(),
+            human_size(group.wasted())
+        );
+        for file in &group.files {
+            println!("  {:?}", file.path);
+        }
+    }
+    println!(
+        "\n扫描文件 {}，部分哈希 {}，完整哈希 {}，重复组 {}，可省 {}",
+        report.scanned,
+        report.partial_hashed,
+        report.full_hashed,
+        report.groups.len(),
+        human_size(report.wasted())
+    );
+}
+
+fn print_actions(report: &ActionReport, action: DupeAction, dry_run: bool) {
+    let tag = match (action, dry_run) {
+        (DupeAction::Delete, false) => "[Deleted]",
+        (DupeAction::Delete, true) => "[Would delete]",
+        (DupeAction::Hardlink, false) => "[Linked]",
+        (DupeAction::Hardlink, true) => "[Would link]",
+    };
+    for applied in &report.applied {
+        println!("{} {:?} (保留 {:?})", tag, applied.path, applied.kept);
+    }
+    println!(
+        "{} {} 个副本，{} {}{}",
+        if dry_run { "将处理" } else { "已处理" },
+        report.applied.len(),
+        if dry_run { "将省下" } else { "省下" },
+        human_size(report.reclaimed),
+        if dry_run {
+            "（dry-run，未做任何修改）"
+        } else {
+            ""
+        }
+    );
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    scan: &'a DupeReport,
+    actions: Option<&'a ActionReport>,
+}
+
+fn main() -> ExitCode {
+    let (roots, options, action, keep, dry_run, json) = match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Find {
+            roots,
+            options,
+            action,
+            keep,
+            dry_run,
+            json,
+        }) => (roots, options, action, keep, dry_run, json),
+        Err(e) => {
+            eprintln!("错误: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let report = find_duplicates(&roots, &options);
+    print_errors(&report.errors);
+    if !json {
+        print_report(&report);
+    }
+
+    let actions = action.map(|action| {
+        let actions = apply(&report.groups, action, keep, dry_run);
+        print_errors(&actions.errors);
+        if !json {
+            print_actions(&actions, action, dry_run);
+        }
+        actions
+    });
+
+    if json {
+        let output = JsonOutput {
+            scan: &report,
+            actions: actions.as_ref(),
+        };
+        match serde_json::to_string_pretty(&output) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("[Error] 无法序列化报告: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let errors = report.errors.len() + actions.map_or(0, |a| a.errors.len());
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let Command::Find {
+            roots,
+            options,
+            action,
+            keep,
+            dry_run,
+            json,
+        } = parse(&["--hardlink", "--keep=shortest", "-j", "4", "-n", "a", "b"]).unwrap()
+        else {
+            panic!("应当解析为 Find");
+        };
+        assert_eq!(roots, [PathBuf::from("a"), PathBuf::from("b")]);
+        assert_eq!(options.threads, 4);
+        assert_eq!(action, Some(DupeAction::Hardlink));
+        assert_eq!(keep, KeepPolicy::ShortestPath);
+        assert!(dry_run && !json);
+
+        assert!(matches!(parse(&["-h"]), Ok(Command::Help)));
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--delete", "--hardlink", "."]).is_err());
+        assert!(parse(&["--keep", "largest", "."]).is_err());
+        assert!(parse(&["--min-size", "-1", "."]).is_err());
+    }
+}
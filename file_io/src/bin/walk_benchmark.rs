@@ -0,0 +1,293 @@
+//! 对比 walkdir 和 [`Walker`] 遍历大目录树的耗时
+//!
+//! ```text
+//! walk_benchmark [选项] [目录]
+//! ```
+//!
+//! 不给目录时在临时目录下生成一棵合成的树，测完删除；给了目录就直接遍历它（只读）。
+//! 每种方式跑若干轮，取最快的一轮，第一轮之前先预热一次，尽量排除页缓存的影响。
+
+use learning_file_io::cleaner::{clean_empty_directories, CleanOptions};
+use learning_file_io::cli::{Arg, ArgParser};
+use learning_file_io::walk::{Sort, Walker};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+const USAGE: &str = "\
+用法: walk_benchmark [选项] [目录]
+
+对比几种目录遍历方式的耗时。不给目录时生成一棵合成的树：
+每个目录有 FANOUT 个子目录和 FILES 个空文件，共 DEPTH 层。
+
+选项:
+      --depth <N>     合成树的层数（默认 4）
+      --fanout <N>    每个目录的子目录数（默认 8）
+      --files <N>     每个目录的文件数（默认 16）
+      --rounds <N>    每种方式跑几轮，取最快的一轮（默认 3）
+  -j, --threads <N>   并行遍历的线程数（默认为 CPU 核数）
+  -h, --help          显示本帮助";
+
+#[derive(Debug)]
+struct Config {
+    root: Option<PathBuf>,
+    depth: usize,
+    fanout: usize,
+    files: usize,
+    rounds: usize,
+    threads: usize,
+}
+
+#[derive(Debug)]
+enum Command {
+    Help,
+    Run(Config),
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut config = Config {
+        root: None,
+        depth: 4,
+        fanout: 8,
+        files: 16,
+        rounds: 3,
+        threads: 0,
+    };
+    let mut args = ArgParser::new(args);
+
+    while let Some(arg) = args.next_arg()? {
+        let name = match arg {
+            Arg::Positional(path) => {
+                if config.root.replace(PathBuf::from(path)).is_some() {
+                    return Err("只能指定一个目录".to_string());
+                }
+                continue;
+            }
+            Arg::Option(name) => name,
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--depth" => config.depth = args.parse()?,
+            "--fanout" => config.fanout = args.parse()?,
+            "--files" => config.files = args.parse()?,
+            "--rounds" => config.rounds = args.parse::<usize>()?.max(1),
+            "-j" | "--threads" => config.threads = args.parse()?,
+            _ => return Err(format!("未知选项: {}", name)),
+        }
+    }
+    Ok(Command::Run(config))
+}
+
+/// 生成合成树，返回目录数和文件数。叶子目录是空的，供 cleaner 的 dry-run 测试用
+fn generate(dir: &Path, depth: usize, fanout: usize, files: usize) -> io::Result<(usize, usize)> {
+    fs::create_dir_all(dir)?;
+    let mut counts = (1, 0);
+    if depth == 0 {
+        return Ok(counts);
+    }
+    for i in 0..files {
+        fs::write(dir.join(format!("file{:03}.txt", i)), b"")?;
+        counts.1 += 1;
+    }
+    for i in 0..fanout {
+        let (dirs, files) = generate(&dir.join(format!("dir{:03}", i)), depth - 1, fanout, files)?;
+        counts.0 += dirs;
+        counts.1 += files;
+    }
+    Ok(counts)
+}
+
+/// 跑 `rounds` 轮，返回最快一轮的耗时和那一轮数到的目录数
+fn measure(rounds: usize, mut run: impl FnMut() -> usize) -> (Duration, usize) {
+    run();
+    (0..rounds)
+        .map(|_| {
+            let start = Instant::now();
+            let dirs = run();
+            (start.elapsed(), dirs)
+        })
+        .min_by_key(|(elapsed, _)| *elapsed)
+        .expect("至少跑一轮")
+}
+
+/// 以前 cleaner 的做法：walkdir 遍历，每个条目再用 `path.is_dir()` stat 一次
+fn walkdir_stat(root: &Path) -> usize {
+    WalkDir::new(root)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .count()
+}
+
+fn walkdir_file_type(root: &Path) -> usize {
+    WalkDir::new(root)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_dir())
+        .count()
+}
+
+/// 流式回调，不收集也不排序
+fn walker_stream(root: &Path, threads: usize) -> usize {
+    let dirs = AtomicUsize::new(0);
+    Walker::new(root)
+        .threads(threads)
+        .contents_first(true)
+        .run(|entry| {
+            if entry.is_ok_and(|entry| entry.is_dir()) {
+                dirs.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    dirs.into_inner()
+}
+
+fn walker_sorted(root: &Path, threads: usize) -> usize {
+    Walker::new(root)
+        .threads(threads)
+        .contents_first(true)
+        .sort(Sort::Name)
+        .into_vec()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.is_dir())
+        .count()
+}
+
+fn run(config: &Config) -> io::Result<()> {
+    let threads = match config.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let (root, generated) = match &config.root {
+        Some(root) => (root.clone(), false),
+        None => {
+            let root = env::temp_dir().join(format!("walk_benchmark-{}", process::id()));
+            println!(
+                "生成合成树: 深度 {}，每层 {} 个子目录、{} 个文件 ...",
+                config.depth, config.fanout, config.files
+            );
+            let (dirs, files) = generate(&root, config.depth, config.fanout, config.files)?;
+            println!("共 {} 个目录、{} 个文件，位于 {:?}", dirs, files, root);
+            (root, true)
+        }
+    };
+    println!(
+        "CPU 核数 {}，并行遍历使用 {} 个线程，每种方式跑 {} 轮取最快\n",
+        thread::available_parallelism().map_or(1, |n| n.get()),
+        threads,
+        config.rounds
+    );
+
+    let cases: Vec<(String, Box<dyn Fn() -> usize + '_>)> = vec![
+        (
+            "walkdir + path.is_dir()".to_string(),
+            Box::new(|| walkdir_stat(&root)),
+        ),
+        (
+            "walkdir + file_type()".to_string(),
+            Box::new(|| walkdir_file_type(&root)),
+        ),
+        (
+            "Walker 流式，1 线程".to_string(),
+            Box::new(|| walker_stream(&root, 1)),
+        ),
+        (
+            format!("Walker 流式，{} 线程", threads),
+            Box::new(|| walker_stream(&root, threads)),
+        ),
+        (
+            format!("Walker 排序，{} 线程", threads),
+            Box::new(|| walker_sorted(&root, threads)),
+        ),
+        (
+            "cleaner dry-run".to_string(),
+            Box::new(|| {
+                let options = CleanOptions {
+                    dry_run: true,
+                    ..CleanOptions::default()
+                };
+                clean_empty_directories(&root, &options).map_or(0, |report| report.scanned)
+            }),
+        ),
+    ];
+
+    let mut baseline = None;
+    for (name, case) in &cases {
+        let (elapsed, dirs) = measure(config.rounds, case);
+        let baseline = *baseline.get_or_insert(elapsed);
+        println!(
+            "{:<28} {:>10.2?}  {:>6.2}x  ({} 个目录)",
+            name,
+            elapsed,
+            baseline.as_secs_f64() / elapsed.as_secs_f64(),
+            dirs
+        );
+    }
+
+    if generated {
+        fs::remove_dir_all(&root)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let config = match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Run(config)) => config,
+        Err(e) => {
+            eprintln!("错误: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&config) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("[Error] {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args_and_generate() {
+        let Ok(Command::Run(config)) = parse(&["--depth=2", "--fanout", "3", "-j", "2"]) else {
+            panic!("应当解析为 Run");
+        };
+        assert_eq!((config.depth, config.fanout, config.files), (2, 3, 16));
+        assert_eq!((config.threads, config.root), (2, None));
+        assert!(parse(&["a", "b"]).is_err());
+        assert!(parse(&["--rounds", "x"]).is_err());
+
+        let root = Path::new("test_env_walk_benchmark");
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        // 1 + 3 + 9 个目录，非叶子目录各 2 个文件
+        assert_eq!(generate(root, 2, 3, 2).unwrap(), (13, 8));
+        assert_eq!(walkdir_stat(root), 13);
+        assert_eq!(walkdir_file_type(root), 13);
+        assert_eq!(walker_stream(root, 2), 13);
+        assert_eq!(walker_sorted(root, 2), 13);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,613 @@
+//! 递归清理空目录（从 cleaner 演示中提取，命令行入口见 src/bin/cleaner.rs）
+//!
+//! 按"先子后父"的顺序处理目录：子目录删光之后父目录也就空了，可以在同一趟遍历里被"连坐"删除。
+//! 配置了 [`JunkRules`] 时，只含垃圾文件的目录也视同空目录：先删掉垃圾文件，再删目录，
+//! 同样会向上连坐。
+//!
+//! 每个结果（删除、保留、排除、错误）都记进返回的 [`CleanReport`]；需要实时输出时传入一个
+//! [`CleanObserver`]，命令行用的是打印到终端的 [`ConsoleObserver`]。
+//! 按 [`CleanOptions::disposal`] 也可以把目录移进隔离目录或回收站而不是直接删除，
+//! 报告里的 `undo` 可以写成撤销清单（见 [`crate::undo`]）。
+//!
+//! 目录树用 [`crate::walk::Walker`] 多线程遍历，`contents_first` 保证后代总在祖先之前；
+//! exclude 在遍历时剪枝，被排除的目录不会被深入。删除本身仍然按排好的顺序在当前线程里进行。
+
+use crate::error::FsError;
+use crate::glob::Pattern;
+use crate::junk::JunkRules;
+use crate::report::{CleanReport, SkipReason, Skipped};
+use crate::trash::{Disposal, Disposer};
+use crate::walk::{Entry, Sort, Walker};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// 只报告将会删除哪些目录，不真正删除
+    pub dry_run: bool,
+    /// 最大遍历深度，根目录为 0。更深的目录不会被访问，因此也不会被删除
+    pub max_depth: Option<usize>,
+    /// 匹配的目录（连同其子树）被跳过。不含 `/` 的模式匹配名字，含 `/` 的匹配相对根目录的路径
+    pub exclude: Vec<Pattern>,
+    /// 根目录本身为空时是否也删除。默认保留，防止误删挂载点
+    pub include_root: bool,
+    /// 跟随符号链接进入目标目录。链接本身永远不会被删除
+    pub follow_symlinks: bool,
+    /// 只含这些文件的目录也会被清理。默认为空，只删真正的空目录
+    pub junk: JunkRules,
+    /// 直接删除，还是移进隔离目录或回收站
+    pub disposal: Disposal,
+}
+
+/// 清理过程中的事件回调，每个事件发生时立即调用，同时也会记进 [`CleanReport`]
+///
+/// 所有方法都有空的默认实现，只关心部分事件时只需实现对应的方法
+pub trait CleanObserver {
+    /// 删除了一个目录（dry-run 时为将会删除）
+    fn deleted(&mut self, _path: &Path, _dry_run: bool) {}
+    /// 删除了一个垃圾文件（dry-run 时为将会删除）
+    fn junk_deleted(&mut self, _path: &Path, _dry_run: bool) {}
+    fn skipped(&mut self, _skipped: &Skipped) {}
+    fn error(&mut self, _error: &FsError) {}
+}
+
+/// 什么都不做的观察者
+impl CleanObserver for () {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// 只输出错误
+    Quiet,
+    /// 输出删除的目录和错误
+    #[default]
+    Normal,
+    /// 额外输出垃圾文件，以及因非空、被排除等原因保留下来的目录
+    Verbose,
+}
+
+/// 以前 `clean_empty_directories` 内置的输出格式：结果打印到 stdout，错误打印到 stderr
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleObserver {
+    pub verbosity: Verbosity,
+}
+
+impl ConsoleObserver {
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self { verbosity }
+    }
+}
+
+impl CleanObserver for ConsoleObserver {
+    fn deleted(&mut self, path: &Path, dry_run: bool) {
+        if self.verbosity != Verbosity::Quiet {
+            let tag = if dry_run {
+                "[Would delete]"
+            } else {
+                "[Deleted]"
+            };
+            println!("{} {:?}", tag, path);
+        }
+    }
+
+    fn junk_deleted(&mut self, path: &Path, dry_run: bool) {
+        if self.verbosity == Verbosity::Verbose {
+            let tag = if dry_run {
+                "[Would delete junk]"
+            } else {
+                "[Deleted junk]"
+            };
+            println!("{} {:?}", tag, path);
+        }
+    }
+
+    fn skipped(&mut self, skipped: &Skipped) {
+        if self.verbosity != Verbosity::Verbose {
+            return;
+        }
+        match &skipped.reason {
+            // 根目录保留是默认行为，不值得每次都提一句
+            SkipReason::Root => {}
+            SkipReason::Symlink => println!("[Kept] {:?} (符号链接)", skipped.path),
+            SkipReason::NotEmpty => println!("[Kept] {:?} (非空)", skipped.path),
+            SkipReason::Excluded { pattern } => {
+                println!("[Excluded] {:?} ({})", skipped.path, pattern)
+            }
+        }
+    }
+
+    fn error(&mut self, error: &FsError) {
+        match &error.path {
+            Some(path) => eprintln!("[Error] {:?}: {}", path, error.message),
+            None => eprintln!("[Error] {}", error.message),
+        }
+    }
+}
+
+/// 核心业务逻辑：递归清理 `target_root` 下的空目录，不输出任何内容
+///
+/// 只有根目录本身无法访问时返回 `Err`；遍历或删除过程中的错误记进报告，
+/// 不会中断对其余目录的处理
+pub fn clean_empty_directories(
+    target_root: &Path,
+    options: &CleanOptions,
+) -> io::Result<CleanReport> {
+    clean_empty_directories_with(target_root, options, &mut ())
+}
+
+/// 同 [`clean_empty_directories`]，每个事件发生时通知 `observer`
+pub fn clean_empty_directories_with(
+    target_root: &Path,
+    options: &CleanOptions,
+    observer: &mut dyn CleanObserver,
+) -> io::Result<CleanReport> {
+    if !fs::metadata(target_root)?.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} 不是目录", target_root.display()),
+        ));
+    }
+
+    let mut cleaner = Cleaner {
+        options,
+        disposer: Disposer::new(&options.disposal)?,
+        observer,
+        report: CleanReport::new(target_root, options.dry_run),
+        removed: HashSet::new(),
+    };
+    // 过滤函数在各个遍历线程里被调用，先攒下来，遍历完再报告
+    let excluded = Mutex::new(Vec::new());
+
+    let entries = Walker::new(target_root)
+        .max_depth(options.max_depth.unwrap_or(usize::MAX))
+        .follow_links(options.follow_symlinks)
+        .contents_first(true)
+        .sort(Sort::Name)
+        .filter_entry(|entry| {
+            if let Some(pattern) = excluded_by(entry, target_root, &options.exclude) {
+                excluded.lock().unwrap().push(Skipped {
+                    path: entry.path().to_path_buf(),
+                    reason: SkipReason::Excluded {
+                        pattern: pattern.to_string(),
+                    },
+                });
+                return false;
+            }
+            // 文件类型取自目录项，不必再对每个条目 stat 一次
+            entry.is_dir()
+        })
+        .into_vec();
+
+    let mut dirs = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => dirs.push(entry),
+            // 没有权限、符号链接成环等；不能悄悄丢掉
+            Err(e) => cleaner.error(Some(e.path()), e.io_error()),
+        }
+    }
+    let mut excluded = excluded.into_inner().unwrap();
+    excluded.sort_by(|a, b| a.path.cmp(&b.path));
+    for skipped in excluded {
+        cleaner.skip(skipped.path, skipped.reason);
+    }
+
+    for entry in &dirs {
+        cleaner.report.scanned += 1;
+        let path = entry.path();
+
+        // 通常我们不想删掉用户指定的那个顶层文件夹，防止误删挂载点
+        if entry.depth() == 0 && !options.include_root {
+            cleaner.skip(path.to_path_buf(), SkipReason::Root);
+            continue;
+        }
+        // 跟随链接时，链接指向的目录里的空目录可以删，但链接本身不是目录，不能 remove_dir
+        if entry.path_is_symlink() {
+            cleaner.skip(path.to_path_buf(), SkipReason::Symlink);
+            continue;
+        }
+
+        // 只有直接删除空目录时才能让 remove_dir 自己判断是否为空；
+        // 移走目录前必须先确认它是空的，否则整棵子树都会被搬走
+        let result = if options.dry_run || !options.junk.is_empty() || !cleaner.disposer.is_delete()
+        {
+            cleaner.remove_with_junk(path)
+        } else {
+            cleaner.dispose_dir(path)
+        };
+        match result {
+            Ok(true) => {
+                cleaner.observer.deleted(path, options.dry_run);
+                cleaner.report.deleted.push(path.to_path_buf());
+            }
+            Ok(false) => cleaner.skip(path.to_path_buf(), SkipReason::NotEmpty),
+            Err((failed, e)) => cleaner.error(Some(&failed), &e),
+        }
+    }
+
+    Ok(cleaner.report)
+}
+
+/// 一次清理的状态：每个结果既通知观察者，也记进报告
+struct Cleaner<'a> {
+    options: &'a CleanOptions,
+    disposer: Disposer,
+    observer: &'a mut dyn CleanObserver,
+    report: CleanReport,
+    /// dry-run 时目录并没有真的被删掉，记下"已删除"的目录，判断父目录是否会变空
+    removed: HashSet<PathBuf>,
+}
+
+/// 出错的路径和错误本身；删除目录时出错的可能是其中的某个垃圾文件
+type RemoveResult = Result<bool, (PathBuf, io::Error)>;
+
+impl Cleaner<'_> {
+    fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        let skipped = Skipped { path, reason };
+        self.observer.skipped(&skipped);
+        self.report.skipped.push(skipped);
+    }
+
+    fn error(&mut self, path: Option<&Path>, e: &io::Error) {
+        let error = FsError::new(path, e);
+        self.observer.error(&error);
+        self.report.errors.push(error);
+    }
+
+    fn dispose_dir(&mut self, path: &Path) -> RemoveResult {
+        match self.disposer.dir(path) {
+            Ok(Some(undo)) => {
+                self.report.undo.push(undo);
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err((path.to_path_buf(), e)),
+        }
+    }
+
+    /// 目录里除了（将会被）删掉的子目录就只剩垃圾文件时，先删垃圾文件再删目录
+    ///
+    /// dry-run 时什么都不删，只把目录记进 `removed`，供父目录判断自己是否会变空
+    fn remove_with_junk(&mut self, path: &Path) -> RemoveResult {
+        let in_dir = |e| (path.to_path_buf(), e);
+        let mut junk = Vec::new();
+        for child in fs::read_dir(path).map_err(in_dir)? {
+            let child = child.map_err(in_dir)?;
+            if self.removed.contains(&child.path()) {
+                continue;
+            }
+            if !self
+                .options
+                .junk
+                .is_junk(&child)
+                .map_err(|e| (child.path(), e))?
+            {
+                return Ok(false);
+            }
+            junk.push(child.path());
+        }
+
+        // 目录保不住时不碰其中的垃圾文件
+        let dry_run = self.options.dry_run;
+        for file in junk {
+            if !dry_run {
+                let undo = self.disposer.file(&file).map_err(|e| (file.clone(), e))?;
+                self.report.undo.push(undo);
+            }
+            self.observer.junk_deleted(&file, dry_run);
+            self.report.junk_files.push(file);
+        }
+        if dry_run {
+            self.removed.insert(path.to_path_buf());
+            Ok(true)
+        } else {
+            // 删垃圾文件期间可能有新文件写进来，这时目录照常保留
+            self.dispose_dir(path)
+        }
+    }
+}
+
+/// 按 exclude 规则判断条目是否应被跳过，返回命中的模式。根目录本身永远不会被排除
+fn excluded_by<'p>(entry: &Entry, root: &Path, patterns: &'p [Pattern]) -> Option<&'p Pattern> {
+    if entry.depth() == 0 || patterns.is_empty() {
+        return None;
+    }
+    let name = entry.file_name().to_string_lossy();
+    let relative = relative_path(entry.path(), root);
+    patterns.iter().find(|pattern| {
+        if pattern.as_str().contains('/') {
+            pattern.matches(&relative)
+        } else {
+            pattern.matches(&name)
+        }
+    })
+}
+
+/// 相对根目录的路径，分隔符统一为 `/`，方便与 glob 模式比较
+fn relative_path(path: &Path, root: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    // 一个辅助函数，用来创建测试环境
+    fn setup_test_environment(root: &str) {
+        // 结构设计：
+        // root/
+        // ├── empty_chain_1/     (空)
+        // │   └── empty_chain_2/ (空) -> 应该被连坐删除
+        // ├── keep_me/
+        // │   └── data.txt       (文件) -> 应该保留 keep_me
+        // └── mixed/
+        //     ├── trash/         (空)   -> 应该被删除
+        //     └── treasure.txt   (文件) -> 应该导致 mixed 被保留
+
+        let root_path = Path::new(root);
+        if root_path.exists() {
+            fs::remove_dir_all(root_path).unwrap(); // 清理旧环境
+        }
+        fs::create_dir_all(root_path.join("empty_chain_1/empty_chain_2")).unwrap();
+        fs::create_dir_all(root_path.join("keep_me")).unwrap();
+        fs::create_dir_all(root_path.join("mixed/trash")).unwrap();
+
+        // 创建文件
+        let mut f1 = File::create(root_path.join("keep_me/data.txt")).unwrap();
+        f1.write_all(b"content").unwrap();
+
+        let mut f2 = File::create(root_path.join("mixed/treasure.txt")).unwrap();
+        f2.write_all(b"gold").unwrap();
+    }
+
+    #[test]
+    fn test_clean_empty_dirs_logic() {
+        let test_root = "test_env_temp";
+        setup_test_environment(test_root);
+        let root_path = Path::new(test_root);
+
+        // --- 执行前断言 ---
+        assert!(
+            root_path.join("empty_chain_1/empty_chain_2").exists(),
+            "Setup 失败"
+        );
+        assert!(root_path.join("mixed/trash").exists(), "Setup 失败");
+
+        // --- 执行逻辑 ---
+        let summary = clean_empty_directories(root_path, &CleanOptions::default())
+            .expect("执行失败")
+            .summary();
+        assert_eq!(summary.deleted, 3);
+        assert_eq!(summary.errors, 0);
+
+        // --- 执行后验证 (Assert) ---
+
+        // 1. 验证连锁删除：最底层的空文件夹没了
+        assert!(
+            !root_path.join("empty_chain_1/empty_chain_2").exists(),
+            "底层空文件夹未删除"
+        );
+        // 2. 验证连锁删除：父级变空后也该没了 (后序遍历的威力)
+        assert!(
+            !root_path.join("empty_chain_1").exists(),
+            "父级空文件夹未被连锁删除"
+        );
+
+        // 3. 验证非空保护：有文件的文件夹还在
+        assert!(root_path.join("keep_me").exists(), "包含文件的文件夹误删");
+        assert!(root_path.join("keep_me/data.txt").exists(), "文件误删");
+
+        // 4. 验证混合情况：删了空的子目录，但保留了有内容的父目录
+        assert!(
+            !root_path.join("mixed/trash").exists(),
+            "混合目录下的空文件夹未删除"
+        );
+        assert!(root_path.join("mixed").exists(), "混合目录被误删");
+        assert!(
+            root_path.join("mixed/treasure.txt").exists(),
+            "混合目录下的文件被误删"
+        );
+
+        // 清理测试现场
+        fs::remove_dir_all(root_path).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_predicts_cascade_without_deleting() {
+        let test_root = "test_env_dry_run";
+        setup_test_environment(test_root);
+        let root_path = Path::new(test_root);
+        fs::create_dir_all(root_path.join("only_empty/a/b")).unwrap();
+        fs::create_dir_all(root_path.join("only_empty/c")).unwrap();
+
+        let options = CleanOptions {
+            dry_run: true,
+            include_root: true,
+            ..CleanOptions::default()
+        };
+        let summary = clean_empty_directories(root_path, &options)
+            .unwrap()
+            .summary();
+        // empty_chain_1/2、mixed/trash、only_empty 及其下的 a、a/b、c
+        assert_eq!(summary.deleted, 7);
+        // 根目录里还有文件，即使 include_root 也不会被删
+        assert_eq!(summary.kept, 3);
+        assert!(root_path.join("only_empty/a/b").exists());
+        assert!(root_path.join("empty_chain_1/empty_chain_2").exists());
+
+        fs::remove_dir_all(root_path).unwrap();
+    }
+
+    #[test]
+    fn test_junk_only_directories_cascade() {
+        let root_path = Path::new("test_env_junk");
+        if root_path.exists() {
+            fs::remove_dir_all(root_path).unwrap();
+        }
+        // photos/2020/ 只有 .DS_Store，photos/ 只有 Thumbs.db 和 2020/ -> 整棵删除
+        // docs/ 有 desktop.ini 和真正的文件 -> 保留，desktop.ini 也不动
+        // logs/ 只有一个空文件 -> 只有开启 zero_size 时才删除
+        fs::create_dir_all(root_path.join("photos/2020")).unwrap();
+        fs::create_dir_all(root_path.join("docs")).unwrap();
+        fs::create_dir_all(root_path.join("logs")).unwrap();
+        for junk in [
+            "photos/2020/.DS_Store",
+            "photos/Thumbs.db",
+            "docs/desktop.ini",
+        ] {
+            File::create(root_path.join(junk))
+                .unwrap()
+                .write_all(b"junk")
+                .unwrap();
+        }
+        File::create(root_path.join("docs/report.txt"))
+            .unwrap()
+            .write_all(b"keep")
+            .unwrap();
+        File::create(root_path.join("logs/empty.log")).unwrap();
+
+        let mut options = CleanOptions {
+            junk: JunkRules::common(),
+            dry_run: true,
+            ..CleanOptions::default()
+        };
+        let summary = clean_empty_directories(root_path, &options)
+            .unwrap()
+            .summary();
+        assert_eq!((summary.deleted, summary.junk_files), (2, 2));
+        assert!(root_path.join("photos/2020/.DS_Store").exists());
+
+        options.dry_run = false;
+        options.junk.zero_size = true;
+        let summary = clean_empty_directories(root_path, &options)
+            .unwrap()
+            .summary();
+        assert_eq!((summary.deleted, summary.junk_files), (3, 3));
+        assert!(!root_path.join("photos").exists());
+        assert!(!root_path.join("logs").exists());
+        assert!(root_path.join("docs/desktop.ini").exists());
+        assert!(root_path.join("docs/report.txt").exists());
+
+        fs::remove_dir_all(root_path).unwrap();
+    }
+
+    #[test]
+    fn test_exclude_max_depth_and_root() {
+        let test_root = "test_env_options";
+        let root_path = Path::new(test_root);
+        if root_path.exists() {
+            fs::remove_dir_all(root_path).unwrap();
+        }
+        fs::create_dir_all(root_path.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(root_path.join("src/deep/deeper")).unwrap();
+        fs::create_dir_all(root_path.join("src/keep/this")).unwrap();
+
+        let options = CleanOptions {
+            max_depth: Some(2),
+            exclude: vec![
+                Pattern::new("node_modules").unwrap(),
+                Pattern::new("src/keep").unwrap(),
+            ],
+            ..CleanOptions::default()
+        };
+        let summary = clean_empty_directories(root_path, &options)
+            .unwrap()
+            .summary();
+        assert_eq!(summary.excluded, 2);
+        // src/deep/deeper 超出深度，没被访问，所以 src/deep 非空
+        assert_eq!(summary.deleted, 0);
+        assert!(root_path.join("node_modules/pkg").exists());
+        assert!(root_path.join("src/deep/deeper").exists());
+
+        // 不限深度、不排除：除根目录以外全部删除
+        let summary = clean_empty_directories(root_path, &CleanOptions::default())
+            .unwrap()
+            .summary();
+        assert_eq!(summary.deleted, 7);
+        assert!(root_path.exists());
+
+        // include_root：空的根目录也删除
+        let options = CleanOptions {
+            include_root: true,
+            ..CleanOptions::default()
+        };
+        let summary = clean_empty_directories(root_path, &options)
+            .unwrap()
+            .summary();
+        assert_eq!(summary.deleted, 1);
+        assert!(!root_path.exists());
+
+        assert!(clean_empty_directories(root_path, &CleanOptions::default()).is_err());
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        deleted: Vec<PathBuf>,
+        skipped: Vec<Skipped>,
+        errors: Vec<FsError>,
+    }
+
+    impl CleanObserver for Recorder {
+        fn deleted(&mut self, path: &Path, _dry_run: bool) {
+            self.deleted.push(path.to_path_buf());
+        }
+        fn skipped(&mut self, skipped: &Skipped) {
+            self.skipped.push(skipped.clone());
+        }
+        fn error(&mut self, error: &FsError) {
+            self.errors.push(error.clone());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_report_records_skips_and_walk_errors() {
+        let root_path = Path::new("test_env_report");
+        if root_path.exists() {
+            fs::remove_dir_all(root_path).unwrap();
+        }
+        fs::create_dir_all(root_path.join("loop")).unwrap();
+        fs::create_dir_all(root_path.join(".git/objects")).unwrap();
+        fs::create_dir_all(root_path.join("empty")).unwrap();
+        // 跟随符号链接时，指回祖先目录的链接会被报告为环
+        std::os::unix::fs::symlink("..", root_path.join("loop/up")).unwrap();
+
+        let options = CleanOptions {
+            follow_symlinks: true,
+            exclude: vec![Pattern::new(".git").unwrap()],
+            ..CleanOptions::default()
+        };
+        let mut recorder = Recorder::default();
+        let report = clean_empty_directories_with(root_path, &options, &mut recorder).unwrap();
+
+        assert_eq!(report.deleted, [root_path.join("empty")]);
+        assert_eq!(recorder.deleted, report.deleted);
+        assert_eq!(recorder.skipped, report.skipped);
+        assert_eq!(recorder.errors, report.errors);
+        assert!(report.skipped.contains(&Skipped {
+            path: root_path.join(".git"),
+            reason: SkipReason::Excluded {
+                pattern: ".git".to_string()
+            },
+        }));
+        assert!(report.skipped.contains(&Skipped {
+            path: root_path.join("loop"),
+            reason: SkipReason::NotEmpty,
+        }));
+        // 环不再被悄悄丢掉
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, Some(root_path.join("loop/up")));
+        assert!(!report.is_ok());
+
+        fs::remove_dir_all(root_path).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! 几个命令行工具共用的参数切分
+//!
+//! 只负责各工具都一样的部分：
+//! - `--` 之后的参数一律当作路径，单独的 `-` 也是路径；
+//! - 长选项的取值可以写成 `--max-depth 3` 或 `--max-depth=3`；
+//! - 选项缺少取值、不接受取值却写了 `=取值` 时报错。
+//!
+//! 有哪些选项、取值怎么解释由各工具自己决定：
+//!
+//! ```
+//! use learning_file_io::cli::{Arg, ArgParser};
+//!
+//! let mut args = ArgParser::new(["--depth=2", "-v", "src"].map(String::from));
+//! let (mut depth, mut verbose, mut paths) = (0, false, vec![]);
+//! while let Some(arg) = args.next_arg()? {
+//!     match arg {
+//!         Arg::Positional(path) => paths.push(path),
+//!         Arg::Option(name) => match name.as_str() {
+//!             "--depth" => depth = args.parse()?,
+//!             "-v" => verbose = true,
+//!             _ => return Err(format!("未知选项: {}", name)),
+//!         },
+//!     }
+//! }
+//! assert_eq!((depth, verbose, paths), (2, true, vec!["src".to_string()]));
+//! # Ok::<(), String>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    /// 不是选项的参数，通常是路径
+    Positional(String),
+    /// 选项名，`--name=value` 只含 `=` 前面的部分
+    Option(String),
+}
+
+pub struct ArgParser<I> {
+    args: I,
+    only_positional: bool,
+    /// 当前选项的名字，以及 `=` 后面还没被取走的取值
+    current: Option<(String, Option<String>)>,
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    pub fn new(args: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            args: args.into_iter(),
+            only_positional: false,
+            current: None,
+        }
+    }
+
+    /// 下一个参数。上一个选项写了 `=取值` 却没有用 [`value`](Self::value) 取走时报错
+    pub fn next_arg(&mut self) -> Result<Option<Arg>, String> {
+        if let Some((name, Some(_))) = self.current.take() {
+            return Err(format!("选项 {} 不接受取值", name));
+        }
+        for arg in self.args.by_ref() {
+            if self.only_positional || !arg.starts_with('-') || arg == "-" {
+                return Ok(Some(Arg::Positional(arg)));
+            }
+            if arg == "--" {
+                self.only_positional = true;
+                continue;
+            }
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            self.current = Some((name.clone(), inline));
+            return Ok(Some(Arg::Option(name)));
+        }
+        Ok(None)
+    }
+
+    /// 当前选项的取值：`=` 后面的部分，没有时取下一个参数
+    pub fn value(&mut self) -> Result<String, String> {
+        let (name, inline) = self
+            .current
+            .as_mut()
+            .expect("只有在 next_arg 返回选项之后才能取值");
+        inline
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| format!("{} 缺少取值", name))
+    }
+
+    /// 取出当前选项的取值并解析
+    pub fn parse<T>(&mut self) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.value()?;
+        value.parse().map_err(|e| {
+            let name = self.current.as_ref().map_or("", |(name, _)| name.as_str());
+            format!("{} 的取值 {:?} 无效: {}", name, value, e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(args: &[&str]) -> ArgParser<std::vec::IntoIter<String>> {
+        ArgParser::new(args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_values_and_positionals() {
+        let mut args = parser(&["-j", "4", "--top=3", "a", "-", "--", "--top", "-x"]);
+        assert_eq!(args.next_arg(), Ok(Some(Arg::Option("-j".into()))));
+        assert_eq!(args.parse::<usize>(), Ok(4));
+        assert_eq!(args.next_arg(), Ok(Some(Arg::Option("--top".into()))));
+        assert_eq!(args.value(), Ok("3".into()));
+        for positional in ["a", "-", "--top", "-x"] {
+            assert_eq!(
+                args.next_arg(),
+                Ok(Some(Arg::Positional(positional.into())))
+            );
+        }
+        assert_eq!(args.next_arg(), Ok(None));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut args = parser(&["--depth"]);
+        args.next_arg().unwrap();
+        assert_eq!(args.value(), Err("--depth 缺少取值".into()));
+
+        let mut args = parser(&["--depth=x"]);
+        args.next_arg().unwrap();
+        assert!(args
+            .parse::<usize>()
+            .unwrap_err()
+            .starts_with("--depth 的取值 \"x\" 无效"));
+
+        // 标志选项没有取走 `=` 后面的取值：下一次 next_arg 报错，包括最后一个参数
+        let mut args = parser(&["--json=yes"]);
+        args.next_arg().unwrap();
+        assert_eq!(args.next_arg(), Err("选项 --json 不接受取值".into()));
+    }
+}
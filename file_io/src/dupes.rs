@@ -0,0 +1,598 @@
+//! 查找内容重复的文件
+//!
+//! 逐级缩小候选范围，尽量少读文件：
+//! 1. 按大小分组，大小独一无二的文件不可能有重复；
+//! 2. 大小相同的文件读首尾各一块算部分哈希，大多数"碰巧一样大"的文件在这一步就分开了；
+//! 3. 部分哈希也相同的才读完整个文件算完整哈希。
+//!
+//! 第 2、3 步在多个线程里并行。哈希用的是标准库的 `DefaultHasher`（64 位），只用来分组；
+//! 删除或替换成硬链接之前还会逐字节比较一次，哈希碰撞不会导致误删。
+//!
+//! 已经互为硬链接的文件本来就只占一份空间，扫描时只保留其中第一个路径。
+//! 拿不到 inode 的平台上退而用规范化后的路径判断，至少重叠的根目录不会把同一个文件算两次。
+
+use crate::error::FsError;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// 部分哈希读取的块大小；不超过两块的文件，部分哈希就是完整哈希
+pub const BLOCK_SIZE: u64 = 4096;
+
+#[derive(Debug, Clone)]
+pub struct DupeOptions {
+    /// 小于这个大小的文件不参与比较。默认 1，即跳过空文件
+    pub min_size: u64,
+    pub follow_symlinks: bool,
+    /// 哈希线程数，0 表示使用 CPU 核数
+    pub threads: usize,
+}
+
+impl Default for DupeOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 1,
+            follow_symlinks: false,
+            threads: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+/// 一组内容相同的文件，按路径排序
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub files: Vec<FileEntry>,
+}
+
+impl DuplicateGroup {
+    /// 只保留一份时可以省下的字节数
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+
+    /// 按策略选出要保留的那个文件的下标；条件相同时取路径排在前面的
+    pub fn keeper(&self, policy: KeepPolicy) -> usize {
+        let indices = 0..self.files.len();
+        let file = |i: usize| &self.files[i];
+        match policy {
+            KeepPolicy::Oldest => indices.min_by_key(|&i| (file(i).modified, &file(i).path)),
+            KeepPolicy::Newest => indices.min_by(|&a, &b| {
+                file(b)
+                    .modified
+                    .cmp(&file(a).modified)
+                    .then_with(|| file(a).path.cmp(&file(b).path))
+            }),
+            KeepPolicy::ShortestPath => indices.min_by_key(|&i| {
+                let path = &file(i).path;
+                (path.as_os_str().len(), path)
+            }),
+        }
+        .expect("重复组至少有两个文件")
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DupeReport {
+    /// 参与比较的文件数（已去掉过小的文件和多余的硬链接）
+    pub scanned: usize,
+    /// 计算了部分哈希的文件数
+    pub partial_hashed: usize,
+    /// 读完整个文件计算了完整哈希的文件数
+    pub full_hashed: usize,
+    /// 按可节省的空间从大到小排列
+    pub groups: Vec<DuplicateGroup>,
+    pub errors: Vec<FsError>,
+}
+
+impl DupeReport {
+    pub fn wasted(&self) -> u64 {
+        self.groups.iter().map(DuplicateGroup::wasted).sum()
+    }
+}
+
+/// 在 `roots` 下查找重复文件。单个文件读不了只记进 `errors`，不影响其余文件
+pub fn find_duplicates<P: AsRef<Path>>(roots: &[P], options: &DupeOptions) -> DupeReport {
+    let mut report = DupeReport::default();
+    let mut by_size: HashMap<u64, Vec<FileEntry>> = HashMap::new();
+    let mut seen = HashSet::new();
+
+    for root in roots {
+        let walker = WalkDir::new(root).follow_links(options.follow_symlinks);
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) if entry.file_type().is_file() => entry,
+                Ok(_) => continue,
+                Err(e) => {
+                    let path = e.path().map(Path::to_path_buf);
+                    report
+                        .errors
+                        .push(FsError::new(path.as_deref(), &io::Error::from(e)));
+                    continue;
+                }
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    report
+                        .errors
+                        .push(FsError::new(Some(entry.path()), &io::Error::from(e)));
+                    continue;
+                }
+            };
+            if metadata.len() < options.min_size {
+                continue;
+            }
+            // 多个根目录重叠，或者本来就是硬链接：同一个文件只算一次
+            if !seen.insert(identity(entry.path(), &metadata)) {
+                continue;
+            }
+            report.scanned += 1;
+            by_size.entry(metadata.len()).or_default().push(FileEntry {
+                path: entry.into_path(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+
+    let threads = match options.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    let candidates = flatten(by_size.into_iter().filter(|(_, files)| files.len() > 1));
+    report.partial_hashed = candidates.len();
+    let partial = regroup(candidates, threads, &mut report.errors, |size, path| {
+        hash_file(path, size, HashMode::Partial)
+    });
+
+    // 小文件的部分哈希已经覆盖了全部内容，不必再读一遍
+    let (small, large): (Vec<_>, Vec<_>) = partial
+        .into_iter()
+        .partition(|(size, _)| *size <= 2 * BLOCK_SIZE);
+    let candidates = flatten(large);
+    report.full_hashed = candidates.len();
+    let full = regroup(candidates, threads, &mut report.errors, |size, path| {
+        hash_file(path, size, HashMode::Full)
+    });
+
+    report.groups = small
+        .into_iter()
+        .chain(full)
+        .map(|(size, mut files)| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            DuplicateGroup { size, files }
+        })
+        .collect();
+    report.groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+    });
+    report
+}
+
+type Group = (u64, Vec<FileEntry>);
+
+/// 拆成 (大小, 文件) 的列表，交给下一步并行计算
+fn flatten(groups: impl IntoIterator<Item = Group>) -> Vec<(u64, FileEntry)> {
+    groups
+        .into_iter()
+        .flat_map(|(size, files)| files.into_iter().map(move |f| (size, f)))
+        .collect()
+}
+
+/// 并行计算每个文件的键，按 (大小, 键) 重新分组，只留下多于一个文件的组
+fn regroup<F>(
+    files: Vec<(u64, FileEntry)>,
+    threads: usize,
+    errors: &mut Vec<FsError>,
+    key: F,
+) -> Vec<Group>
+where
+    F: Fn(u64, &Path) -> io::Result<u64> + Sync,
+{
+    let keys = parallel_map(&files, threads, |(size, file)| key(*size, &file.path));
+
+    let mut groups: HashMap<(u64, u64), Vec<FileEntry>> = HashMap::new();
+    for ((size, file), key) in files.into_iter().zip(keys) {
+        match key {
+            Ok(key) => groups.entry((size, key)).or_default().push(file),
+            Err(e) => errors.push(FsError::new(Some(&file.path), &e)),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|((size, _), files)| (size, files))
+        .collect()
+}
+
+/// 用 `threads` 个线程对每个元素调用 `f`，结果与输入一一对应
+///
+/// 文件大小差别很大，按固定区间切分容易让一个线程拖到最后；这里用一个共享下标，谁空闲谁领下一个
+fn parallel_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|s| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            s.spawn(|| {
+                let mut local = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    local.push((i, f(item)));
+                }
+                results.lock().unwrap().extend(local);
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[derive(Clone, Copy)]
+enum HashMode {
+    /// 首尾各 [`BLOCK_SIZE`] 字节
+    Partial,
+    Full,
+}
+
+fn hash_file(path: &Path, size: u64, mode: HashMode) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    match mode {
+        HashMode::Partial if size > 2 * BLOCK_SIZE => {
+            let mut block = vec![0; BLOCK_SIZE as usize];
+            file.read_exact(&mut block)?;
+            hasher.write(&block);
+            file.seek(SeekFrom::End(-(BLOCK_SIZE as i64)))?;
+            file.read_exact(&mut block)?;
+            hasher.write(&block);
+        }
+        _ => {
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.write(&buf[..n]);
+            }
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// 判断是不是同一个文件用的标识
+#[derive(PartialEq, Eq, Hash)]
+enum FileIdentity {
+    /// (设备, inode)，能认出硬链接
+    Inode(u64, u64),
+    /// 拿不到 inode 时用规范化后的路径，只能认出同一路径的不同写法
+    Path(PathBuf),
+}
+
+fn identity(path: &Path, metadata: &fs::Metadata) -> FileIdentity {
+    match file_id(metadata) {
+        Some((dev, ino)) => FileIdentity::Inode(dev, ino),
+        None => FileIdentity::Path(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())),
+    }
+}
+
+/// 两个路径是否指向同一个文件。这时删掉"副本"就把唯一的一份删掉了
+fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    Ok(identity(a, &fs::metadata(a)?) == identity(b, &fs::metadata(b)?))
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 每组保留哪一个文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeepPolicy {
+    /// 修改时间最早的
+    #[default]
+    Oldest,
+    /// 修改时间最晚的
+    Newest,
+    /// 路径最短的，通常是"原件"而不是某个深层备份目录里的副本
+    ShortestPath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DupeAction {
+    /// 删除除保留文件以外的所有副本
+    Delete,
+    /// 把副本替换成指向保留文件的硬链接，路径都还在，但只占一份空间
+    Hardlink,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Applied {
+    /// 被删除或被替换成硬链接的副本
+    pub path: PathBuf,
+    /// 保留下来的文件
+    pub kept: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActionReport {
+    pub applied: Vec<Applied>,
+    /// 省下（dry-run 时为将会省下）的字节数
+    pub reclaimed: u64,
+    pub errors: Vec<FsError>,
+}
+
+/// 对每组重复文件执行 `action`。动手前逐字节确认副本与保留文件仍然相同，不同的跳过并记为错误；
+/// 副本和保留文件其实是同一个文件（比如扫描之后已经被链接过）时什么也不做
+pub fn apply(
+    groups: &[DuplicateGroup],
+    action: DupeAction,
+    keep: KeepPolicy,
+    dry_run: bool,
+) -> ActionReport {
+    let mut report = ActionReport::default();
+    for group in groups {
+        let kept = &group.files[group.keeper(keep)].path;
+        for file in group.files.iter().filter(|f| &f.path != kept) {
+            let result = match same_file(kept, &file.path) {
+                Ok(true) => continue,
+                Ok(false) => same_contents(kept, &file.path),
+                Err(e) => Err(e),
+            };
+            let result = match result {
+                Ok(true) if dry_run => Ok(()),
+                Ok(true) => match action {
+                    DupeAction::Delete => fs::remove_file(&file.path),
+                    DupeAction::Hardlink => replace_with_hardlink(kept, &file.path),
+                },
+                Ok(false) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("扫描之后内容有变化，与 {} 不再相同，已跳过", kept.display()),
+                )),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => {
+                    report.reclaimed += group.size;
+                    report.applied.push(Applied {
+                        path: file.path.clone(),
+                        kept: kept.clone(),
+                    });
+                }
+                Err(e) => report.errors.push(FsError::new(Some(&file.path), &e)),
+            }
+        }
+    }
+    report
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let mut buf_a = vec![0; 64 * 1024];
+    let mut buf_b = vec![0; 64 * 1024];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            // 长度相同，a 读完时 b 也该读完了；除非比较期间文件被改了
+            return Ok(b.read(&mut buf_b[..1])? == 0);
+        }
+        b.read_exact(&mut buf_b[..n])?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// 先在同一目录下建一个临时名字的硬链接，再 rename 覆盖副本：任何时刻副本路径要么是旧文件要么是新链接
+fn replace_with_hardlink(kept: &Path, duplicate: &Path) -> io::Result<()> {
+    let mut tmp_name = duplicate.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".dupes-tmp");
+    let tmp = duplicate.with_file_name(tmp_name);
+    fs::hard_link(kept, &tmp)?;
+    fs::rename(&tmp, duplicate).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write(path: &Path, contents: &[u8], age_secs: u64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    fn setup(root: &Path) {
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        // 大文件只在中间差一个字节：大小、部分哈希都相同，要靠完整哈希区分
+        let big: Vec<u8> = (0..5 * BLOCK_SIZE).map(|i| (i % 251) as u8).collect();
+        let mut big_variant = big.clone();
+        big_variant[2 * BLOCK_SIZE as usize] ^= 1;
+
+        write(&root.join("photos/a.jpg"), &big, 300);
+        write(&root.join("backup/2020/photos/a.jpg"), &big, 100);
+        write(&root.join("backup/a-edited.jpg"), &big_variant, 200);
+        write(&root.join("notes.txt"), b"hello", 50);
+        write(&root.join("old/notes.txt"), b"hello", 500);
+        write(&root.join("other.txt"), b"world", 10);
+        write(&root.join("empty1"), b"", 10);
+        write(&root.join("empty2"), b"", 10);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let root = Path::new("test_env_dupes_find");
+        setup(root);
+
+        let report = find_duplicates(&[root], &DupeOptions::default());
+        assert!(report.errors.is_empty());
+        // 空文件默认不参与
+        assert_eq!(report.scanned, 6);
+        // other.txt 与 notes.txt 一样大，在部分哈希这一步被分开
+        assert_eq!(report.partial_hashed, 6);
+        assert_eq!(report.full_hashed, 3);
+        assert_eq!(report.groups.len(), 2);
+
+        let big = &report.groups[0];
+        assert_eq!(big.size, 5 * BLOCK_SIZE);
+        assert_eq!(
+            big.files.iter().map(|f| &f.path).collect::<Vec<_>>(),
+            [
+                &root.join("backup/2020/photos/a.jpg"),
+                &root.join("photos/a.jpg")
+            ]
+        );
+        assert_eq!(report.wasted(), 5 * BLOCK_SIZE + 5);
+
+        let small = &report.groups[1];
+        assert_eq!(
+            small.files[small.keeper(KeepPolicy::Oldest)].path,
+            root.join("old/notes.txt")
+        );
+        assert_eq!(
+            small.files[small.keeper(KeepPolicy::Newest)].path,
+            root.join("notes.txt")
+        );
+        assert_eq!(
+            small.files[small.keeper(KeepPolicy::ShortestPath)].path,
+            root.join("notes.txt")
+        );
+
+        // 同一个根目录给两次，不会把文件和它自己当成重复
+        let report = find_duplicates(&[root, root], &DupeOptions::default());
+        assert_eq!(report.groups.len(), 2);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_same_file_is_never_deleted() {
+        let root = Path::new("test_env_dupes_same_file");
+        setup(root);
+
+        // 同一个文件的两种写法，比如重叠的根目录在不支持 inode 的平台上扫出来的结果
+        let modified = SystemTime::UNIX_EPOCH;
+        let group = DuplicateGroup {
+            size: 5,
+            files: vec![
+                FileEntry {
+                    path: root.join("notes.txt"),
+                    modified,
+                },
+                FileEntry {
+                    path: root.join("old/../notes.txt"),
+                    modified,
+                },
+            ],
+        };
+        for action in [DupeAction::Delete, DupeAction::Hardlink] {
+            let report = apply(
+                std::slice::from_ref(&group),
+                action,
+                KeepPolicy::ShortestPath,
+                false,
+            );
+            assert!(report.applied.is_empty() && report.errors.is_empty());
+            assert_eq!(report.reclaimed, 0);
+        }
+        assert_eq!(fs::read(root.join("notes.txt")).unwrap(), b"hello");
+
+        let overlapping = find_duplicates(&[root, &root.join("old")], &DupeOptions::default());
+        assert_eq!(overlapping.scanned, 6);
+        assert_eq!(overlapping.groups.len(), 2);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_delete_and_hardlink() {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = Path::new("test_env_dupes_apply");
+        setup(root);
+        let report = find_duplicates(&[root], &DupeOptions::default());
+
+        let dry = apply(&report.groups, DupeAction::Delete, KeepPolicy::Oldest, true);
+        assert_eq!(dry.applied.len(), 2);
+        assert_eq!(dry.reclaimed, report.wasted());
+        assert!(root.join("notes.txt").exists());
+
+        let linked = apply(
+            &report.groups,
+            DupeAction::Hardlink,
+            KeepPolicy::ShortestPath,
+            false,
+        );
+        assert!(linked.errors.is_empty());
+        let ino = |p: &str| fs::metadata(root.join(p)).unwrap().ino();
+        assert_eq!(ino("photos/a.jpg"), ino("backup/2020/photos/a.jpg"));
+        assert_eq!(ino("notes.txt"), ino("old/notes.txt"));
+        // 已经是硬链接了，再扫一遍没有重复
+        assert!(find_duplicates(&[root], &DupeOptions::default())
+            .groups
+            .is_empty());
+
+        // 扫描之后被改过的副本不会被删
+        setup(root);
+        let report = find_duplicates(&[root], &DupeOptions::default());
+        fs::write(root.join("notes.txt"), b"HELLO").unwrap();
+        let deleted = apply(
+            &report.groups,
+            DupeAction::Delete,
+            KeepPolicy::Oldest,
+            false,
+        );
+        assert_eq!(deleted.applied.len(), 1);
+        assert_eq!(deleted.errors.len(), 1);
+        assert_eq!(deleted.errors[0].path, Some(root.join("notes.txt")));
+        assert!(root.join("notes.txt").exists());
+        assert!(!root.join("backup/2020/photos/a.jpg").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,33 @@
+//! 各个工具共用的文件系统错误记录
+
+use serde::{Serialize, Serializer};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 处理某个路径时遇到的一个错误。`io::Error` 既不能 Clone 也不能序列化，这里只留下种类和描述
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FsError {
+    /// 出错的路径；遍历错误拿不到路径时为 `None`
+    pub path: Option<PathBuf>,
+    #[serde(serialize_with = "serialize_error_kind")]
+    pub kind: io::ErrorKind,
+    pub message: String,
+}
+
+impl FsError {
+    pub fn new(path: Option<&Path>, error: &io::Error) -> Self {
+        Self {
+            path: path.map(Path::to_path_buf),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// `io::ErrorKind` 没有实现 `Serialize`，用 Debug 形式（如 `PermissionDenied`）代替
+fn serialize_error_kind<S: Serializer>(
+    kind: &io::ErrorKind,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:?}", kind))
+}
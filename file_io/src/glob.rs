@@ -0,0 +1,208 @@
+//! 简单的 glob 通配符匹配
+//!
+//! 支持的语法：
+//! - `*`：任意个字符，但不跨越 `/`
+//! - `**`：任意个字符，可以跨越 `/`（例如 `**/target`、`a/**/b`）
+//! - `?`：恰好一个字符（不匹配 `/`）
+//! - `[abc]`、`[a-z]`、`[!a-z]`：字符集合及其取反
+//! - `\` 转义下一个字符
+//!
+//! 匹配的对象是整个字符串，不是子串；路径分隔符统一使用 `/`。
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `**`
+    GlobStar,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// `[` 没有对应的 `]`
+    UnclosedClass,
+    /// 模式以单独的 `\` 结尾
+    TrailingEscape,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::UnclosedClass => f.write_str("字符集合缺少结尾的 `]`"),
+            PatternError::TrailingEscape => f.write_str("模式以单独的转义符 `\\` 结尾"),
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` 也能匹配零层目录，例如 `**/a` 匹配 `a`
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        tokens.push(Token::GlobStar);
+                        tokens.push(Token::Literal('/'));
+                        continue;
+                    }
+                    Token::GlobStar
+                }
+                '*' => Token::Star,
+                '?' => Token::Any,
+                '\\' => Token::Literal(chars.next().ok_or(PatternError::TrailingEscape)?),
+                '[' => {
+                    let negated = matches!(chars.peek(), Some('!' | '^'));
+                    if negated {
+                        chars.next();
+                    }
+                    let mut ranges = Vec::new();
+                    let mut first = true;
+                    loop {
+                        let c = chars.next().ok_or(PatternError::UnclosedClass)?;
+                        // 紧跟在 `[` 之后的 `]` 当作普通字符
+                        if c == ']' && !first {
+                            break;
+                        }
+                        first = false;
+                        let start = if c == '\\' {
+                            chars.next().ok_or(PatternError::UnclosedClass)?
+                        } else {
+                            c
+                        };
+                        let mut lookahead = chars.clone();
+                        match (lookahead.next(), lookahead.next()) {
+                            (Some('-'), Some(end)) if end != ']' => {
+                                chars.next();
+                                chars.next();
+                                ranges.push((start, end));
+                            }
+                            _ => ranges.push((start, start)),
+                        }
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self {
+            source: pattern.to_string(),
+            tokens,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        match_tokens(&self.tokens, &text)
+    }
+}
+
+/// 回溯匹配。`GlobStar` 的 `**/` 形式在解析时已经拆成 `GlobStar` + `/`，
+/// 这里额外允许它连同后面的 `/` 一起匹配空串
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+    match token {
+        Token::Literal(c) => text.first() == Some(c) && match_tokens(rest, &text[1..]),
+        Token::Any => text.first().is_some_and(|&c| c != '/') && match_tokens(rest, &text[1..]),
+        Token::Class { negated, ranges } => {
+            text.first().is_some_and(|&c| {
+                c != '/' && ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }) && match_tokens(rest, &text[1..])
+        }
+        Token::Star => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| match_tokens(rest, &text[i..])),
+        Token::GlobStar => {
+            if let [Token::Literal('/'), after @ ..] = rest {
+                if match_tokens(after, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|i| match_tokens(rest, &text[i..]))
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Pattern::new(pattern).unwrap().matches(text)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*.log", "debug.log"));
+        assert!(!matches("*.log", "logs/debug.log"));
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(!matches("file?.txt", "file10.txt"));
+        assert!(matches("node_modules", "node_modules"));
+        assert!(!matches("node_modules", "node_modules2"));
+    }
+
+    #[test]
+    fn test_globstar() {
+        assert!(matches("**/target", "target"));
+        assert!(matches("**/target", "a/b/target"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(matches("build/**", "build/x/y"));
+        assert!(!matches("a/**/b", "ab"));
+    }
+
+    #[test]
+    fn test_classes_and_escapes() {
+        assert!(matches("[abc].rs", "b.rs"));
+        assert!(matches("[a-c]x", "cx"));
+        assert!(!matches("[!a-c]x", "cx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches(r"\*", "*"));
+        assert!(!matches(r"\*", "a"));
+        assert_eq!(Pattern::new("[ab"), Err(PatternError::UnclosedClass));
+        assert_eq!(Pattern::new("a\\"), Err(PatternError::TrailingEscape));
+    }
+}
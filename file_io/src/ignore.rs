@@ -0,0 +1,201 @@
+//! `.gitignore` / `.ignore` 规则
+//!
+//! 支持 gitignore 的常用子集：
+//! - 空行和 `#` 开头的行被忽略，行尾空格被去掉；
+//! - `!` 开头表示取反，重新包含之前被忽略的路径；
+//! - `/` 结尾只匹配目录；
+//! - 模式里（去掉结尾的 `/` 之后）含有 `/` 时相对规则文件所在目录匹配，否则匹配任意层级的名字；
+//! - 通配符语法见 [`crate::glob`]，`\` 可以转义开头的 `#` 和 `!`。
+//!
+//! 同一个文件里后面的规则优先；深层目录里的规则文件优先于浅层的。
+//! 和 git 一样，目录被忽略后其中的内容不会再被访问，也就无法被子规则重新包含。
+
+use crate::glob::Pattern;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 每个目录里按这个顺序读取的规则文件，后读的优先
+pub const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// 一个目录里的全部规则
+#[derive(Debug, Clone)]
+pub struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    /// 解析规则文本。`base` 是规则文件所在的目录；写错的模式按 git 的做法直接跳过
+    pub fn parse(base: &Path, text: &str) -> Self {
+        let rules = text.lines().filter_map(parse_rule).collect();
+        Self {
+            base: base.to_path_buf(),
+            rules,
+        }
+    }
+
+    /// 读取 `dir` 下的 [`IGNORE_FILE_NAMES`]，都不存在或都没有规则时返回 `None`
+    pub fn load(dir: &Path) -> io::Result<Option<Self>> {
+        let mut file = Self::parse(dir, "");
+        for name in IGNORE_FILE_NAMES {
+            match fs::read_to_string(dir.join(name)) {
+                Ok(text) => file.rules.extend(text.lines().filter_map(parse_rule)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((!file.rules.is_empty()).then_some(file))
+    }
+
+    /// `Some(true)` 表示被忽略，`Some(false)` 表示被 `!` 规则重新包含，`None` 表示没有规则命中
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let name = relative.file_name()?.to_string_lossy();
+        let mut relative_str = None;
+        self.rules.iter().rev().find_map(|rule| {
+            if rule.dir_only && !is_dir {
+                return None;
+            }
+            let matched = if rule.anchored {
+                let relative = relative_str.get_or_insert_with(|| slash_path(relative));
+                rule.pattern.matches(relative)
+            } else {
+                rule.pattern.matches(&name)
+            };
+            matched.then_some(!rule.negated)
+        })
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end_matches([' ', '\r']);
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    let pattern = Pattern::new(line).ok()?;
+    Some(Rule {
+        pattern,
+        negated,
+        dir_only,
+        anchored,
+    })
+}
+
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 从当前目录一直到遍历根目录的规则链，子目录共享父目录的那一段
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreStack {
+    top: Option<Arc<Level>>,
+}
+
+#[derive(Debug)]
+struct Level {
+    file: IgnoreFile,
+    parent: Option<Arc<Level>>,
+}
+
+impl IgnoreStack {
+    /// 进入 `dir`：它有规则文件时压入一层
+    pub fn enter(&self, dir: &Path) -> io::Result<Self> {
+        Ok(match IgnoreFile::load(dir)? {
+            Some(file) => Self {
+                top: Some(Arc::new(Level {
+                    file,
+                    parent: self.top.clone(),
+                })),
+            },
+            None => self.clone(),
+        })
+    }
+
+    /// 从最深的一层往上找，第一个命中的规则说了算
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut level = self.top.as_deref();
+        while let Some(current) = level {
+            if let Some(ignored) = current.file.matched(path, is_dir) {
+                return ignored;
+            }
+            level = current.parent.as_deref();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let file = IgnoreFile::parse(
+            Path::new("repo"),
+            "# 注释\n\
+             \n\
+             target/\n\
+             *.log\n\
+             !keep.log\n\
+             /build\n\
+             docs/*.html\n\
+             \\#literal\n",
+        );
+        let ignored = |path: &str, is_dir| file.matched(Path::new(path), is_dir);
+        assert_eq!(ignored("repo/target", true), Some(true));
+        assert_eq!(ignored("repo/a/target", true), Some(true));
+        // 只匹配目录
+        assert_eq!(ignored("repo/target", false), None);
+        assert_eq!(ignored("repo/a/debug.log", false), Some(true));
+        assert_eq!(ignored("repo/a/keep.log", false), Some(false));
+        // 以 / 开头的只匹配规则文件所在目录下的
+        assert_eq!(ignored("repo/build", true), Some(true));
+        assert_eq!(ignored("repo/src/build", true), None);
+        assert_eq!(ignored("repo/docs/index.html", false), Some(true));
+        assert_eq!(ignored("repo/docs/api/index.html", false), None);
+        assert_eq!(ignored("repo/#literal", false), Some(true));
+        // 不在规则文件所在目录之下
+        assert_eq!(ignored("other/debug.log", false), None);
+    }
+
+    #[test]
+    fn test_stack_precedence() {
+        let root = IgnoreFile::parse(Path::new("r"), "*.tmp\n");
+        let sub = IgnoreFile::parse(Path::new("r/sub"), "!*.tmp\n");
+        let stack = IgnoreStack {
+            top: Some(Arc::new(Level {
+                file: sub,
+                parent: Some(Arc::new(Level {
+                    file: root,
+                    parent: None,
+                })),
+            })),
+        };
+        assert!(stack.is_ignored(Path::new("r/a.tmp"), false));
+        assert!(!stack.is_ignored(Path::new("r/sub/a.tmp"), false));
+        assert!(!stack.is_ignored(Path::new("r/a.txt"), false));
+    }
+}
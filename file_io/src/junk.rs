@@ -0,0 +1,138 @@
+//! "垃圾文件"规则：只含这类文件的目录在清理时视同空目录
+//!
+//! 一个普通文件满足以下条件时算作垃圾：
+//! - 名字匹配任一 glob 模式（如 `.DS_Store`、`Thumbs.db`），或开启了 `zero_size` 且文件大小为 0；
+//! - 并且设置了 `min_age` 时，最后修改时间早于 `min_age` 之前（避免误删正在写入的新文件）。
+//!
+//! 目录和符号链接永远不算垃圾。
+
+use crate::glob::Pattern;
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// macOS、Windows 资源管理器等自动生成的常见文件
+pub const COMMON_JUNK_NAMES: &[&str] = &[".DS_Store", "._*", "Thumbs.db", "desktop.ini"];
+
+#[derive(Debug, Clone, Default)]
+pub struct JunkRules {
+    /// 匹配文件名的 glob 模式
+    pub names: Vec<Pattern>,
+    /// 大小为 0 的文件算作垃圾
+    pub zero_size: bool,
+    /// 只有修改时间早于这么久之前的文件才算垃圾
+    pub min_age: Option<Duration>,
+}
+
+impl JunkRules {
+    /// 只包含 [`COMMON_JUNK_NAMES`] 的规则
+    pub fn common() -> Self {
+        Self {
+            names: COMMON_JUNK_NAMES
+                .iter()
+                .map(|name| Pattern::new(name).expect("内置模式合法"))
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// 没有任何规则时，清理行为与只删真正的空目录完全相同
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && !self.zero_size
+    }
+
+    pub fn is_junk(&self, entry: &fs::DirEntry) -> io::Result<bool> {
+        if self.is_empty() || !entry.file_type()?.is_file() {
+            return Ok(false);
+        }
+        let name = entry.file_name();
+        let name_matches = self
+            .names
+            .iter()
+            .any(|pattern| pattern.matches(&name.to_string_lossy()));
+        // 只按名字判断且不看年龄时，不必 stat
+        if !name_matches && !self.zero_size {
+            return Ok(false);
+        }
+        if name_matches && self.min_age.is_none() {
+            return Ok(true);
+        }
+
+        let metadata = entry.metadata()?;
+        if !name_matches && metadata.len() != 0 {
+            return Ok(false);
+        }
+        match self.min_age {
+            None => Ok(true),
+            // 修改时间在未来（时钟回拨）的文件当作新文件
+            Some(min_age) => Ok(SystemTime::now()
+                .duration_since(metadata.modified()?)
+                .is_ok_and(|age| age >= min_age)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn entry(dir: &Path, name: &str) -> fs::DirEntry {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|e| e.file_name() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        let dir = Path::new("test_env_junk_rules");
+        if dir.exists() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        File::create(dir.join(".DS_Store"))
+            .unwrap()
+            .write_all(b"x")
+            .unwrap();
+        File::create(dir.join("empty.txt")).unwrap();
+        File::create(dir.join("data.txt"))
+            .unwrap()
+            .write_all(b"data")
+            .unwrap();
+
+        let rules = JunkRules::common();
+        assert!(rules.is_junk(&entry(dir, ".DS_Store")).unwrap());
+        assert!(!rules.is_junk(&entry(dir, "empty.txt")).unwrap());
+        assert!(!JunkRules::default()
+            .is_junk(&entry(dir, ".DS_Store"))
+            .unwrap());
+
+        let rules = JunkRules {
+            zero_size: true,
+            ..JunkRules::common()
+        };
+        assert!(rules.is_junk(&entry(dir, "empty.txt")).unwrap());
+        assert!(!rules.is_junk(&entry(dir, "data.txt")).unwrap());
+        assert!(!rules.is_junk(&entry(dir, "sub")).unwrap());
+
+        // 刚创建的文件还不够"老"；把修改时间拨回两小时前就够了
+        let rules = JunkRules {
+            min_age: Some(Duration::from_secs(3600)),
+            ..rules
+        };
+        assert!(!rules.is_junk(&entry(dir, "empty.txt")).unwrap());
+        File::options()
+            .write(true)
+            .open(dir.join("empty.txt"))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(7200))
+            .unwrap();
+        assert!(rules.is_junk(&entry(dir, "empty.txt")).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
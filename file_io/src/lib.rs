@@ -0,0 +1,12 @@
+pub mod cleaner;
+pub mod cli;
+pub mod dupes;
+pub mod error;
+pub mod glob;
+pub mod ignore;
+pub mod junk;
+pub mod report;
+pub mod trash;
+pub mod undo;
+pub mod usage;
+pub mod walk;
@@ -0,0 +1,183 @@
+//! 清理结果的结构化记录
+//!
+//! [`CleanReport`] 记下一次清理里每个被删除、被跳过的路径和每个错误，库的调用方可以逐条检查，
+//! 也可以用 [`CleanReport::to_json`] 序列化后留档审计。
+//! 计数形式的 [`CleanSummary`] 由报告推导出来，不再单独维护。
+
+use crate::error::FsError;
+use crate::undo::UndoEntry;
+use serde::Serialize;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+
+/// 一个目录被保留下来的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// 用户指定的根目录，且没有开启 `include_root`
+    Root,
+    /// 符号链接本身不会被删除
+    Symlink,
+    /// 目录里还有（非垃圾）内容
+    NotEmpty,
+    /// 命中了 exclude 规则，整棵子树都没有被访问
+    Excluded { pattern: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+/// 给人看的大小，1024 进制：1536 -> "1.5 KiB"
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// 一次清理的完整记录。dry-run 时 `deleted` 和 `junk_files` 是"将会删除"的路径
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CleanReport {
+    pub root: PathBuf,
+    pub dry_run: bool,
+    /// 访问到的目录数（不含被排除的）
+    pub scanned: usize,
+    /// 按删除顺序排列，子目录总在父目录之前
+    pub deleted: Vec<PathBuf>,
+    /// 随目录一起删除的垃圾文件
+    pub junk_files: Vec<PathBuf>,
+    pub skipped: Vec<Skipped>,
+    pub errors: Vec<FsError>,
+    /// 真正删除或移走的每个条目，用来生成撤销清单；dry-run 时为空
+    pub undo: Vec<UndoEntry>,
+}
+
+impl CleanReport {
+    pub fn new(root: &Path, dry_run: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dry_run,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn summary(&self) -> CleanSummary {
+        let excluded = self
+            .skipped
+            .iter()
+            .filter(|s| matches!(s.reason, SkipReason::Excluded { .. }))
+            .count();
+        CleanSummary {
+            scanned: self.scanned,
+            deleted: self.deleted.len(),
+            kept: self.skipped.len() - excluded,
+            excluded,
+            junk_files: self.junk_files.len(),
+            errors: self.errors.len(),
+        }
+    }
+
+    /// 带缩进的 JSON。路径不是合法 UTF-8 时会失败
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// 一次或多次清理的计数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct CleanSummary {
+    /// 访问到的目录数（不含被排除的）
+    pub scanned: usize,
+    /// 删除（dry-run 时为将会删除）的目录数
+    pub deleted: usize,
+    /// 因为非空、是符号链接或是根目录而保留的目录数
+    pub kept: usize,
+    /// 被 exclude 规则跳过的条目数
+    pub excluded: usize,
+    /// 随目录一起删除（dry-run 时为将会删除）的垃圾文件数
+    pub junk_files: usize,
+    pub errors: usize,
+}
+
+impl AddAssign for CleanSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.scanned += other.scanned;
+        self.deleted += other.deleted;
+        self.kept += other.kept;
+        self.excluded += other.excluded;
+        self.junk_files += other.junk_files;
+        self.errors += other.errors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_summary_and_json() {
+        let mut report = CleanReport::new(Path::new("root"), true);
+        report.scanned = 3;
+        report.deleted.push(PathBuf::from("root/a"));
+        report.skipped.push(Skipped {
+            path: PathBuf::from("root"),
+            reason: SkipReason::Root,
+        });
+        report.skipped.push(Skipped {
+            path: PathBuf::from("root/.git"),
+            reason: SkipReason::Excluded {
+                pattern: ".git".to_string(),
+            },
+        });
+        report.errors.push(FsError::new(
+            Some(Path::new("root/locked")),
+            &io::Error::from(io::ErrorKind::PermissionDenied),
+        ));
+
+        let summary = report.summary();
+        assert_eq!(
+            (
+                summary.deleted,
+                summary.kept,
+                summary.excluded,
+                summary.errors
+            ),
+            (1, 1, 1, 1)
+        );
+        assert!(!report.is_ok());
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["dry_run"], true);
+        assert_eq!(json["deleted"][0], "root/a");
+        assert_eq!(json["skipped"][0]["reason"], "root");
+        assert_eq!(json["skipped"][1]["reason"], "excluded");
+        assert_eq!(json["skipped"][1]["pattern"], ".git");
+        assert_eq!(json["errors"][0]["kind"], "PermissionDenied");
+        assert_eq!(json["errors"][0]["path"], "root/locked");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
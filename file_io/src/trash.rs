@@ -0,0 +1,372 @@
+//! 可撤销的删除：把目录和文件移进隔离目录或回收站，而不是直接删掉
+//!
+//! - [`Disposal::Quarantine`]：移进 `<隔离目录>/<本次运行>/` 下，保留原来的绝对路径结构，
+//!   例如 `/home/a/tmp/x` 会被移到 `<隔离目录>/<本次运行>/home/a/tmp/x`；
+//! - [`Disposal::Trash`]：按 freedesktop.org 回收站规范放进 `$XDG_DATA_HOME/Trash`，
+//!   `files/` 里放条目本身，`info/` 里放记录原路径和删除时间的 `.trashinfo`，
+//!   桌面环境的回收站也能看到和还原它们。
+//!
+//! 两种方式都先尝试 `rename`；跨文件系统时退化为"在目标处新建目录（或复制文件）再删除原条目"。
+//! 每次成功都返回一条 [`UndoEntry`]，交给 [`crate::undo`] 写进撤销清单。
+
+use crate::undo::{EntryKind, UndoEntry};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{self, Component, Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 清理时怎样处理要删除的目录和垃圾文件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Disposal {
+    /// 直接删除。目录可以按撤销清单重新建出来，垃圾文件则无法恢复
+    #[default]
+    Delete,
+    /// 移进这个隔离目录下以本次运行命名的子目录
+    Quarantine(PathBuf),
+    /// 移进当前用户的 freedesktop.org 回收站
+    Trash,
+}
+
+/// `$XDG_DATA_HOME/Trash`，没有设置时为 `~/.local/share/Trash`
+///
+/// 规范里其他挂载点上的文件应该放进该挂载点的 `.Trash-$uid`，这里统一放进主目录的回收站，
+/// 跨文件系统时需要复制
+pub fn home_trash_dir() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_home.join("Trash"))
+}
+
+/// 一次清理中真正执行删除（或移走）的对象
+#[derive(Debug)]
+pub(crate) enum Disposer {
+    Delete,
+    Quarantine { run_dir: PathBuf },
+    Trash { files: PathBuf, info: PathBuf },
+}
+
+impl Disposer {
+    /// 只确定目标位置，不创建任何目录：什么都没删的运行不会留下空的隔离目录
+    pub(crate) fn new(disposal: &Disposal) -> io::Result<Self> {
+        Ok(match disposal {
+            Disposal::Delete => Disposer::Delete,
+            Disposal::Quarantine(dir) => {
+                let dir = path::absolute(dir)?;
+                let base = format!("{}-{}", unix_now(), process::id());
+                let mut run_dir = dir.join(&base);
+                let mut n = 2;
+                while run_dir.exists() {
+                    run_dir = dir.join(format!("{}-{}", base, n));
+                    n += 1;
+                }
+                Disposer::Quarantine { run_dir }
+            }
+            Disposal::Trash => {
+                let trash = home_trash_dir().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "找不到回收站：HOME 没有设置")
+                })?;
+                Disposer::Trash {
+                    files: trash.join("files"),
+                    info: trash.join("info"),
+                }
+            }
+        })
+    }
+
+    pub(crate) fn is_delete(&self) -> bool {
+        matches!(self, Disposer::Delete)
+    }
+
+    /// 删除（或移走）一个空目录。目录非空时返回 `Ok(None)`，什么都不动
+    pub(crate) fn dir(&self, path: &Path) -> io::Result<Option<UndoEntry>> {
+        let original = path::absolute(path)?;
+        match self {
+            Disposer::Delete => {
+                Ok(remove_dir(path)?.then(|| UndoEntry::new(EntryKind::Dir, original, None, None)))
+            }
+            Disposer::Quarantine { run_dir } => {
+                let stored = stash_path(run_dir, &original);
+                fs::create_dir_all(stored.parent().expect("位于隔离目录之下"))?;
+                // 子目录先一步被移了进来，目录结构已经在了，原目录删掉即可
+                let moved = if stored.is_dir() {
+                    remove_dir(path)?
+                } else {
+                    move_dir(path, &stored)?
+                };
+                Ok(moved.then(|| UndoEntry::new(EntryKind::Dir, original, Some(stored), None)))
+            }
+            Disposer::Trash { files, info } => {
+                let (stored, info) = reserve_trash_name(files, info, &original)?;
+                match move_dir(path, &stored) {
+                    Ok(true) => Ok(Some(UndoEntry::new(
+                        EntryKind::Dir,
+                        original,
+                        Some(stored),
+                        Some(info),
+                    ))),
+                    result => {
+                        let _ = fs::remove_file(&info);
+                        result.map(|_| None)
+                    }
+                }
+            }
+        }
+    }
+
+    /// 删除（或移走）一个文件
+    pub(crate) fn file(&self, path: &Path) -> io::Result<UndoEntry> {
+        let original = path::absolute(path)?;
+        match self {
+            Disposer::Delete => {
+                fs::remove_file(path)?;
+                Ok(UndoEntry::new(EntryKind::File, original, None, None))
+            }
+            Disposer::Quarantine { run_dir } => {
+                let stored = stash_path(run_dir, &original);
+                fs::create_dir_all(stored.parent().expect("位于隔离目录之下"))?;
+                move_file(path, &stored)?;
+                Ok(UndoEntry::new(
+                    EntryKind::File,
+                    original,
+                    Some(stored),
+                    None,
+                ))
+            }
+            Disposer::Trash { files, info } => {
+                let (stored, info) = reserve_trash_name(files, info, &original)?;
+                move_file(path, &stored).inspect_err(|_| {
+                    let _ = fs::remove_file(&info);
+                })?;
+                Ok(UndoEntry::new(
+                    EntryKind::File,
+                    original,
+                    Some(stored),
+                    Some(info),
+                ))
+            }
+        }
+    }
+}
+
+/// 删除空目录，返回是否删除；非空不算错误
+pub(crate) fn remove_dir(path: &Path) -> io::Result<bool> {
+    match fs::remove_dir(path) {
+        Ok(()) => Ok(true),
+        // 忽略"非空"错误，这是预期行为
+        Err(e) if is_dir_not_empty_error(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// 辅助函数：判断错误是否为"文件夹非空"
+fn is_dir_not_empty_error(e: &io::Error) -> bool {
+    // Windows: 145, Unix-like: DirectoryNotEmpty
+    e.kind() == io::ErrorKind::DirectoryNotEmpty || (cfg!(windows) && e.raw_os_error() == Some(145))
+}
+
+/// 把空目录移到 `to`（`to` 必须还不存在），返回是否移走；目录非空时什么都不动
+///
+/// 不用 rename：它会连同内容一起搬走非空目录，先检查是否为空再 rename
+/// 又挡不住两步之间新建的文件。空目录没有内容要搬，在目标处新建一个，
+/// 再用 `remove_dir` 删掉原目录——目录是否为空由文件系统在删除时原子地判断。
+/// 代价是原目录的权限和时间戳不会跟过去
+fn move_dir(from: &Path, to: &Path) -> io::Result<bool> {
+    fs::create_dir(to)?;
+    let removed = remove_dir(from).inspect_err(|_| {
+        let _ = fs::remove_dir(to);
+    })?;
+    if !removed {
+        fs::remove_dir(to)?;
+    }
+    Ok(removed)
+}
+
+/// 把文件移到 `to`；跨文件系统时复制后删除原文件
+pub(crate) fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from).inspect_err(|_| {
+                let _ = fs::remove_file(to);
+            })
+        }
+        result => result,
+    }
+}
+
+/// 隔离目录里对应 `original`（绝对路径）的位置。Windows 的盘符变成一层普通目录
+fn stash_path(run_dir: &Path, original: &Path) -> PathBuf {
+    let mut stored = run_dir.to_path_buf();
+    for component in original.components() {
+        match component {
+            Component::Prefix(prefix) => stored.push(
+                prefix
+                    .as_os_str()
+                    .to_string_lossy()
+                    .replace([':', '\\'], ""),
+            ),
+            Component::Normal(name) => stored.push(name),
+            Component::RootDir | Component::CurDir | Component::ParentDir => {}
+        }
+    }
+    stored
+}
+
+/// 在回收站里占一个不重名的位置，并写好对应的 `.trashinfo`
+///
+/// 规范要求先用"不存在才创建"的方式建 info 文件来占住名字，这样多个程序同时往回收站里放东西也不会冲突
+fn reserve_trash_name(
+    files: &Path,
+    info: &Path,
+    original: &Path,
+) -> io::Result<(PathBuf, PathBuf)> {
+    fs::create_dir_all(files)?;
+    fs::create_dir_all(info)?;
+    let name = original
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string());
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode_path(original),
+        format_deletion_date(unix_now())
+    );
+
+    for n in 1.. {
+        let candidate = if n == 1 {
+            name.clone()
+        } else {
+            format!("{}.{}", name, n)
+        };
+        let stored = files.join(&candidate);
+        let info_path = info.join(format!("{}.trashinfo", candidate));
+        if stored.symlink_metadata().is_ok() {
+            continue;
+        }
+        match File::options()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes()).inspect_err(|_| {
+                    let _ = fs::remove_file(&info_path);
+                })?;
+                return Ok((stored, info_path));
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("1.. 是无限序列")
+}
+
+/// `.trashinfo` 里的 `Path=` 是 URL 编码的：除了非保留字符和 `/` 以外的字节都写成 `%XX`
+fn percent_encode_path(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().replace('\\', "/").into_bytes();
+
+    let mut encoded = String::with_capacity(bytes.len());
+    for b in bytes {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// 规范要求本地时间 `YYYY-MM-DDThh:mm:ss`；标准库拿不到时区，这里用的是 UTC
+fn format_deletion_date(unix_secs: u64) -> String {
+    let days = (unix_secs / 86400) as i64;
+    let secs = unix_secs % 86400;
+    // 公历日期换算（Howard Hinnant 的 civil_from_days）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trashinfo_fields() {
+        assert_eq!(format_deletion_date(0), "1970-01-01T00:00:00");
+        assert_eq!(format_deletion_date(951_782_400), "2000-02-29T00:00:00");
+        assert_eq!(format_deletion_date(1_700_000_000), "2023-11-14T22:13:20");
+        assert_eq!(
+            percent_encode_path(Path::new("/home/a/my dir/100%.txt")),
+            "/home/a/my%20dir/100%25.txt"
+        );
+        assert_eq!(
+            stash_path(Path::new("/q/run"), Path::new("/home/a/x")),
+            Path::new("/q/run/home/a/x")
+        );
+    }
+
+    #[test]
+    fn test_trash_layout() {
+        let root = Path::new("test_env_trash");
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        fs::create_dir_all(root.join("src/x")).unwrap();
+        fs::create_dir_all(root.join("other/x")).unwrap();
+        let trash = path::absolute(root.join("Trash")).unwrap();
+        let disposer = Disposer::Trash {
+            files: trash.join("files"),
+            info: trash.join("info"),
+        };
+
+        let first = disposer.dir(&root.join("src/x")).unwrap().unwrap();
+        // 重名的条目换一个名字，不会覆盖
+        let second = disposer.dir(&root.join("other/x")).unwrap().unwrap();
+        assert_eq!(first.stored, Some(trash.join("files/x")));
+        assert_eq!(second.stored, Some(trash.join("files/x.2")));
+        assert!(trash.join("files/x.2").is_dir());
+        assert!(!root.join("other/x").exists());
+
+        let info = fs::read_to_string(second.trash_info.unwrap()).unwrap();
+        let original = path::absolute(root.join("other/x")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath="));
+        assert!(info.contains(&format!("Path={}\n", percent_encode_path(&original))));
+        assert!(info.contains("DeletionDate="));
+
+        // 非空目录原样保留，也不留下 .trashinfo
+        File::create(root.join("src/file")).unwrap();
+        assert_eq!(disposer.dir(&root.join("src")).unwrap(), None);
+        assert_eq!(fs::read_dir(trash.join("info")).unwrap().count(), 2);
+        assert!(!trash.join("files/src").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+//! 撤销清单：记下一次清理移走或删除了什么，以便之后原样恢复
+//!
+//! 清单里的条目按删除顺序排列（子目录在父目录之前），[`restore`] 倒序重放：
+//! 先恢复父目录，再恢复其中的内容。
+//! - 移进隔离目录或回收站的条目移回原处；
+//! - 直接删除的目录重新建一个空目录；
+//! - 直接删除的文件无法恢复，只在结果里列出来。
+//!
+//! 原位置已经有同名文件时不会覆盖，记为冲突。
+
+use crate::error::FsError;
+use crate::trash::{move_file, unix_now};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Dir,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub kind: EntryKind,
+    /// 删除前的绝对路径
+    pub original: PathBuf,
+    /// 移到了哪里；直接删除时为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored: Option<PathBuf>,
+    /// 放进回收站时对应的 `.trashinfo`，恢复后一并删除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_info: Option<PathBuf>,
+}
+
+impl UndoEntry {
+    pub fn new(
+        kind: EntryKind,
+        original: PathBuf,
+        stored: Option<PathBuf>,
+        trash_info: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            kind,
+            original,
+            stored,
+            trash_info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoManifest {
+    pub version: u32,
+    /// 写入时间，Unix 时间戳（秒）
+    pub created: u64,
+    pub entries: Vec<UndoEntry>,
+}
+
+impl UndoManifest {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(entries: Vec<UndoEntry>) -> Self {
+        Self {
+            version: Self::VERSION,
+            created: unix_now(),
+            entries,
+        }
+    }
+
+    /// 先写临时文件再改名，中途失败不会留下半个清单
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let manifest: Self = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if manifest.version != Self::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("不支持的撤销清单版本 {}", manifest.version),
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RestoreReport {
+    /// 恢复（或重新创建）的路径，父目录在前
+    pub restored: Vec<PathBuf>,
+    /// 直接删除的文件，没有副本可以恢复
+    pub unrecoverable: Vec<PathBuf>,
+    /// 原位置已经被占用，没有覆盖
+    pub conflicts: Vec<PathBuf>,
+    pub errors: Vec<FsError>,
+}
+
+impl RestoreReport {
+    pub fn is_complete(&self) -> bool {
+        self.unrecoverable.is_empty() && self.conflicts.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// 按清单倒序恢复。单个条目失败不影响其余条目
+pub fn restore(manifest: &UndoManifest) -> RestoreReport {
+    let mut report = RestoreReport::default();
+    // 跨文件系统恢复目录时只是新建，原来那份要等里面的东西都搬走之后再删
+    let mut leftovers = Vec::new();
+
+    for entry in manifest.entries.iter().rev() {
+        match restore_entry(entry, &mut leftovers) {
+            Ok(Restored::Yes) => {
+                if let Some(info) = &entry.trash_info {
+                    let _ = fs::remove_file(info);
+                }
+                report.restored.push(entry.original.clone());
+            }
+            Ok(Restored::Unrecoverable) => report.unrecoverable.push(entry.original.clone()),
+            Ok(Restored::Conflict) => report.conflicts.push(entry.original.clone()),
+            Err(e) => report.errors.push(FsError::new(Some(&entry.original), &e)),
+        }
+    }
+    for dir in leftovers.iter().rev() {
+        let _ = fs::remove_dir(dir);
+    }
+    report
+}
+
+enum Restored {
+    Yes,
+    Unrecoverable,
+    Conflict,
+}
+
+fn restore_entry(entry: &UndoEntry, leftovers: &mut Vec<PathBuf>) -> io::Result<Restored> {
+    let original = &entry.original;
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let stored = entry
+        .stored
+        .as_deref()
+        .filter(|p| p.symlink_metadata().is_ok());
+
+    match entry.kind {
+        // 父目录先恢复时会把其中的子目录一起带回来，这时子目录已经在了
+        EntryKind::Dir if original.is_dir() => {
+            if let Some(stored) = stored {
+                leftovers.push(stored.to_path_buf());
+            }
+            Ok(Restored::Yes)
+        }
+        EntryKind::Dir => {
+            match stored {
+                Some(stored) => {
+                    if let Err(e) = fs::rename(stored, original) {
+                        if e.kind() != io::ErrorKind::CrossesDevices {
+                            return Err(e);
+                        }
+                        fs::create_dir(original)?;
+                        leftovers.push(stored.to_path_buf());
+                    }
+                }
+                None => fs::create_dir(original)?,
+            }
+            Ok(Restored::Yes)
+        }
+        EntryKind::File => {
+            let occupied = original.symlink_metadata().is_ok();
+            match (stored, &entry.stored) {
+                (Some(_), _) if occupied => Ok(Restored::Conflict),
+                (Some(stored), _) => {
+                    move_file(stored, original)?;
+                    Ok(Restored::Yes)
+                }
+                // 和父目录一起被带回来了
+                (None, Some(_)) if occupied => Ok(Restored::Yes),
+                // 清单里有副本的位置，但副本已经不见了
+                (None, Some(missing)) => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("副本 {} 不存在", missing.display()),
+                )),
+                (None, None) => Ok(Restored::Unrecoverable),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleaner::{clean_empty_directories, CleanOptions};
+    use crate::junk::JunkRules;
+    use crate::trash::Disposal;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path;
+
+    fn setup(root: &Path) {
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("keep")).unwrap();
+        File::create(root.join("a/b/.DS_Store"))
+            .unwrap()
+            .write_all(b"junk")
+            .unwrap();
+        File::create(root.join("keep/data.txt"))
+            .unwrap()
+            .write_all(b"data")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_quarantine_round_trip() {
+        let root = Path::new("test_env_quarantine");
+        let quarantine = Path::new("test_env_quarantine_store");
+        setup(root);
+        if quarantine.exists() {
+            fs::remove_dir_all(quarantine).unwrap();
+        }
+
+        let options = CleanOptions {
+            junk: JunkRules::common(),
+            disposal: Disposal::Quarantine(quarantine.to_path_buf()),
+            ..CleanOptions::default()
+        };
+        let report = clean_empty_directories(root, &options).unwrap();
+        assert_eq!(report.deleted.len(), 3);
+        assert!(!root.join("a").exists());
+        // 原来的绝对路径结构保留在隔离目录里
+        let stored_junk = report
+            .undo
+            .iter()
+            .find(|e| e.kind == EntryKind::File)
+            .and_then(|e| e.stored.clone())
+            .unwrap();
+        assert!(stored_junk.ends_with("test_env_quarantine/a/b/.DS_Store"));
+        assert!(stored_junk.starts_with(path::absolute(quarantine).unwrap()));
+        assert!(stored_junk.exists());
+
+        let manifest_path = quarantine.join("undo.json");
+        UndoManifest::new(report.undo).save(&manifest_path).unwrap();
+        let manifest = UndoManifest::load(&manifest_path).unwrap();
+        let restored = restore(&manifest);
+        assert!(restored.is_complete(), "{:?}", restored);
+        assert_eq!(restored.restored.len(), 4);
+        assert!(root.join("a/b/c").is_dir());
+        assert_eq!(fs::read(root.join("a/b/.DS_Store")).unwrap(), b"junk");
+        assert!(!stored_junk.exists());
+
+        // 再恢复一次什么都不用做
+        assert!(restore(&manifest).is_complete());
+
+        // 隔离之后原位置又出现了同名文件：不覆盖，其余照常恢复
+        let report = clean_empty_directories(root, &options).unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/.DS_Store"), b"new").unwrap();
+        let restored = restore(&UndoManifest::new(report.undo));
+        assert_eq!(
+            restored.conflicts,
+            [path::absolute(root.join("a/b/.DS_Store")).unwrap()]
+        );
+        assert!(root.join("a/b/c").is_dir());
+        assert_eq!(fs::read(root.join("a/b/.DS_Store")).unwrap(), b"new");
+
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(quarantine).unwrap();
+    }
+
+    #[test]
+    fn test_restore_after_plain_delete() {
+        let root = Path::new("test_env_undo_delete");
+        setup(root);
+
+        let options = CleanOptions {
+            junk: JunkRules::common(),
+            ..CleanOptions::default()
+        };
+        let report = clean_empty_directories(root, &options).unwrap();
+        assert_eq!(report.undo.len(), 4);
+
+        let restored = restore(&UndoManifest::new(report.undo));
+        // 目录都重新建了出来，直接删掉的垃圾文件找不回来
+        assert!(root.join("a/b/c").is_dir());
+        assert_eq!(restored.restored.len(), 3);
+        assert_eq!(restored.unrecoverable.len(), 1);
+        assert!(!restored.is_complete());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+//! 统计目录树的磁盘占用（类似 `du`）
+//!
+//! 每个目录记两种大小：
+//! - 表观大小（apparent）：文件长度之和，即 `ls -l` 看到的大小；
+//! - 占用大小（allocated）：实际分配的块数 × 512，稀疏文件更小，小文件通常更大。
+//!   非 unix 平台拿不到块数，两者相同。
+//!
+//! 目录本身的大小也计入。同一个 inode 的多个硬链接只按路径顺序计第一个；
+//! 默认不进入其他文件系统（挂载点），与 `du -x` 相同。
+//!
+//! 遍历和 stat 在 [`Walker`] 的多个线程里进行，汇总在当前线程里按路径排序后完成，
+//! 所以结果（包括硬链接算在哪个路径上）与线程数无关。
+
+use crate::error::FsError;
+use crate::walk::{Entry, Walker};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::io;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct UsageOptions {
+    pub follow_symlinks: bool,
+    /// 进入其他文件系统上的目录。默认不进入
+    pub cross_file_systems: bool,
+    /// 遍历线程数，0 表示使用 CPU 核数
+    pub threads: usize,
+    /// [`UsageReport::largest_files`] 保留多少个文件
+    pub largest_files: usize,
+    /// 按哪种大小挑选最大的文件
+    pub size_kind: SizeKind,
+}
+
+impl Default for UsageOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: false,
+            cross_file_systems: false,
+            threads: 0,
+            largest_files: 10,
+            size_kind: SizeKind::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeKind {
+    /// 实际占用的磁盘空间
+    #[default]
+    Allocated,
+    /// 文件长度
+    Apparent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Usage {
+    pub apparent: u64,
+    pub allocated: u64,
+}
+
+impl Usage {
+    fn of(metadata: &Metadata) -> Self {
+        Self {
+            apparent: metadata.len(),
+            allocated: allocated_size(metadata),
+        }
+    }
+
+    pub fn get(&self, kind: SizeKind) -> u64 {
+        match kind {
+            SizeKind::Allocated => self.allocated,
+            SizeKind::Apparent => self.apparent,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+    }
+}
+
+/// 一个目录连同整棵子树的占用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirUsage {
+    pub path: PathBuf,
+    /// 根目录为 0
+    pub depth: usize,
+    pub usage: Usage,
+    /// 子树里的文件数（不含目录，重复的硬链接不计）
+    pub files: u64,
+    /// 子树里的目录数，不含自己
+    pub dirs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileUsage {
+    pub path: PathBuf,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub root: PathBuf,
+    /// 按路径排序，祖先在前；第一个是根目录
+    pub directories: Vec<DirUsage>,
+    /// 最大的若干个文件，从大到小
+    pub largest_files: Vec<FileUsage>,
+    /// 因为是已经计过的硬链接而没有计入的路径数
+    pub hard_links: u64,
+    pub errors: Vec<FsError>,
+}
+
+impl UsageReport {
+    /// 根目录的总占用
+    pub fn total(&self) -> Usage {
+        self.directories
+            .first()
+            .map_or_else(Usage::default, |d| d.usage)
+    }
+
+    /// 除根目录外最大的 `n` 个目录，从大到小；一样大时浅的在前
+    pub fn largest_dirs(&self, n: usize, kind: SizeKind) -> Vec<&DirUsage> {
+        let mut dirs: Vec<_> = self.directories.iter().skip(1).collect();
+        dirs.sort_by(|a, b| {
+            b.usage
+                .get(kind)
+                .cmp(&a.usage.get(kind))
+                .then(a.path.cmp(&b.path))
+        });
+        dirs.truncate(n);
+        dirs
+    }
+}
+
+/// 遍历线程里收集的一条记录，汇总时再按路径排序
+struct Record {
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+    usage: Usage,
+    /// 有多个硬链接的文件的 (设备, inode)
+    link_id: Option<(u64, u64)>,
+}
+
+/// 统计 `root` 下的磁盘占用
+///
+/// 只有根目录本身无法访问或不是目录时返回 `Err`；其余错误记进报告，对应的条目不计入
+pub fn disk_usage(root: &Path, options: &UsageOptions) -> io::Result<UsageReport> {
+    if !fs::metadata(root)?.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} 不是目录", root.display()),
+        ));
+    }
+
+    let records = Mutex::new(Vec::new());
+    let errors = Mutex::new(Vec::new());
+    Walker::new(root)
+        .threads(options.threads)
+        .follow_links(options.follow_symlinks)
+        .same_file_system(!options.cross_file_systems)
+        .run(|result| {
+            let record = result
+                .map_err(|e| FsError::new(Some(e.path()), e.io_error()))
+                .and_then(|entry| record(entry).map_err(|(path, e)| FsError::new(Some(&path), &e)));
+            match record {
+                Ok(record) => records.lock().unwrap().push(record),
+                Err(error) => errors.lock().unwrap().push(error),
+            }
+        });
+    let mut records = records.into_inner().unwrap();
+    let mut errors = errors.into_inner().unwrap();
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut directories = Vec::new();
+    let mut index = HashMap::new();
+    for record in records.iter().filter(|r| r.is_dir) {
+        index.insert(record.path.as_path(), directories.len());
+        directories.push(DirUsage {
+            path: record.path.clone(),
+            depth: record.depth,
+            usage: Usage::default(),
+            files: 0,
+            dirs: 0,
+        });
+    }
+
+    let mut seen_links = HashSet::new();
+    let mut hard_links = 0;
+    let mut files = Vec::new();
+    for record in &records {
+        if record.link_id.is_some_and(|id| !seen_links.insert(id)) {
+            hard_links += 1;
+            continue;
+        }
+        // 自己（是目录时）和每一层祖先目录都计入
+        let mut ancestor = if record.is_dir {
+            Some(record.path.as_path())
+        } else {
+            record.path.parent()
+        };
+        while let Some(&i) = ancestor.and_then(|dir| index.get(dir)) {
+            let dir = &mut directories[i];
+            dir.usage += record.usage;
+            if !record.is_dir {
+                dir.files += 1;
+            } else if dir.path != record.path {
+                dir.dirs += 1;
+            }
+            ancestor = dir.path.parent();
+        }
+        if !record.is_dir {
+            files.push(FileUsage {
+                path: record.path.clone(),
+                usage: record.usage,
+            });
+        }
+    }
+
+    let kind = options.size_kind;
+    files.sort_by(|a, b| {
+        b.usage
+            .get(kind)
+            .cmp(&a.usage.get(kind))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    files.truncate(options.largest_files);
+
+    Ok(UsageReport {
+        root: root.to_path_buf(),
+        directories,
+        largest_files: files,
+        hard_links,
+        errors,
+    })
+}
+
+fn record(entry: Entry) -> Result<Record, (PathBuf, io::Error)> {
+    let metadata = match entry.metadata() {
+        Ok(metadata) => metadata,
+        Err(e) => return Err((entry.into_path(), e)),
+    };
+    Ok(Record {
+        depth: entry.depth(),
+        is_dir: entry.is_dir(),
+        usage: Usage::of(&metadata),
+        link_id: link_id(&metadata),
+        path: entry.into_path(),
+    })
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks 的单位固定是 512 字节，与文件系统的块大小无关
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(unix)]
+fn link_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (!metadata.is_dir() && metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn link_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_usage() {
+        let root = Path::new("test_env_usage");
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("a/b/big.bin"), vec![1u8; 10_000]).unwrap();
+        fs::write(root.join("a/small.txt"), b"hello").unwrap();
+        fs::write(root.join("c/mid.txt"), vec![2u8; 3_000]).unwrap();
+        // 硬链接只计一次
+        fs::hard_link(root.join("c/mid.txt"), root.join("c/mid-link.txt")).unwrap();
+
+        let options = UsageOptions {
+            threads: 3,
+            largest_files: 2,
+            size_kind: SizeKind::Apparent,
+            ..UsageOptions::default()
+        };
+        let report = disk_usage(root, &options).unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(report.hard_links, 1);
+
+        let paths: Vec<_> = report.directories.iter().map(|d| &d.path).collect();
+        assert_eq!(
+            paths,
+            [root, &root.join("a"), &root.join("a/b"), &root.join("c")]
+        );
+        let dir_size = |i: usize| fs::metadata(paths[i]).unwrap().len();
+        let total = report.total();
+        assert_eq!(
+            total.apparent,
+            10_000 + 5 + 3_000 + (0..4).map(dir_size).sum::<u64>()
+        );
+        assert_eq!(
+            (report.directories[0].files, report.directories[0].dirs),
+            (3, 3)
+        );
+        assert_eq!(report.directories[1].files, 2);
+        #[cfg(unix)]
+        assert!(total.allocated >= 10_000 + 3_000);
+
+        let largest: Vec<_> = report
+            .largest_dirs(2, SizeKind::Apparent)
+            .into_iter()
+            .map(|d| d.path.clone())
+            .collect();
+        assert_eq!(largest, [root.join("a"), root.join("a/b")]);
+        assert_eq!(report.largest_files.len(), 2);
+        assert_eq!(report.largest_files[0].path, root.join("a/b/big.bin"));
+        assert_eq!(report.largest_files[1].path, root.join("c/mid-link.txt"));
+
+        // 结果与线程数无关
+        let single = disk_usage(
+            root,
+            &UsageOptions {
+                threads: 1,
+                ..options
+            },
+        )
+        .unwrap();
+        assert_eq!(single.directories, report.directories);
+
+        assert!(disk_usage(&root.join("a/small.txt"), &UsageOptions::default()).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,790 @@
+//! 多线程目录遍历
+//!
+//! 所有线程共享一个目录队列：每个线程取出一个目录，读出其中的条目，子目录再放回队列，
+//! 谁空闲谁接着处理，宽而浅的树和窄而深的树都能把线程用满。
+//!
+//! - 条目类型直接取自 `fs::DirEntry::file_type`，在 Linux、macOS、Windows 上都不需要额外 stat；
+//!   只有跟随符号链接时才对链接做一次 stat；
+//! - 可以读取每个目录里的 `.gitignore` / `.ignore`（见 [`crate::ignore`]），被忽略的目录整个跳过；
+//! - `filter_entry` 返回 `false` 的条目被跳过，是目录时整棵子树都不会被访问；
+//! - `contents_first` 模式保证目录总在它的全部内容之后产出，适合"先子后父"地删除。
+//!
+//! [`Walker::run`] 在各个线程里直接回调，除了 `contents_first` 的保证之外顺序不确定；
+//! [`Walker::into_vec`] 收集全部结果后按 [`Sort`] 排成确定的顺序。
+
+use crate::ignore::IgnoreStack;
+use std::cmp::Ordering;
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, FileType, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+
+/// 遍历到的一个条目
+#[derive(Debug, Clone)]
+pub struct Entry {
+    path: PathBuf,
+    depth: usize,
+    /// 跟随符号链接时是链接目标的类型
+    file_type: FileType,
+    follow_link: bool,
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// 根目录没有名字时返回整个路径
+    pub fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// 根目录为 0
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+
+    /// 条目本身是一个被跟随了的符号链接
+    pub fn path_is_symlink(&self) -> bool {
+        self.follow_link || self.file_type.is_symlink()
+    }
+
+    /// 跟随了链接的条目返回目标的元数据，否则返回条目本身的
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        if self.follow_link {
+            fs::metadata(&self.path)
+        } else {
+            fs::symlink_metadata(&self.path)
+        }
+    }
+}
+
+/// 遍历中的一个错误，总是带着出错的路径
+#[derive(Debug)]
+pub struct Error {
+    path: PathBuf,
+    depth: usize,
+    source: io::Error,
+}
+
+impl Error {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn io_error(&self) -> &io::Error {
+        &self.source
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.source)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        io::Error::new(e.source.kind(), e)
+    }
+}
+
+pub type WalkResult = Result<Entry, Error>;
+
+/// [`Walker::into_vec`] 的排序方式。同一目录下的条目按名字排序，父子之间由 `contents_first` 决定先后
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sort {
+    #[default]
+    Name,
+    /// 同一目录下先目录后文件，各自按名字排序
+    DirsFirst,
+    /// 保持各线程产出的顺序，不排序
+    Unsorted,
+}
+
+type Filter<'a> = Box<dyn Fn(&Entry) -> bool + Sync + 'a>;
+
+pub struct Walker<'a> {
+    root: PathBuf,
+    threads: usize,
+    max_depth: usize,
+    follow_links: bool,
+    same_file_system: bool,
+    ignore_files: bool,
+    contents_first: bool,
+    sort: Sort,
+    filter: Option<Filter<'a>>,
+}
+
+impl fmt::Debug for Walker<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Walker")
+            .field("root", &self.root)
+            .field("threads", &self.threads)
+            .field("max_depth", &self.max_depth)
+            .field("follow_links", &self.follow_links)
+            .field("same_file_system", &self.same_file_system)
+            .field("ignore_files", &self.ignore_files)
+            .field("contents_first", &self.contents_first)
+            .field("sort", &self.sort)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            threads: 0,
+            max_depth: usize::MAX,
+            follow_links: false,
+            same_file_system: false,
+            ignore_files: false,
+            contents_first: false,
+            sort: Sort::default(),
+            filter: None,
+        }
+    }
+
+    /// 线程数，0（默认）表示使用 CPU 核数
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// 最大深度，根目录为 0。更深的条目不会被产出，深度等于它的目录不会被读取
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// 跟随符号链接进入目录。指回祖先目录的链接报告为错误，不会无限循环
+    pub fn follow_links(mut self, yes: bool) -> Self {
+        self.follow_links = yes;
+        self
+    }
+
+    /// 不进入与根目录不在同一个文件系统上的目录（挂载点本身也不产出）。只在 unix 上生效
+    pub fn same_file_system(mut self, yes: bool) -> Self {
+        self.same_file_system = yes;
+        self
+    }
+
+    /// 遵守每个目录里的 `.gitignore` 和 `.ignore`
+    pub fn ignore_files(mut self, yes: bool) -> Self {
+        self.ignore_files = yes;
+        self
+    }
+
+    /// 目录在它的全部内容之后产出
+    pub fn contents_first(mut self, yes: bool) -> Self {
+        self.contents_first = yes;
+        self
+    }
+
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// 返回 `false` 的条目被跳过；是目录时不会被读取。根目录不经过过滤。
+    /// 会在多个线程里同时被调用
+    pub fn filter_entry<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Entry) -> bool + Sync + 'a,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// 遍历全部条目并按 [`Sort`] 排好序
+    pub fn into_vec(self) -> Vec<WalkResult> {
+        let sort = self.sort;
+        let contents_first = self.contents_first;
+        let results = Mutex::new(Vec::new());
+        self.run(|result| results.lock().unwrap().push(result));
+        let mut results = results.into_inner().unwrap();
+        if sort != Sort::Unsorted {
+            // 同一路径的多个结果（读目录出错和目录本身）出自同一个线程，稳定排序保留它们的先后
+            results.sort_by(|a, b| compare(a, b, sort, contents_first));
+        }
+        results
+    }
+
+    /// 在多个线程里对每个条目和错误调用 `visit`，全部处理完才返回。
+    /// `visit` 或 `filter_entry` panic 时其他线程尽快停下，panic 传给调用方
+    pub fn run<F>(self, visit: F)
+    where
+        F: Fn(WalkResult) + Sync,
+    {
+        let root = match root_entry(&self.root) {
+            Ok(root) => root,
+            Err(source) => {
+                return visit(Err(Error {
+                    path: self.root.clone(),
+                    depth: 0,
+                    source,
+                }))
+            }
+        };
+        if !root.is_dir() || self.max_depth == 0 {
+            return visit(Ok(root));
+        }
+
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let shared = Shared {
+            walker: &self,
+            visit: &visit,
+            root_device: if self.same_file_system {
+                device(&root.path)
+            } else {
+                None
+            },
+            queue: Mutex::new(QueueState {
+                jobs: Vec::new(),
+                active: 0,
+                aborted: false,
+            }),
+            ready: Condvar::new(),
+        };
+        let root_id = if self.follow_links {
+            dir_id(&root.path)
+        } else {
+            None
+        };
+        shared.queue.lock().unwrap().jobs.push(Job {
+            entry: root,
+            id: root_id,
+            parent: None,
+            ignore: IgnoreStack::default(),
+        });
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| shared.work());
+            }
+        });
+    }
+}
+
+/// 错误的路径通常是读不了的目录或成环的链接，排序时按目录对待
+fn sort_key(result: &WalkResult) -> (&Path, bool) {
+    match result {
+        Ok(entry) => (&entry.path, entry.is_dir()),
+        Err(e) => (&e.path, true),
+    }
+}
+
+fn root_entry(root: &Path) -> io::Result<Entry> {
+    let link = fs::symlink_metadata(root)?;
+    // 和 walkdir 一样，根目录本身是链接时总是跟随
+    let (file_type, follow_link) = if link.file_type().is_symlink() {
+        (fs::metadata(root)?.file_type(), true)
+    } else {
+        (link.file_type(), false)
+    };
+    Ok(Entry {
+        path: root.to_path_buf(),
+        depth: 0,
+        file_type,
+        follow_link,
+    })
+}
+
+/// 排序规则：在第一个不同的路径分量处比较（`DirsFirst` 时先比是否为目录，再比名字）；
+/// 一个是另一个的祖先时，`contents_first` 下祖先在后，否则在前
+fn compare(a: &WalkResult, b: &WalkResult, sort: Sort, contents_first: bool) -> Ordering {
+    let ((a_path, a_dir), (b_path, b_dir)) = (sort_key(a), sort_key(b));
+    let mut a_iter = a_path.components();
+    let mut b_iter = b_path.components();
+    loop {
+        match (a_iter.next(), b_iter.next()) {
+            (Some(x), Some(y)) if x == y => continue,
+            (Some(x), Some(y)) => {
+                if sort == Sort::DirsFirst {
+                    // 后面还有分量说明这一层是目录
+                    let x_dir = a_dir || a_iter.next().is_some();
+                    let y_dir = b_dir || b_iter.next().is_some();
+                    if x_dir != y_dir {
+                        return y_dir.cmp(&x_dir);
+                    }
+                }
+                return x.as_os_str().cmp(y.as_os_str());
+            }
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) if contents_first => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) if contents_first => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+}
+
+struct Job {
+    entry: Entry,
+    /// 跟随链接时用来检测环
+    id: Option<(u64, u64)>,
+    parent: Option<Arc<DirNode>>,
+    ignore: IgnoreStack,
+}
+
+/// 读过的目录。`pending` 是还没处理完的子目录数，降到 0 时目录本身处理完毕
+struct DirNode {
+    entry: Entry,
+    id: Option<(u64, u64)>,
+    pending: AtomicUsize,
+    parent: Option<Arc<DirNode>>,
+}
+
+struct QueueState {
+    jobs: Vec<Job>,
+    /// 正在处理目录的线程数。队列空了且没有线程在干活时遍历结束
+    active: usize,
+    /// 某个线程在 `filter_entry` 或 `visit` 里 panic 了，其他线程做完手头的目录就退出
+    aborted: bool,
+}
+
+/// 处理一个目录期间持有，结束时把 `active` 减一。放在 drop 里是为了 panic 时也能执行：
+/// 否则其他线程会一直等在 `ready` 上，panic 永远传不到调用方
+struct ActiveGuard<'q> {
+    queue: &'q Mutex<QueueState>,
+    ready: &'q Condvar,
+}
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.active -= 1;
+        if thread::panicking() {
+            queue.aborted = true;
+            self.ready.notify_all();
+        } else if queue.active == 0 && queue.jobs.is_empty() {
+            self.ready.notify_all();
+        }
+    }
+}
+
+struct Shared<'w, 'a, F> {
+    walker: &'w Walker<'a>,
+    visit: &'w F,
+    /// 开启 `same_file_system` 时根目录所在的设备
+    root_device: Option<u64>,
+    queue: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl<F: Fn(WalkResult) + Sync> Shared<'_, '_, F> {
+    fn work(&self) {
+        while let Some(job) = self.next_job() {
+            let _active = ActiveGuard {
+                queue: &self.queue,
+                ready: &self.ready,
+            };
+            self.read_dir(job);
+        }
+    }
+
+    fn next_job(&self) -> Option<Job> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if queue.aborted {
+                return None;
+            }
+            // 后进先出，接近深度优先，队列不会因为宽目录而膨胀
+            if let Some(job) = queue.jobs.pop() {
+                queue.active += 1;
+                return Some(job);
+            }
+            if queue.active == 0 {
+                return None;
+            }
+            queue = self.ready.wait(queue).unwrap();
+        }
+    }
+
+    fn error(&self, path: PathBuf, depth: usize, source: io::Error) {
+        (self.visit)(Err(Error {
+            path,
+            depth,
+            source,
+        }));
+    }
+
+    fn read_dir(&self, job: Job) {
+        let walker = self.walker;
+        let Job {
+            entry,
+            id,
+            parent,
+            ignore,
+        } = job;
+        if !walker.contents_first {
+            (self.visit)(Ok(entry.clone()));
+        }
+        let node = Arc::new(DirNode {
+            entry,
+            id,
+            pending: AtomicUsize::new(0),
+            parent,
+        });
+        let dir = &node.entry;
+        let depth = dir.depth + 1;
+
+        let ignore = if walker.ignore_files {
+            ignore.enter(&dir.path).unwrap_or_else(|e| {
+                self.error(dir.path.clone(), dir.depth, e);
+                ignore
+            })
+        } else {
+            ignore
+        };
+
+        let mut subdirs = Vec::new();
+        match fs::read_dir(&dir.path) {
+            Ok(read_dir) => {
+                for child in read_dir {
+                    match self.child_entry(child, dir, depth) {
+                        Ok(Some(child)) => {
+                            if walker.ignore_files && ignore.is_ignored(&child.path, child.is_dir())
+                            {
+                                continue;
+                            }
+                            if walker.filter.as_ref().is_some_and(|keep| !keep(&child)) {
+                                continue;
+                            }
+                            if child.is_dir() && self.other_file_system(&child) {
+                                continue;
+                            }
+                            if child.is_dir() && depth < walker.max_depth {
+                                match self.check_loop(&child, &node) {
+                                    Ok(id) => subdirs.push((child, id)),
+                                    Err(e) => self.error(child.path, depth, e),
+                                }
+                            } else {
+                                (self.visit)(Ok(child));
+                            }
+                        }
+                        Ok(None) => {}
+                        Err((path, e)) => self.error(path, depth, e),
+                    }
+                }
+            }
+            Err(e) => self.error(dir.path.clone(), dir.depth, e),
+        }
+
+        if subdirs.is_empty() {
+            self.finish(node);
+            return;
+        }
+        node.pending.store(subdirs.len(), atomic::Ordering::Relaxed);
+        let mut queue = self.queue.lock().unwrap();
+        for (entry, id) in subdirs {
+            queue.jobs.push(Job {
+                entry,
+                id,
+                parent: Some(Arc::clone(&node)),
+                ignore: ignore.clone(),
+            });
+        }
+        self.ready.notify_all();
+    }
+
+    /// 读出一个子条目的类型；读目录项本身失败时没有子路径可用，报告在父目录上
+    fn child_entry(
+        &self,
+        child: io::Result<fs::DirEntry>,
+        dir: &Entry,
+        depth: usize,
+    ) -> Result<Option<Entry>, (PathBuf, io::Error)> {
+        let child = child.map_err(|e| (dir.path.clone(), e))?;
+        let path = child.path();
+        let file_type = match child.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => return Err((path, e)),
+        };
+        let (file_type, follow_link) = if file_type.is_symlink() && self.walker.follow_links {
+            match fs::metadata(&path) {
+                Ok(metadata) => (metadata.file_type(), true),
+                // 悬空链接：作为链接本身产出
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (file_type, false),
+                Err(e) => return Err((path, e)),
+            }
+        } else {
+            (file_type, false)
+        };
+        Ok(Some(Entry {
+            path,
+            depth,
+            file_type,
+            follow_link,
+        }))
+    }
+
+    fn other_file_system(&self, dir: &Entry) -> bool {
+        match self.root_device {
+            Some(root) => device(&dir.path).is_some_and(|dev| dev != root),
+            None => false,
+        }
+    }
+
+    /// 跟随链接时，子目录与任何一个祖先是同一个目录就成环了
+    fn check_loop(&self, child: &Entry, parent: &Arc<DirNode>) -> io::Result<Option<(u64, u64)>> {
+        if !self.walker.follow_links {
+            return Ok(None);
+        }
+        let id = dir_id(&child.path);
+        if child.follow_link && id.is_some() {
+            let mut ancestor = Some(parent);
+            while let Some(node) = ancestor {
+                if node.id == id {
+                    return Err(io::Error::other(format!(
+                        "符号链接成环：指向祖先目录 {}",
+                        node.entry.path.display()
+                    )));
+                }
+                ancestor = node.parent.as_ref();
+            }
+        }
+        Ok(id)
+    }
+
+    /// 目录及其全部子目录都处理完了：contents_first 时产出它，再看父目录是不是也完成了
+    fn finish(&self, node: Arc<DirNode>) {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if self.walker.contents_first {
+                (self.visit)(Ok(node.entry.clone()));
+            }
+            current = node
+                .parent
+                .as_ref()
+                .filter(|parent| parent.pending.fetch_sub(1, atomic::Ordering::AcqRel) == 1)
+                .cloned();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn setup(root: &Path) {
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        for dir in ["a/b/c", "a/d", "e", "target/debug", "src/gen"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a/b/c/1.txt",
+            "a/2.log",
+            "a/keep.log",
+            "z.txt",
+            "target/debug/app",
+        ] {
+            fs::write(root.join(file), b"x").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(root.join("src/.ignore"), "gen\n").unwrap();
+    }
+
+    fn relative(results: Vec<WalkResult>, root: &Path) -> Vec<String> {
+        results
+            .into_iter()
+            .map(|r| {
+                let path = r.unwrap().into_path();
+                let relative = path.strip_prefix(root).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sorted_orders() {
+        let root = Path::new("test_env_walk_order");
+        setup(root);
+
+        let pre = relative(Walker::new(root).threads(4).into_vec(), root);
+        assert_eq!(pre.len(), 17);
+        assert_eq!(pre[0], "");
+        assert_eq!(&pre[1..6], [".gitignore", "a", "a/2.log", "a/b", "a/b/c"]);
+        assert_eq!(pre.last().unwrap(), "z.txt");
+
+        let post = relative(Walker::new(root).contents_first(true).into_vec(), root);
+        assert_eq!(&post[1..5], ["a/2.log", "a/b/c/1.txt", "a/b/c", "a/b"]);
+        assert_eq!(post.last().unwrap(), "");
+
+        let dirs_first = relative(
+            Walker::new(root)
+                .sort(Sort::DirsFirst)
+                .max_depth(1)
+                .into_vec(),
+            root,
+        );
+        assert_eq!(
+            dirs_first,
+            ["", "a", "e", "src", "target", ".gitignore", "z.txt"]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_contents_first_streaming_and_filters() {
+        let root = Path::new("test_env_walk_stream");
+        setup(root);
+
+        // 并行回调：每个目录产出时，它的所有内容都已经产出过了
+        let seen = Mutex::new(Vec::new());
+        Walker::new(root)
+            .threads(4)
+            .contents_first(true)
+            .sort(Sort::Unsorted)
+            .run(|r| seen.lock().unwrap().push(r.unwrap().into_path()));
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(seen.len(), 17);
+        for (i, path) in seen.iter().enumerate() {
+            assert!(seen[i + 1..]
+                .iter()
+                .all(|later| !later.starts_with(path) || later == path));
+        }
+
+        let ignored = relative(Walker::new(root).ignore_files(true).into_vec(), root);
+        let ignored: HashSet<_> = ignored.iter().map(String::as_str).collect();
+        assert!(ignored.contains("a/keep.log"));
+        assert!(ignored.contains("src/.ignore"));
+        for gone in ["target", "target/debug/app", "a/2.log", "src/gen"] {
+            assert!(!ignored.contains(gone), "{} 应当被忽略", gone);
+        }
+
+        let filtered = relative(
+            Walker::new(root)
+                .filter_entry(|e| e.file_name() != "a")
+                .into_vec(),
+            root,
+        );
+        assert!(filtered.iter().all(|p| !p.starts_with('a')));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_panic_in_callback_propagates() {
+        let root = Path::new("test_env_walk_panic");
+        setup(root);
+
+        // 在子线程里遍历，卡死时用超时把它变成测试失败
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let visit = std::panic::catch_unwind(|| {
+                Walker::new(root).threads(4).run(|r| {
+                    if r.unwrap().file_name() == "1.txt" {
+                        panic!("visit 出错");
+                    }
+                })
+            });
+            let filter = std::panic::catch_unwind(|| {
+                Walker::new(root)
+                    .threads(4)
+                    .filter_entry(|e| e.file_name() != "c" || panic!("filter 出错"))
+                    .run(|_| {})
+            });
+            tx.send((visit.is_err(), filter.is_err())).unwrap();
+        });
+        let result = rx.recv_timeout(std::time::Duration::from_secs(10));
+        assert_eq!(result, Ok((true, true)), "panic 没有传到调用方");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loop_and_errors() {
+        let root = Path::new("test_env_walk_loop");
+        if root.exists() {
+            fs::remove_dir_all(root).unwrap();
+        }
+        fs::create_dir_all(root.join("a")).unwrap();
+        std::os::unix::fs::symlink("..", root.join("a/up")).unwrap();
+
+        let results = Walker::new(root).follow_links(true).into_vec();
+        let errors: Vec<_> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path(), root.join("a/up"));
+        assert_eq!(results.len(), 3);
+
+        // 不跟随时链接只是一个普通条目
+        let results = Walker::new(root).into_vec();
+        assert!(results.iter().all(Result::is_ok));
+        let link = results
+            .iter()
+            .flatten()
+            .find(|e| e.path().ends_with("up"))
+            .unwrap();
+        assert!(link.path_is_symlink() && !link.is_dir());
+
+        let missing = Walker::new(root.join("missing")).into_vec();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            missing[0].as_ref().unwrap_err().io_error().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}